@@ -0,0 +1,192 @@
+//! Content-hash based invalidation of cached build steps.
+//!
+//! The generator stores a key (a hash of all inputs of a step)
+//! in a file next to the step's output. If the key calculated
+//! on the next run is the same, the output is reused.
+
+extern crate clang;
+
+use config::Config;
+use cpp_compiler_builder;
+use dependency_info::DependencyInfo;
+use errors::{Result, ChainErr};
+use file_utils::{PathBufWithAdded, open_file, create_file, file_to_string, read_dir, remove_file,
+                 canonicalize, path_to_str};
+use launcher::BuildProfile;
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// Incremental 64-bit FNV-1a hash. Unlike hashers from `std`,
+/// its value doesn't depend on the compiler version.
+#[derive(Debug, Clone)]
+pub struct ContentHash {
+  state: u64,
+  visited_dirs: HashSet<PathBuf>,
+}
+
+impl ContentHash {
+  pub fn new() -> ContentHash {
+    ContentHash {
+      state: FNV_OFFSET_BASIS,
+      visited_dirs: HashSet::new(),
+    }
+  }
+
+  fn add_bytes(&mut self, bytes: &[u8]) {
+    for byte in bytes {
+      self.state ^= *byte as u64;
+      self.state = self.state.wrapping_mul(FNV_PRIME);
+    }
+  }
+
+  /// Adds a string to the hash. The length is hashed as well,
+  /// so that different sequences of strings produce different hashes.
+  pub fn add_str<S: AsRef<str>>(&mut self, text: S) {
+    let text = text.as_ref();
+    self.add_bytes(format!("{}:", text.len()).as_bytes());
+    self.add_bytes(text.as_bytes());
+  }
+
+  /// Adds path and content of a file.
+  pub fn add_file(&mut self, path: &Path) -> Result<()> {
+    let bytes = try!(try!(open_file(path)).read_all_bytes());
+    self.add_str(try!(path_to_str(path)));
+    self.add_bytes(format!("{}:", bytes.len()).as_bytes());
+    self.add_bytes(&bytes);
+    Ok(())
+  }
+
+  /// Adds all files in `path` and its subdirectories in sorted order,
+  /// or the file itself if `path` is a file. Directories listed in `excluded`
+  /// (as canonical paths) are skipped. Nonexistent paths are hashed as such.
+  pub fn add_path(&mut self, path: &Path, excluded: &[PathBuf]) -> Result<()> {
+    if path.is_file() {
+      return self.add_file(path);
+    }
+    if !path.is_dir() {
+      self.add_str(format!("missing: {}", path.display()));
+      return Ok(());
+    }
+    // symlinks may form cycles
+    let canonical_path = try!(canonicalize(&path.to_path_buf()));
+    if self.visited_dirs.contains(&canonical_path) || excluded.contains(&canonical_path) {
+      return Ok(());
+    }
+    self.visited_dirs.insert(canonical_path);
+    let mut items = Vec::new();
+    for item in try!(read_dir(path)) {
+      items.push(try!(item).path());
+    }
+    items.sort();
+    for item in items {
+      try!(self.add_path(&item, excluded));
+    }
+    Ok(())
+  }
+
+  /// Returns the hash as a hex string.
+  pub fn value(&self) -> String {
+    format!("{:016x}", self.state)
+  }
+}
+
+/// Calculates the key of the C++ parser's output. It depends on the content
+/// of the library's headers, clang version, parser settings and data
+/// of the dependencies. The generator's executable (usually the build script)
+/// is hashed as well because it contains `Config`'s filter functions.
+pub fn parse_key(config: &Config, dependencies: &[DependencyInfo]) -> Result<String> {
+  let mut hash = ContentHash::new();
+  hash.add_str(clang::get_version());
+  let exe_path = try!(::std::env::current_exe().chain_err(|| "failed to get executable path"));
+  try!(hash.add_file(&exe_path));
+  for dir in config.include_paths().iter().chain(config.target_include_paths()) {
+    try!(hash.add_path(dir, &[]));
+  }
+  for dir in config.framework_paths().iter().filter(|x| x.is_dir()) {
+    for item in try!(read_dir(dir)) {
+      let path = try!(item).path();
+      if path.extension().map_or(false, |x| x == "framework") {
+        try!(hash.add_path(&path.with_added("Headers"), &[]));
+      }
+    }
+  }
+  for dep in dependencies {
+    try!(hash.add_file(&dep.path.with_added("cpp_data.json")));
+  }
+  hash.add_str(format!("{:?}", config.include_directives()));
+  hash.add_str(format!("{:?}", config.cpp_parser_flags()));
+  hash.add_str(format!("{:?}", config.cpp_parser_blocked_names()));
+  hash.add_str(format!("{:?}", config.target_platforms()));
+  hash.add_str(format!("{:?}", config.filter_rules()));
+  Ok(hash.value())
+}
+
+/// Calculates the key of all generated files, including the built
+/// C wrapper library. In addition to the inputs of the parser, it depends on
+/// all `Config` settings, the crate's source directory (except `output_dir`
+/// and `target`), dependencies, enabled features, build profile, Qt doc data
+/// and environment variables selecting the build backend and the compiler.
+pub fn output_key(parse_key: &str,
+                  config: &Config,
+                  source_dir: &PathBuf,
+                  output_dir: &PathBuf,
+                  dependencies: &[DependencyInfo],
+                  cargo_features: &[String],
+                  build_profile: BuildProfile,
+                  doc_data_path: Option<&PathBuf>)
+                  -> Result<String> {
+  let mut hash = ContentHash::new();
+  hash.add_str(parse_key);
+  hash.add_str(format!("{:?}", config));
+  for path in config.cache_dependencies() {
+    try!(hash.add_path(path, &[]));
+  }
+  let excluded = vec![output_dir.clone(), source_dir.with_added("target")];
+  try!(hash.add_path(source_dir, &excluded));
+  for dep in dependencies {
+    try!(hash.add_file(&dep.path.with_added("rust_export_info.json")));
+  }
+  let mut cargo_features = Vec::from(cargo_features);
+  cargo_features.sort();
+  hash.add_str(format!("{:?}", cargo_features));
+  hash.add_str(build_profile.name());
+  hash.add_str(format!("{:?}", ::std::env::var("CPP_TO_RUST_BUILD_BACKEND").ok()));
+  hash.add_str(format!("{:?}", cpp_compiler_builder::build_env_vars()));
+  if let Some(path) = doc_data_path {
+    try!(hash.add_path(path, &[]));
+  }
+  Ok(hash.value())
+}
+
+/// Returns true if the key file at `path` contains `key`.
+pub fn is_up_to_date(path: &Path, key: &str) -> bool {
+  if !path.is_file() {
+    return false;
+  }
+  match file_to_string(path) {
+    Ok(content) => content.trim() == key,
+    Err(err) => {
+      err.discard_expected();
+      false
+    }
+  }
+}
+
+/// Writes `key` to the key file at `path`.
+pub fn save_key(path: &Path, key: &str) -> Result<()> {
+  let mut file = try!(create_file(path));
+  file.write(key)
+}
+
+/// Removes the key file at `path`, if any, so the output is regenerated
+/// if the current step is interrupted.
+pub fn remove_key(path: &Path) -> Result<()> {
+  if path.exists() {
+    try!(remove_file(path));
+  }
+  Ok(())
+}
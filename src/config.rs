@@ -3,6 +3,9 @@ use errors::Result;
 use cpp_method::CppMethod;
 use cpp_data::CppData;
 
+pub use serializable::{RenameRule, RenameTarget};
+pub use filter_rules::{FilterRule, FilterAction};
+
 pub type CppFfiGeneratorFilterFn = Fn(&CppMethod) -> Result<bool>;
 
 struct CppFfiGeneratorFilter(Box<CppFfiGeneratorFilterFn>);
@@ -23,6 +26,144 @@ impl ::std::fmt::Debug for CppDataFilter {
   }
 }
 
+/// Representation of C++ pointer arguments and return values
+/// in the generated Rust API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointerPolicy {
+  /// Pointers are exposed as `*const T` or `*mut T`.
+  RawPointer,
+  /// Pointers are exposed as `Option<&T>` or `Option<&mut T>`.
+  /// Null pointer corresponds to `None`.
+  OptionalReference,
+  /// Pointers are exposed as `&T` or `&mut T`. A null pointer
+  /// returned from C++ causes a panic.
+  NonNullReference,
+}
+
+impl Default for PointerPolicy {
+  fn default() -> PointerPolicy {
+    PointerPolicy::RawPointer
+  }
+}
+
+/// Representation of C++ enums in the generated Rust API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnumRepresentation {
+  /// Rust enum with the same `repr` as the C++ enum.
+  /// Duplicate values are removed. Integers are converted
+  /// to the enum with `TryFrom`. Values are passed through FFI
+  /// as integers, and a wrapper function panics if C++ returns
+  /// a value not declared in the enum.
+  RustEnum,
+  /// Newtype over the integer type with an associated constant
+  /// for each C++ variant. Any integer value can be represented,
+  /// so this is suitable for enums that are used as integers
+  /// or combined with bitwise operations (like `Qt::Key`).
+  Newtype,
+}
+
+impl Default for EnumRepresentation {
+  fn default() -> EnumRepresentation {
+    EnumRepresentation::RustEnum
+  }
+}
+
+/// Representation of overloaded C++ methods and methods
+/// with default arguments in the generated Rust API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverloadStrategy {
+  /// All variants are accepted by a single method taking a tuple
+  /// of arguments that implements a generated parameters trait.
+  ParametersTrait,
+  /// Each C++ overload gets a separate method. If there are several
+  /// overloads, method names get suffixes derived from argument types
+  /// (e.g. `arg_i32` and `arg_string_ref`). Arguments with default
+  /// values are exposed as `Option`s. Methods returning `CppBox`
+  /// get `_as_box` suffix.
+  SuffixedNames,
+}
+
+impl Default for OverloadStrategy {
+  fn default() -> OverloadStrategy {
+    OverloadStrategy::ParametersTrait
+  }
+}
+
+/// Placement of generated items into Rust modules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModuleLayout {
+  /// Each include file produces a module.
+  IncludeFile,
+  /// Modules mirror C++ namespaces. Items of the global namespace
+  /// are placed according to the include file, as in
+  /// `ModuleLayout::IncludeFile`.
+  Namespace,
+}
+
+impl Default for ModuleLayout {
+  fn default() -> ModuleLayout {
+    ModuleLayout::IncludeFile
+  }
+}
+
+/// Tool used to build the C wrapper library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CppBuildBackend {
+  /// The library is configured with CMake and built with the chosen generator.
+  CMake,
+  /// Sources are compiled directly with the system C++ compiler
+  /// and archived into a static library. CMake is not required.
+  /// The compiler is chosen by `CXX` environment variable (and its
+  /// target-specific variants), as Rust build scripts conventionally do.
+  Compiler,
+}
+
+impl Default for CppBuildBackend {
+  fn default() -> CppBuildBackend {
+    CppBuildBackend::CMake
+  }
+}
+
+impl CppBuildBackend {
+  /// Parses a backend name (`"cmake"` or `"compiler"`).
+  pub fn from_name(name: &str) -> Result<CppBuildBackend> {
+    match name {
+      "cmake" => Ok(CppBuildBackend::CMake),
+      "compiler" => Ok(CppBuildBackend::Compiler),
+      _ => Err(format!("unknown build backend: {}", name).into()),
+    }
+  }
+}
+
+/// Source of the lifetime of a reference returned by a method.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReturnLifetime {
+  /// The returned reference is valid while `self` is borrowed.
+  SelfArgument,
+  /// The returned reference is valid while the argument with
+  /// the specified index (0-based, not counting `self`) is borrowed.
+  Argument(usize),
+  /// The returned reference has `'static` lifetime.
+  Static,
+}
+
+/// A target platform for which C++ headers are parsed separately.
+/// Items that are only found on some of the platforms are
+/// generated with conditional compilation attributes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TargetPlatform {
+  /// Identifier of the platform, e.g. `"windows"`
+  pub name: String,
+  /// Target triple passed to the parser, e.g. `"x86_64-pc-windows-msvc"`
+  pub target_triple: String,
+  /// Macros predefined for the parser, e.g. `"_WIN32"` or `"WINVER=0x0601"`
+  pub defines: Vec<String>,
+  /// Condition of Rust `cfg` attribute, e.g. `target_os = "windows"`
+  pub rust_cfg: String,
+  /// Condition of preprocessor `#if` directive in the C++ wrapper,
+  /// e.g. `defined(_WIN32)`
+  pub cpp_condition: String,
+}
 
 /// The starting point of `cpp_to_rust` API.
 /// Create a `Config` object, set its properties,
@@ -43,6 +184,29 @@ pub struct Config {
   cpp_parser_blocked_names: Vec<String>,
   cpp_ffi_generator_filters: Vec<CppFfiGeneratorFilter>,
   cpp_data_filters: Vec<CppDataFilter>,
+  default_pointer_policy: PointerPolicy,
+  pointer_policies: Vec<(String, PointerPolicy)>,
+  unsafe_methods: Vec<String>,
+  return_lifetimes: Vec<(String, ReturnLifetime)>,
+  default_enum_representation: EnumRepresentation,
+  enum_representations: Vec<(String, EnumRepresentation)>,
+  flag_enums: Vec<String>,
+  flags_containers: Vec<String>,
+  rename_rules: Vec<RenameRule>,
+  module_layout: ModuleLayout,
+  module_mapping: Vec<(String, String)>,
+  flat_module: Option<String>,
+  overload_strategy: OverloadStrategy,
+  module_features: bool,
+  target_platforms: Vec<TargetPlatform>,
+  sys_crate: bool,
+  c_header: bool,
+  filter_rules: Vec<FilterRule>,
+  cache_dependencies: Vec<PathBuf>,
+  cmake_cache_vars: Vec<(String, String)>,
+  cmake_generator: Option<String>,
+  cpp_compiler_launcher: Option<String>,
+  cpp_build_backend: CppBuildBackend,
 }
 
 impl Config {
@@ -177,6 +341,237 @@ impl Config {
     self.cpp_data_filters.push(CppDataFilter(f));
   }
 
+  /// Sets the pointer policy used for methods that don't have
+  /// a policy set with `Config::add_pointer_policy`.
+  /// Default is `PointerPolicy::RawPointer`.
+  pub fn set_default_pointer_policy(&mut self, policy: PointerPolicy) {
+    self.default_pointer_policy = policy;
+  }
+
+  /// Sets the pointer policy for a C++ method (e.g. `"QObject::parent"`
+  /// or `"qobject_cast"`) or for all methods of a class (e.g. `"QObject"`).
+  /// The policy applies only to pointers to class types, and it
+  /// affects both arguments and return values of the methods.
+  /// A method's own policy takes priority over its class's policy.
+  pub fn add_pointer_policy<P: Into<String>>(&mut self, name: P, policy: PointerPolicy) {
+    self.pointer_policies.push((name.into(), policy));
+  }
+
+  /// Adds a C++ method (e.g. `"QObject::deleteLater"`) or a class
+  /// (e.g. `"QObject"`) whose Rust wrappers must be marked as `unsafe`
+  /// regardless of their signatures. Methods taking raw pointers
+  /// or returning non-owned pointers are marked `unsafe` automatically.
+  /// `Drop` is not implemented for a class whose destructor is unsafe.
+  pub fn add_unsafe_method<P: Into<String>>(&mut self, name: P) {
+    self.unsafe_methods.push(name.into());
+  }
+
+  /// Adds multiple unsafe methods. See `Config::add_unsafe_method`.
+  pub fn add_unsafe_methods<Item, Iter>(&mut self, items: Iter)
+    where Item: Into<String>,
+          Iter: IntoIterator<Item = Item>
+  {
+    for item in items {
+      self.unsafe_methods.push(item.into());
+    }
+  }
+
+  /// Sets the lifetime of the reference returned by a C++ method
+  /// (e.g. `"QCoreApplication::instance"`). The setting applies to all
+  /// overloads of the method.
+  ///
+  /// If the lifetime is not set, the returned reference is tied
+  /// to `self` if present, or to all reference arguments otherwise.
+  /// If the method doesn't take any references, it returns a raw pointer.
+  pub fn add_return_lifetime<P: Into<String>>(&mut self, method: P, lifetime: ReturnLifetime) {
+    self.return_lifetimes.push((method.into(), lifetime));
+  }
+
+  /// Sets the representation used for enums that don't have
+  /// a representation set with `Config::add_enum_representation`.
+  /// Default is `EnumRepresentation::RustEnum`.
+  pub fn set_default_enum_representation(&mut self, representation: EnumRepresentation) {
+    self.default_enum_representation = representation;
+  }
+
+  /// Sets the representation of a C++ enum (e.g. `"Qt::Key"`).
+  pub fn add_enum_representation<P: Into<String>>(&mut self,
+                                                  name: P,
+                                                  representation: EnumRepresentation) {
+    self.enum_representations.push((name.into(), representation));
+  }
+
+  /// Declares a C++ enum (e.g. `"Qt::AlignmentFlag"`) as a set of flags.
+  /// The generated crate will allow combining values of this enum
+  /// into a `flags::Flags` value. Enums used as template arguments
+  /// of flags containers (see `Config::add_flags_container`)
+  /// are detected automatically.
+  pub fn add_flag_enum<P: Into<String>>(&mut self, name: P) {
+    self.flag_enums.push(name.into());
+  }
+
+  /// Adds multiple flag enums. See `Config::add_flag_enum`.
+  pub fn add_flag_enums<Item, Iter>(&mut self, items: Iter)
+    where Item: Into<String>,
+          Iter: IntoIterator<Item = Item>
+  {
+    for item in items {
+      self.flag_enums.push(item.into());
+    }
+  }
+
+  /// Declares a C++ class template (e.g. `"QFlags"`) as a container
+  /// of flags. The template must take one enum argument and must be
+  /// convertible to and constructible from `unsigned int`.
+  /// Values of such types are passed through FFI as integers and
+  /// represented as `flags::Flags` in the generated crate.
+  pub fn add_flags_container<P: Into<String>>(&mut self, name: P) {
+    self.flags_containers.push(name.into());
+  }
+
+  /// Adds multiple flags containers. See `Config::add_flags_container`.
+  pub fn add_flags_containers<Item, Iter>(&mut self, items: Iter)
+    where Item: Into<String>,
+          Iter: IntoIterator<Item = Item>
+  {
+    for item in items {
+      self.flags_containers.push(item.into());
+    }
+  }
+
+  /// Adds a rule for converting C++ identifiers to Rust identifiers,
+  /// e.g. removal of the library's prefix (like `"gtk_"` or `"Gst"`).
+  /// Rules are applied in order of addition. Explicit renames take
+  /// precedence over other rules. Qt prefixes are removed automatically
+  /// for Qt libraries.
+  pub fn add_rename_rule(&mut self, rule: RenameRule) {
+    self.rename_rules.push(rule);
+  }
+
+  /// Adds multiple rename rules. See `Config::add_rename_rule`.
+  pub fn add_rename_rules<Iter>(&mut self, items: Iter)
+    where Iter: IntoIterator<Item = RenameRule>
+  {
+    self.rename_rules.extend(items);
+  }
+
+  /// Adds a declarative rule that allows or blocks C++ types and methods
+  /// by name, signature, include file, access level, deprecated status
+  /// or argument types. Rules are applied to the output of the C++ parser
+  /// after `cpp_data_filter`s, and the first matching rule decides whether
+  /// the item is kept. Each removed item is reported with the rule that removed it.
+  pub fn add_filter_rule(&mut self, rule: FilterRule) {
+    self.filter_rules.push(rule);
+  }
+
+  /// Adds multiple filter rules. See `Config::add_filter_rule`.
+  pub fn add_filter_rules<Iter>(&mut self, items: Iter)
+    where Iter: IntoIterator<Item = FilterRule>
+  {
+    self.filter_rules.extend(items);
+  }
+
+  /// Sets the placement of generated items into Rust modules.
+  /// Default is `ModuleLayout::IncludeFile`.
+  pub fn set_module_layout(&mut self, layout: ModuleLayout) {
+    self.module_layout = layout;
+  }
+
+  /// Places all items declared in the include file (e.g. `"gtkbutton.h"`)
+  /// into the Rust module (e.g. `"widgets"`), instead of the module
+  /// derived from the include file name. In `ModuleLayout::Namespace`
+  /// mode, this applies to items of the global namespace.
+  pub fn add_module_mapping<P1: Into<String>, P2: Into<String>>(&mut self,
+                                                                include_file: P1,
+                                                                module: P2) {
+    self.module_mapping.push((include_file.into(), module.into()));
+  }
+
+  /// Sets the representation of overloaded methods and methods
+  /// with default arguments. Default is `OverloadStrategy::ParametersTrait`.
+  pub fn set_overload_strategy(&mut self, strategy: OverloadStrategy) {
+    self.overload_strategy = strategy;
+  }
+
+  /// Enables gating each generated top level module and its FFI functions
+  /// behind a Cargo feature named after the module. Feature dependencies
+  /// are derived from types used across modules and are written to the
+  /// `[features]` section of the generated `Cargo.toml`. If the crate is
+  /// generated by its build script, the crate's `Cargo.toml` must declare
+  /// the same features. Only C++ wrapper sources required by the enabled
+  /// features are compiled.
+  pub fn set_module_features(&mut self, enabled: bool) {
+    self.module_features = enabled;
+  }
+
+  /// Adds a target platform. If any platforms are added, C++ headers are
+  /// parsed once for each platform, and items not available on all
+  /// platforms are only compiled on the platforms where they were found.
+  pub fn add_target_platform(&mut self, platform: TargetPlatform) {
+    self.target_platforms.push(platform);
+  }
+
+  /// Enables generation of a separate `<crate>_sys` crate in the `sys`
+  /// subdirectory of the output. The `-sys` crate contains raw FFI
+  /// declarations and plain struct definitions, and the main crate
+  /// depends on it instead of declaring FFI functions itself.
+  /// The C wrapper library is built by the build script of the `-sys` crate
+  /// using CMake, so the CMake build backend must be used.
+  pub fn set_sys_crate(&mut self, enabled: bool) {
+    self.sys_crate = enabled;
+  }
+
+  /// Enables generation of `<c_lib>_c_api.h`, a header of the C wrapper
+  /// library that can be included from plain C code. It contains opaque
+  /// struct typedefs for C++ classes, enum definitions and prototypes
+  /// of all FFI functions, and is installed along with other headers.
+  pub fn set_c_header(&mut self, enabled: bool) {
+    self.c_header = enabled;
+  }
+
+  /// Adds a file or a directory affecting the output in a way not
+  /// described by other settings (e.g. a data file read by a filter).
+  /// Generated files are regenerated if its content changes.
+  pub fn add_cache_dependency<P: Into<PathBuf>>(&mut self, path: P) {
+    self.cache_dependencies.push(path.into());
+  }
+
+  /// Adds a CMake cache variable passed as a `-D` option when the C wrapper
+  /// library is configured. It can override variables set by the generator,
+  /// e.g. `CMAKE_BUILD_TYPE`.
+  pub fn add_cmake_cache_var<P1: Into<String>, P2: Into<String>>(&mut self,
+                                                                  name: P1,
+                                                                  value: P2) {
+    self.cmake_cache_vars.push((name.into(), value.into()));
+  }
+
+  /// Sets the CMake generator used to build the C wrapper library
+  /// (e.g. `"Ninja"`). By default, `"NMake Makefiles"` is used
+  /// with MSVC and CMake's default generator otherwise.
+  pub fn set_cmake_generator<P: Into<String>>(&mut self, name: P) {
+    self.cmake_generator = Some(name.into());
+  }
+
+  /// Sets a command prepended to C++ compiler invokations when the C wrapper
+  /// library is built (e.g. `"ccache"`). Requires CMake 3.4 or newer
+  /// and a Makefile or Ninja generator.
+  pub fn set_cpp_compiler_launcher<P: Into<String>>(&mut self, command: P) {
+    self.cpp_compiler_launcher = Some(command.into());
+  }
+
+  /// Sets the tool used to build the C wrapper library. The value can be
+  /// overridden with `CPP_TO_RUST_BUILD_BACKEND` environment variable
+  /// (`"cmake"` or `"compiler"`).
+  pub fn set_cpp_build_backend(&mut self, backend: CppBuildBackend) {
+    self.cpp_build_backend = backend;
+  }
+
+  /// Enables generation of a module that re-exports all types
+  /// of the crate under their short names, regardless of the layout.
+  pub fn set_flat_module<P: Into<String>>(&mut self, name: P) {
+    self.flat_module = Some(name.into());
+  }
+
   /// Starts execution of the generator.
   /// This function will print the necessary build script output to stdout.
   /// It also displays some debugging output that can be made visible by
@@ -242,4 +637,96 @@ impl Config {
   pub fn cpp_data_filters(&self) -> Vec<&Box<CppDataFilterFn>> {
     self.cpp_data_filters.iter().map(|x| &x.0).collect()
   }
+
+  pub fn default_pointer_policy(&self) -> PointerPolicy {
+    self.default_pointer_policy
+  }
+
+  pub fn pointer_policies(&self) -> &[(String, PointerPolicy)] {
+    &self.pointer_policies
+  }
+
+  pub fn unsafe_methods(&self) -> &[String] {
+    &self.unsafe_methods
+  }
+
+  pub fn return_lifetimes(&self) -> &[(String, ReturnLifetime)] {
+    &self.return_lifetimes
+  }
+
+  pub fn default_enum_representation(&self) -> EnumRepresentation {
+    self.default_enum_representation
+  }
+
+  pub fn enum_representations(&self) -> &[(String, EnumRepresentation)] {
+    &self.enum_representations
+  }
+
+  pub fn flag_enums(&self) -> &[String] {
+    &self.flag_enums
+  }
+
+  pub fn flags_containers(&self) -> &[String] {
+    &self.flags_containers
+  }
+
+  pub fn rename_rules(&self) -> &[RenameRule] {
+    &self.rename_rules
+  }
+
+  pub fn filter_rules(&self) -> &[FilterRule] {
+    &self.filter_rules
+  }
+
+  pub fn module_layout(&self) -> ModuleLayout {
+    self.module_layout
+  }
+
+  pub fn module_mapping(&self) -> &[(String, String)] {
+    &self.module_mapping
+  }
+
+  pub fn flat_module(&self) -> Option<&String> {
+    self.flat_module.as_ref()
+  }
+
+  pub fn overload_strategy(&self) -> OverloadStrategy {
+    self.overload_strategy
+  }
+
+  pub fn module_features(&self) -> bool {
+    self.module_features
+  }
+
+  pub fn target_platforms(&self) -> &[TargetPlatform] {
+    &self.target_platforms
+  }
+
+  pub fn sys_crate(&self) -> bool {
+    self.sys_crate
+  }
+
+  pub fn c_header(&self) -> bool {
+    self.c_header
+  }
+
+  pub fn cache_dependencies(&self) -> &[PathBuf] {
+    &self.cache_dependencies
+  }
+
+  pub fn cmake_cache_vars(&self) -> &[(String, String)] {
+    &self.cmake_cache_vars
+  }
+
+  pub fn cmake_generator(&self) -> Option<&String> {
+    self.cmake_generator.as_ref()
+  }
+
+  pub fn cpp_compiler_launcher(&self) -> Option<&String> {
+    self.cpp_compiler_launcher.as_ref()
+  }
+
+  pub fn cpp_build_backend(&self) -> CppBuildBackend {
+    self.cpp_build_backend
+  }
 }
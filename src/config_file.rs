@@ -0,0 +1,327 @@
+//! Loading `Config` from a `cpp_to_rust.toml` file, a JSON file
+//! with the same structure, or the `[package.metadata.cpp_to_rust]`
+//! section of the crate's `Cargo.toml`.
+//!
+//! All keys are optional, and unknown keys are rejected. Relative paths
+//! are resolved relative to the directory containing the file.
+//!
+//! ```toml
+//! linked_libs = ["foo"]
+//! include_paths = ["/usr/include/foo"]
+//! lib_paths = ["/usr/lib"]
+//! include_directives = ["foo.h"]
+//! cpp_parser_flags = ["-fPIC"]
+//! cpp_parser_blocked_names = ["foo::detail"]
+//! # regular expressions matched against full names of methods
+//! blocked_methods = ["^Foo::internal_"]
+//! # regular expressions matched against class names;
+//! # all methods of matching classes are blocked
+//! blocked_classes = ["Private$"]
+//! # build settings of the C wrapper library
+//! cpp_build_backend = "cmake" # or "compiler"
+//! cmake_generator = "Ninja"
+//! cpp_compiler_launcher = "ccache"
+//!
+//! [cmake_cache_vars]
+//! CMAKE_CXX_COMPILER = "clang++"
+//!
+//! # declarative filter rules (see `FilterRule`)
+//! [[filter_rules]]
+//! action = "block"
+//! name = "Foo::*"
+//! access = "protected"
+//! deprecated = true
+//! ```
+
+use config::{Config, CppBuildBackend, FilterRule, FilterAction};
+use cpp_data::CppVisibility;
+use cpp_method::CppMethod;
+use errors::{Result, ChainErr};
+use file_utils::{PathBufWithAdded, load_toml, load_json};
+use log;
+use utils::MapIfOk;
+
+use std::path::{Path, PathBuf};
+
+extern crate regex;
+extern crate serde_json;
+extern crate toml;
+
+use self::regex::Regex;
+
+/// Name of the config file searched for in the crate directory
+pub const FILE_NAME: &'static str = "cpp_to_rust.toml";
+
+/// Returns list of strings stored under `key` in `table`.
+fn string_list(table: &toml::Table, key: &str) -> Result<Vec<String>> {
+  if let Some(value) = table.get(key) {
+    let items = try!(value.as_slice().chain_err(|| format!("'{}' must be an array", key)));
+    items.iter().map_if_ok(|x| -> Result<_> {
+      Ok(try!(x.as_str().chain_err(|| format!("'{}[i]' must be a string", key))).to_string())
+    })
+  } else {
+    Ok(Vec::new())
+  }
+}
+
+/// Returns list of paths stored under `key` in `table`,
+/// resolving relative paths against `base_dir`.
+fn path_list(table: &toml::Table, key: &str, base_dir: &Path) -> Result<Vec<PathBuf>> {
+  Ok(try!(string_list(table, key))
+    .into_iter()
+    .map(|x| {
+      let path = PathBuf::from(x);
+      if path.is_absolute() {
+        path
+      } else {
+        base_dir.with_added(path)
+      }
+    })
+    .collect())
+}
+
+/// Returns list of compiled regular expressions stored under `key` in `table`.
+fn regex_list(table: &toml::Table, key: &str) -> Result<Vec<Regex>> {
+  try!(string_list(table, key)).iter().map_if_ok(|x| -> Result<_> {
+    Ok(try!(Regex::new(x).chain_err(|| format!("invalid regex in '{}': {}", key, x))))
+  })
+}
+
+/// Returns boolean value stored under `key` in `table`.
+fn bool_value(table: &toml::Table, key: &str) -> Result<Option<bool>> {
+  if let Some(value) = table.get(key) {
+    Ok(Some(try!(value.as_bool().chain_err(|| format!("'{}' must be a boolean", key)))))
+  } else {
+    Ok(None)
+  }
+}
+
+/// Returns string value stored under `key` in `table`.
+fn string_value(table: &toml::Table, key: &str) -> Result<Option<String>> {
+  if let Some(value) = table.get(key) {
+    Ok(Some(try!(value.as_str().chain_err(|| format!("'{}' must be a string", key))).to_string()))
+  } else {
+    Ok(None)
+  }
+}
+
+/// Keys accepted by `apply_table`
+const KEYS: &'static [&'static str] = &["linked_libs",
+                                        "linked_frameworks",
+                                        "include_paths",
+                                        "lib_paths",
+                                        "framework_paths",
+                                        "target_include_paths",
+                                        "include_directives",
+                                        "cpp_parser_flags",
+                                        "cpp_compiler_flags",
+                                        "cpp_parser_blocked_names",
+                                        "unsafe_methods",
+                                        "flag_enums",
+                                        "flags_containers",
+                                        "flat_module",
+                                        "module_features",
+                                        "sys_crate",
+                                        "c_header",
+                                        "cpp_build_backend",
+                                        "cmake_generator",
+                                        "cpp_compiler_launcher",
+                                        "cmake_cache_vars",
+                                        "filter_rules",
+                                        "blocked_methods",
+                                        "blocked_classes"];
+
+/// Keys accepted in `[[filter_rules]]` items
+const FILTER_RULE_KEYS: &'static [&'static str] = &["action",
+                                                    "name",
+                                                    "signature",
+                                                    "include_file",
+                                                    "argument_type",
+                                                    "deprecated",
+                                                    "access"];
+
+/// Returns an error if `table` contains a key not present in `valid_keys`,
+/// so that misspelled keys are not silently ignored.
+fn check_keys(table: &toml::Table, valid_keys: &[&str]) -> Result<()> {
+  for key in table.keys() {
+    if !valid_keys.contains(&key.as_str()) {
+      return Err(format!("unknown key: '{}'. Valid keys are: {}",
+                         key,
+                         valid_keys.join(", "))
+        .into());
+    }
+  }
+  Ok(())
+}
+
+/// Parses a `[[filter_rules]]` item.
+fn filter_rule(value: &toml::Value) -> Result<FilterRule> {
+  let table = try!(value.as_table().chain_err(|| "'filter_rules[i]' must be a table"));
+  try!(check_keys(table, FILTER_RULE_KEYS));
+  let action = try!(try!(string_value(table, "action"))
+    .chain_err(|| "'action' is required in filter rules"));
+  let mut rule = FilterRule::new(match action.as_ref() {
+    "allow" => FilterAction::Allow,
+    "block" => FilterAction::Block,
+    _ => return Err(format!("unknown filter rule action: {}", action).into()),
+  });
+  rule.name = try!(string_value(table, "name"));
+  rule.signature = try!(string_value(table, "signature"));
+  rule.include_file = try!(string_value(table, "include_file"));
+  rule.argument_type = try!(string_value(table, "argument_type"));
+  rule.deprecated = try!(bool_value(table, "deprecated"));
+  if let Some(access) = try!(string_value(table, "access")) {
+    rule.access = Some(match access.as_ref() {
+      "public" => CppVisibility::Public,
+      "protected" => CppVisibility::Protected,
+      "private" => CppVisibility::Private,
+      _ => return Err(format!("unknown access level: {}", access).into()),
+    });
+  }
+  Ok(rule)
+}
+
+/// Applies values of `table` to `config`.
+pub fn apply_table(config: &mut Config, table: &toml::Table, base_dir: &Path) -> Result<()> {
+  try!(check_keys(table, KEYS));
+  for lib in try!(string_list(table, "linked_libs")) {
+    config.add_linked_lib(lib);
+  }
+  for framework in try!(string_list(table, "linked_frameworks")) {
+    config.add_linked_framework(framework);
+  }
+  for path in try!(path_list(table, "include_paths", base_dir)) {
+    config.add_include_path(path);
+  }
+  for path in try!(path_list(table, "lib_paths", base_dir)) {
+    config.add_lib_path(path);
+  }
+  for path in try!(path_list(table, "framework_paths", base_dir)) {
+    config.add_framework_path(path);
+  }
+  for path in try!(path_list(table, "target_include_paths", base_dir)) {
+    config.add_target_include_path(path);
+  }
+  for directive in try!(string_list(table, "include_directives")) {
+    config.add_include_directive(directive);
+  }
+  config.add_cpp_parser_flags(try!(string_list(table, "cpp_parser_flags")));
+  config.add_cpp_compiler_flags(try!(string_list(table, "cpp_compiler_flags")));
+  config.add_cpp_parser_blocked_names(try!(string_list(table, "cpp_parser_blocked_names")));
+  config.add_unsafe_methods(try!(string_list(table, "unsafe_methods")));
+  config.add_flag_enums(try!(string_list(table, "flag_enums")));
+  config.add_flags_containers(try!(string_list(table, "flags_containers")));
+  if let Some(value) = table.get("flat_module") {
+    config.set_flat_module(try!(value.as_str().chain_err(|| "'flat_module' must be a string")));
+  }
+  if let Some(value) = try!(bool_value(table, "module_features")) {
+    config.set_module_features(value);
+  }
+  if let Some(value) = try!(bool_value(table, "sys_crate")) {
+    config.set_sys_crate(value);
+  }
+  if let Some(value) = try!(bool_value(table, "c_header")) {
+    config.set_c_header(value);
+  }
+
+  if let Some(value) = try!(string_value(table, "cpp_build_backend")) {
+    config.set_cpp_build_backend(try!(CppBuildBackend::from_name(&value)));
+  }
+  if let Some(value) = try!(string_value(table, "cmake_generator")) {
+    config.set_cmake_generator(value);
+  }
+  if let Some(value) = try!(string_value(table, "cpp_compiler_launcher")) {
+    config.set_cpp_compiler_launcher(value);
+  }
+  if let Some(value) = table.get("cmake_cache_vars") {
+    let vars = try!(value.as_table().chain_err(|| "'cmake_cache_vars' must be a table"));
+    for (name, value) in vars {
+      let value = try!(value.as_str()
+        .chain_err(|| format!("'cmake_cache_vars.{}' must be a string", name)));
+      config.add_cmake_cache_var(name.as_str(), value);
+    }
+  }
+
+  if let Some(value) = table.get("filter_rules") {
+    let items = try!(value.as_slice().chain_err(|| "'filter_rules' must be an array"));
+    config.add_filter_rules(try!(items.iter().map_if_ok(filter_rule)));
+  }
+
+  let blocked_methods = try!(regex_list(table, "blocked_methods"));
+  if !blocked_methods.is_empty() {
+    config.add_cpp_ffi_generator_filter(Box::new(move |method: &CppMethod| {
+      let name = method.full_name();
+      if let Some(regex) = blocked_methods.iter().find(|x| x.is_match(&name)) {
+        log::noisy(format!("Method {} is blocked by config rule: {}", name, regex.as_str()));
+        return Ok(false);
+      }
+      Ok(true)
+    }));
+  }
+  let blocked_classes = try!(regex_list(table, "blocked_classes"));
+  if !blocked_classes.is_empty() {
+    config.add_cpp_ffi_generator_filter(Box::new(move |method: &CppMethod| {
+      if let Some(class_name) = method.class_name() {
+        if let Some(regex) = blocked_classes.iter().find(|x| x.is_match(class_name)) {
+          log::noisy(format!("Method {} is blocked by config rule: {}",
+                             method.full_name(),
+                             regex.as_str()));
+          return Ok(false);
+        }
+      }
+      Ok(true)
+    }));
+  }
+  Ok(())
+}
+
+/// Converts a JSON value to the equivalent TOML value.
+fn json_to_toml(value: serde_json::Value) -> Result<toml::Value> {
+  use self::serde_json::Value;
+  Ok(match value {
+    Value::Null => return Err("null values are not supported".into()),
+    Value::Bool(x) => toml::Value::Boolean(x),
+    Value::I64(x) => toml::Value::Integer(x),
+    Value::U64(x) => {
+      if x > i64::max_value() as u64 {
+        return Err(format!("integer is too large: {}", x).into());
+      }
+      toml::Value::Integer(x as i64)
+    }
+    Value::F64(x) => toml::Value::Float(x),
+    Value::String(x) => toml::Value::String(x),
+    Value::Array(items) => toml::Value::Array(try!(items.into_iter().map_if_ok(json_to_toml))),
+    Value::Object(map) => {
+      let mut table = toml::Table::new();
+      for (key, value) in map {
+        table.insert(key, try!(json_to_toml(value)));
+      }
+      toml::Value::Table(table)
+    }
+  })
+}
+
+/// Applies content of a config file to `config`.
+/// Files with `.json` extension are parsed as JSON, and other files as TOML.
+pub fn apply_file(config: &mut Config, path: &Path) -> Result<()> {
+  let table = if path.extension().map_or(false, |x| x == "json") {
+    let value: serde_json::Value = try!(load_json(path));
+    match try!(json_to_toml(value)) {
+      toml::Value::Table(table) => table,
+      _ => return Err(format!("JSON config must be an object: {}", path.display()).into()),
+    }
+  } else {
+    try!(load_toml(path))
+  };
+  config.add_cache_dependency(path);
+  let base_dir = try!(path.parent().chain_err(|| "config file path has no parent"));
+  apply_table(config, &table, base_dir)
+    .chain_err(|| format!("invalid config file: {}", path.display()))
+}
+
+/// Loads `Config` from a TOML or JSON file.
+pub fn load(path: &Path) -> Result<Config> {
+  let mut config = Config::new();
+  try!(apply_file(&mut config, path));
+  Ok(config)
+}
@@ -0,0 +1,196 @@
+use cpp_data::CppData;
+use cpp_method::CppMethod;
+use errors::Result;
+use file_utils::{PathBufWithAdded, create_file, save_json};
+use rust_info::{RustModule, RustMethod, RustMethodArguments, RustTypeDeclarationKind};
+
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+pub use serializable::{SkipStage, SkippedItem, ClassCoverage, CoverageReport};
+
+impl SkipStage {
+  /// Returns human-readable description of the stage.
+  pub fn caption(&self) -> &'static str {
+    match *self {
+      SkipStage::ParserIntegrityCheck => "C++ parser integrity check",
+      SkipStage::FilterRule => "filter rule",
+      SkipStage::FfiGeneratorFilter => "FFI generator filter",
+      SkipStage::FfiSignature => "FFI signature",
+      SkipStage::RustClassUnavailable => "class unavailable in Rust",
+      SkipStage::RustTypes => "Rust types",
+      SkipStage::RustDuplicate => "duplicate Rust signature",
+      SkipStage::RustUnsafeTraitImpl => "unsafe trait method",
+    }
+  }
+}
+
+impl SkippedItem {
+  /// Creates an item representing a dropped method.
+  pub fn method<S: Into<String>>(method: &CppMethod, stage: SkipStage, reason: S) -> SkippedItem {
+    SkippedItem {
+      name: method.short_text(),
+      class_name: method.class_name().cloned(),
+      include_file: method.include_file.clone(),
+      is_type: false,
+      stage: stage,
+      reason: reason.into(),
+    }
+  }
+}
+
+/// C++ methods included in coverage statistics, as pairs of
+/// class name (empty for free functions) and method's text representation.
+/// Destructors are not included.
+pub fn coverage_methods(cpp_data: &CppData) -> Vec<(String, String)> {
+  let mut result: Vec<_> = cpp_data.methods
+    .iter()
+    .filter(|x| !x.is_destructor())
+    .map(|x| (x.class_name().cloned().unwrap_or_else(String::new), x.short_text()))
+    .collect();
+  for item in &cpp_data.skipped_items {
+    if !item.is_type {
+      result.push((item.class_name.clone().unwrap_or_else(String::new), item.name.clone()));
+    }
+  }
+  result
+}
+
+/// Adds text representations of C++ methods wrapped by `method` to `output`.
+fn add_wrapped_methods(method: &RustMethod, output: &mut HashSet<String>) {
+  match method.arguments {
+    RustMethodArguments::SingleVariant(ref variant) => {
+      output.insert(variant.cpp_method.cpp_method.short_text());
+    }
+    RustMethodArguments::DefaultArguments(ref variants) => {
+      for variant in variants {
+        output.insert(variant.cpp_method.cpp_method.short_text());
+      }
+    }
+    // variants are listed in the parameters trait declaration
+    RustMethodArguments::MultipleVariants { .. } => {}
+  }
+}
+
+/// Collects text representations of all C++ methods wrapped in `modules`.
+fn wrapped_methods(modules: &[RustModule], output: &mut HashSet<String>) {
+  for module in modules {
+    for method in &module.functions {
+      add_wrapped_methods(method, output);
+    }
+    for type1 in &module.types {
+      match type1.kind {
+        RustTypeDeclarationKind::CppTypeWrapper { ref methods, ref traits, .. } => {
+          for method in methods {
+            add_wrapped_methods(method, output);
+          }
+          for trait1 in traits {
+            for method in &trait1.methods {
+              add_wrapped_methods(method, output);
+            }
+          }
+        }
+        RustTypeDeclarationKind::MethodParametersTrait { ref impls, .. } => {
+          for variant in impls {
+            output.insert(variant.cpp_method.cpp_method.short_text());
+          }
+        }
+      }
+    }
+    wrapped_methods(&module.submodules, output);
+  }
+}
+
+/// Calculates per-class coverage. `methods` is the output of `coverage_methods`.
+pub fn generate(methods: Vec<(String, String)>,
+                skipped_items: Vec<SkippedItem>,
+                modules: &[RustModule])
+                -> CoverageReport {
+  let mut wrapped = HashSet::new();
+  wrapped_methods(modules, &mut wrapped);
+  let mut counts: HashMap<String, (usize, usize)> = HashMap::new();
+  for (class_name, text) in methods {
+    let count = counts.entry(class_name).or_insert((0, 0));
+    count.0 += 1;
+    if wrapped.contains(&text) {
+      count.1 += 1;
+    }
+  }
+  let mut classes: Vec<_> = counts.into_iter()
+    .map(|(class_name, (total, wrapped))| {
+      ClassCoverage {
+        class_name: class_name,
+        total_methods: total,
+        wrapped_methods: wrapped,
+        percentage: if total == 0 {
+          100.0
+        } else {
+          wrapped as f64 * 100.0 / total as f64
+        },
+      }
+    })
+    .collect();
+  classes.sort_by(|a, b| a.class_name.cmp(&b.class_name));
+  let mut skipped_items = skipped_items;
+  skipped_items.sort_by(|a, b| {
+    (&a.class_name, &a.name, &a.reason).cmp(&(&b.class_name, &b.name, &b.reason))
+  });
+  CoverageReport {
+    classes: classes,
+    skipped_items: skipped_items,
+  }
+}
+
+impl CoverageReport {
+  /// Generates Markdown representation of the report.
+  pub fn to_markdown(&self) -> String {
+    let total: usize = self.classes.iter().map(|x| x.total_methods).sum();
+    let wrapped: usize = self.classes.iter().map(|x| x.wrapped_methods).sum();
+    let mut text = "# API coverage report\n\n".to_string();
+    text.push_str(&format!("{} of {} methods are wrapped ({:.1}%). Destructors are not \
+                            counted.\n\n",
+                           wrapped,
+                           total,
+                           if total == 0 {
+                             100.0
+                           } else {
+                             wrapped as f64 * 100.0 / total as f64
+                           }));
+    text.push_str("## Classes\n\n| Class | Wrapped | Total | Coverage |\n|---|---|---|---|\n");
+    for class in &self.classes {
+      text.push_str(&format!("| {} | {} | {} | {:.1}% |\n",
+                             if class.class_name.is_empty() {
+                               "(free functions)"
+                             } else {
+                               class.class_name.as_str()
+                             },
+                             class.wrapped_methods,
+                             class.total_methods,
+                             class.percentage));
+    }
+    text.push_str("\n## Skipped items\n");
+    let mut last_class_name = None;
+    for item in &self.skipped_items {
+      if last_class_name != Some(&item.class_name) {
+        text.push_str(&format!("\n### {}\n\n",
+                               item.class_name
+                                 .as_ref()
+                                 .map_or("(free functions)", |x| x.as_str())));
+        last_class_name = Some(&item.class_name);
+      }
+      text.push_str(&format!("- `{}` ({}): {}: {}\n",
+                             item.name,
+                             item.include_file,
+                             item.stage.caption(),
+                             item.reason.replace("\n", " ").trim()));
+    }
+    text
+  }
+
+  /// Writes the report to `coverage.json` and `coverage.md` in `output_dir`.
+  pub fn save(&self, output_dir: &PathBuf) -> Result<()> {
+    try!(save_json(output_dir.with_added("coverage.json"), self));
+    let mut file = try!(create_file(output_dir.with_added("coverage.md")));
+    file.write(self.to_markdown())
+  }
+}
@@ -1,13 +1,17 @@
+use config::TargetPlatform;
+use cpp_data::{CppTypeData, CppTypeKind};
 use cpp_ffi_data::{IndirectionChange, CppAndFfiMethod, CppFfiArgumentMeaning};
 use cpp_ffi_generator::CppFfiHeaderData;
 use cpp_method::ReturnValueAllocationPlace;
-use cpp_type::{CppTypeIndirection, CppTypeBase};
+use cpp_type::{CppTypeIndirection, CppTypeBase, CppType, CppBuiltInNumericType,
+               CppSpecificNumericTypeKind};
 use errors::{Result, ChainErr, unexpected};
 use file_utils::{PathBufWithAdded, create_dir_all, create_file, path_to_str};
 use log;
 use string_utils::JoinWithString;
 use utils::MapIfOk;
 
+use std::collections::BTreeSet;
 use std::path::PathBuf;
 
 /// Generates C++ code for the C wrapper library.
@@ -21,16 +25,20 @@ pub struct CppCodeGenerator {
 
   is_shared: bool,
   cpp_libs: Vec<String>,
+  /// Target platforms used for conditional compilation
+  platforms: Vec<TargetPlatform>,
 }
 
 impl CppCodeGenerator {
   /// Creates a generator for a library.
   /// lib_name: library name
   /// lib_path: path to the directory where the library is generated
+  /// platforms: target platforms referenced by methods' platform lists
   pub fn new(lib_name: String,
              lib_path: ::std::path::PathBuf,
              is_shared: bool,
-             cpp_libs: Vec<String>)
+             cpp_libs: Vec<String>,
+             platforms: Vec<TargetPlatform>)
              -> Self {
     CppCodeGenerator {
       lib_name: lib_name.clone(),
@@ -38,9 +46,34 @@ impl CppCodeGenerator {
       lib_path: lib_path,
       is_shared: is_shared,
       cpp_libs: cpp_libs,
+      platforms: platforms,
     }
   }
 
+  /// Returns preprocessor condition that holds on any of `platforms`,
+  /// or `None` if the code is available on all platforms.
+  fn platforms_condition(&self, platforms: &[String]) -> Result<Option<String>> {
+    if platforms.is_empty() {
+      return Ok(None);
+    }
+    let conditions = try!(platforms.iter().map_if_ok(|name| -> Result<_> {
+      let platform = try!(self.platforms
+        .iter()
+        .find(|x| &x.name == name)
+        .chain_err(|| format!("unknown platform: {}", name)));
+      Ok(format!("({})", platform.cpp_condition))
+    }));
+    Ok(Some(conditions.join(" || ")))
+  }
+
+  /// Wraps `code` in `#if` block if `platforms` is not empty.
+  fn with_platforms_condition(&self, platforms: &[String], code: String) -> Result<String> {
+    Ok(match try!(self.platforms_condition(platforms)) {
+      Some(condition) => format!("#if {}\n{}#endif\n", condition, code),
+      None => code,
+    })
+  }
+
   /// Generates function name, return type and arguments list
   /// as it appears in both function declaration and implementation.
   fn function_signature(&self, method: &CppAndFfiMethod) -> Result<String> {
@@ -95,7 +128,7 @@ impl CppCodeGenerator {
         result = format!("&{}", result);
       }
       IndirectionChange::QFlagsToUInt => {
-        result = format!("uint({})", result);
+        result = format!("static_cast<unsigned int>({})", result);
       }
     }
 
@@ -309,7 +342,7 @@ impl CppCodeGenerator {
   }
 
   pub fn generate_files(&self, data: &[CppFfiHeaderData]) -> Result<()> {
-    try!(self.generate_all_headers_file(data.iter().map(|x| &x.include_file)));
+    try!(self.generate_all_headers_file(data));
     for item in data {
       try!(self.generate_one(item).chain_err(|| "C++ code generator failed"));
     }
@@ -317,14 +350,16 @@ impl CppCodeGenerator {
   }
 
   /// Generates the header file that includes all other headers of the library.
-  fn generate_all_headers_file<'a, I: Iterator<Item = &'a String>>(&self, names: I) -> Result<()> {
+  /// Includes of headers that are only available on some platforms are wrapped in `#if` blocks.
+  fn generate_all_headers_file(&self, data: &[CppFfiHeaderData]) -> Result<()> {
     let mut h_path = self.lib_path.clone();
     h_path.push("include");
     h_path.push(format!("{}.h", &self.lib_name));
     let mut all_header_file = try!(create_file(&h_path));
     try!(all_header_file.write(format!("#ifndef {0}_H\n#define {0}_H\n\n", &self.lib_name_upper)));
-    for name in names {
-      try!(all_header_file.write(format!("#include \"{}_{}.h\"\n", &self.lib_name, name)));
+    for item in data {
+      let include = format!("#include \"{}_{}.h\"\n", &self.lib_name, item.include_file);
+      try!(all_header_file.write(try!(self.with_platforms_condition(&item.platforms(), include))));
     }
     try!(all_header_file.write(format!("#endif // {}_H\n", &self.lib_name_upper)));
     Ok(())
@@ -346,7 +381,10 @@ impl CppCodeGenerator {
     let mut cpp_file = try!(create_file(&cpp_path));
     let mut h_file = try!(create_file(&h_path));
 
-    try!(cpp_file.write(format!("#include \"{}\"\n\n", ffi_include_file)));
+    let header_platforms = data.platforms();
+    let include = format!("#include \"{}\"\n", ffi_include_file);
+    try!(cpp_file.write(try!(self.with_platforms_condition(&header_platforms, include))));
+    try!(cpp_file.write("\n"));
     let include_guard_name = ffi_include_file.replace(".", "_").to_uppercase();
     try!(h_file.write(format!("#ifndef {}\n#define {}\n\n",
                               include_guard_name,
@@ -357,8 +395,18 @@ impl CppCodeGenerator {
     try!(h_file.write("extern \"C\" {\n\n"));
 
     for method in &data.methods {
+      let condition = try!(self.platforms_condition(&method.cpp_method.platforms));
+      if let Some(ref condition) = condition {
+        let directive = format!("#if {}\n", condition);
+        try!(h_file.write(&directive));
+        try!(cpp_file.write(&directive));
+      }
       try!(h_file.write(try!(self.function_declaration(method))));
       try!(cpp_file.write(try!(self.function_implementation(method))));
+      if condition.is_some() {
+        try!(h_file.write("#endif\n"));
+        try!(cpp_file.write("#endif\n\n"));
+      }
     }
 
     try!(h_file.write("\n} // extern \"C\"\n\n"));
@@ -366,4 +414,225 @@ impl CppCodeGenerator {
     try!(h_file.write(format!("#endif // {}\n", include_guard_name)));
     Ok(())
   }
+
+  /// Returns name of the type used in the plain C header
+  /// for a C++ class or enum with `caption`.
+  fn c_type_name(&self, caption: &str) -> String {
+    format!("{}_{}", self.lib_name, caption)
+  }
+
+  /// Generates code of an FFI type for the plain C header. C++ classes are
+  /// replaced with opaque structs, and their C names are added to `classes`.
+  /// Names of C++ enums are added to `enums`.
+  fn c_type_code(&self,
+                 cpp_type: &CppType,
+                 function_pointer_inner_text: Option<&String>,
+                 classes: &mut BTreeSet<String>,
+                 enums: &mut BTreeSet<String>)
+                 -> Result<String> {
+    let base_code = match cpp_type.base {
+      CppTypeBase::Void => "void".to_string(),
+      CppTypeBase::BuiltInNumeric(ref t) => {
+        match *t {
+          CppBuiltInNumericType::Char16 => "uint_least16_t".to_string(),
+          CppBuiltInNumericType::Char32 => "uint_least32_t".to_string(),
+          CppBuiltInNumericType::Int128 => "__int128".to_string(),
+          CppBuiltInNumericType::UInt128 => "unsigned __int128".to_string(),
+          _ => t.to_cpp_code().to_string(),
+        }
+      }
+      CppTypeBase::SpecificNumeric { ref bits, ref kind, .. } => {
+        match *kind {
+          CppSpecificNumericTypeKind::Integer { ref is_signed } => {
+            format!("{}int{}_t", if *is_signed { "" } else { "u" }, bits)
+          }
+          CppSpecificNumericTypeKind::FloatingPoint => {
+            match *bits {
+              32 => "float".to_string(),
+              64 => "double".to_string(),
+              _ => return Err(format!("unsupported floating point type size: {}", bits).into()),
+            }
+          }
+        }
+      }
+      CppTypeBase::PointerSizedInteger { ref is_signed, .. } => {
+        (if *is_signed { "intptr_t" } else { "uintptr_t" }).to_string()
+      }
+      CppTypeBase::Enum { ref name } => {
+        enums.insert(name.clone());
+        self.c_type_name(&name.replace("::", "_"))
+      }
+      CppTypeBase::Class(ref base) => {
+        let name = self.c_type_name(&try!(base.caption()));
+        classes.insert(name.clone());
+        name
+      }
+      CppTypeBase::TemplateParameter { .. } => {
+        return Err("template parameters are not allowed in C code".into());
+      }
+      CppTypeBase::FunctionPointer { ref return_type,
+                                     ref arguments,
+                                     ref allows_variadic_arguments } => {
+        if *allows_variadic_arguments {
+          return Err("function pointers with variadic arguments are not supported".into());
+        }
+        let inner_text = try!(function_pointer_inner_text
+          .chain_err(|| "function_pointer_inner_text argument is missing"));
+        let mut arg_texts = Vec::new();
+        for arg in arguments {
+          arg_texts.push(try!(self.c_type_code(arg, None, classes, enums)));
+        }
+        let return_type_code = try!(self.c_type_code(return_type, None, classes, enums));
+        return Ok(format!("{} (*{})({})", return_type_code, inner_text, arg_texts.join(", ")));
+      }
+    };
+    let indirection = match cpp_type.indirection {
+      CppTypeIndirection::None => "",
+      CppTypeIndirection::Ptr => "*",
+      CppTypeIndirection::PtrPtr => if cpp_type.is_const2 { "* const *" } else { "**" },
+      _ => return Err(format!("references are not allowed in C code: {:?}", cpp_type).into()),
+    };
+    Ok(format!("{}{}{}",
+               if cpp_type.is_const { "const " } else { "" },
+               base_code,
+               indirection))
+  }
+
+  /// Generates a prototype of an FFI function for the plain C header.
+  fn c_function_declaration(&self,
+                            method: &CppAndFfiMethod,
+                            classes: &mut BTreeSet<String>,
+                            enums: &mut BTreeSet<String>)
+                            -> Result<String> {
+    let mut arg_texts = Vec::new();
+    for arg in &method.c_signature.arguments {
+      let ffi_type = &arg.argument_type.ffi_type;
+      arg_texts.push(if ffi_type.base.is_function_pointer() {
+        try!(self.c_type_code(ffi_type, Some(&arg.name), classes, enums))
+      } else {
+        format!("{} {}",
+                try!(self.c_type_code(ffi_type, None, classes, enums)),
+                arg.name)
+      });
+    }
+    if arg_texts.is_empty() {
+      arg_texts.push("void".to_string());
+    }
+    let name_with_args = format!("{}({})", method.c_name, arg_texts.join(", "));
+    let return_type = &method.c_signature.return_type.ffi_type;
+    let signature = if return_type.base.is_function_pointer() {
+      try!(self.c_type_code(return_type, Some(&name_with_args), classes, enums))
+    } else {
+      format!("{} {}",
+              try!(self.c_type_code(return_type, None, classes, enums)),
+              name_with_args)
+    };
+    Ok(format!("{}_EXPORT {};\n", self.lib_name_upper, signature))
+  }
+
+  /// Generates definition of a C++ enum for the plain C header.
+  /// The enum type is declared as its underlying integer type
+  /// to preserve the ABI, and its values are declared as constants.
+  fn c_enum_definition(&self, type_data: &CppTypeData) -> Result<String> {
+    if let CppTypeKind::Enum { ref values, ref underlying_type, .. } = type_data.kind {
+      let name = self.c_type_name(&type_data.name.replace("::", "_"));
+      let underlying_code = try!(self.c_type_code(underlying_type,
+                                                  None,
+                                                  &mut BTreeSet::new(),
+                                                  &mut BTreeSet::new()));
+      let mut code = format!("typedef {} {};\n", underlying_code, name);
+      let (int_values, other_values): (Vec<_>, Vec<_>) = values.iter()
+        .partition(|v| v.value >= ::std::i32::MIN as i64 && v.value <= ::std::i32::MAX as i64);
+      if !int_values.is_empty() {
+        code.push_str("enum {\n");
+        for value in int_values {
+          code.push_str(&try!(self.with_platforms_condition(&value.platforms,
+                                                            format!("  {}_{} = {},\n",
+                                                                    name,
+                                                                    value.name,
+                                                                    value.value))));
+        }
+        code.push_str("};\n");
+      }
+      for value in other_values {
+        code.push_str(&try!(self.with_platforms_condition(&value.platforms,
+                                                          format!("#define {}_{} (({}) {}LL)\n",
+                                                                  name,
+                                                                  value.name,
+                                                                  name,
+                                                                  value.value))));
+      }
+      Ok(code)
+    } else {
+      Err(unexpected(format!("not an enum: {}", type_data.name)).into())
+    }
+  }
+
+  /// Generates `<lib_name>_c_api.h`, a header that declares all FFI functions
+  /// of the library using only C types, so that the library can be used
+  /// from C and other languages that can import C headers.
+  /// `types` should contain all types of the library and its dependencies
+  /// so that definitions of referenced enums can be generated.
+  pub fn generate_c_header(&self,
+                           data: &[CppFfiHeaderData],
+                           types: &[&CppTypeData])
+                           -> Result<()> {
+    let mut classes = BTreeSet::new();
+    let mut enums = BTreeSet::new();
+    let mut functions_code = String::new();
+    for header in data {
+      for method in &header.methods {
+        let declaration = match self.c_function_declaration(method, &mut classes, &mut enums) {
+          Ok(r) => r,
+          Err(err) => {
+            log::warning(format!("Can't add {} to the C header: {}", method.c_name, err));
+            continue;
+          }
+        };
+        let condition = try!(self.platforms_condition(&method.cpp_method.platforms));
+        if let Some(ref condition) = condition {
+          functions_code.push_str(&format!("#if {}\n", condition));
+        }
+        functions_code.push_str(&declaration);
+        if condition.is_some() {
+          functions_code.push_str("#endif\n");
+        }
+      }
+    }
+
+    let mut types_code = String::new();
+    for name in &classes {
+      types_code.push_str(&format!("typedef struct {0} {0};\n", name));
+    }
+    types_code.push_str("\n");
+    for name in &enums {
+      if let Some(type_data) = types.iter().find(|t| &t.name == name) {
+        let condition = try!(self.platforms_condition(&type_data.platforms));
+        if let Some(ref condition) = condition {
+          types_code.push_str(&format!("#if {}\n", condition));
+        }
+        types_code.push_str(&try!(self.c_enum_definition(type_data)));
+        if condition.is_some() {
+          types_code.push_str("#endif\n");
+        }
+        types_code.push_str("\n");
+      } else {
+        log::warning(format!("Enum not found: {}; declaring it as int in the C header", name));
+        types_code.push_str(&format!("typedef int {};\n\n",
+                                     self.c_type_name(&name.replace("::", "_"))));
+      }
+    }
+
+    let h_path = self.lib_path
+      .with_added("include")
+      .with_added(format!("{}_c_api.h", &self.lib_name));
+    log::noisy(format!("Generating C header file: {:?}", h_path));
+    let mut h_file = try!(create_file(&h_path));
+    try!(h_file.write(format!(include_str!("../templates/c_lib/c_api.h"),
+                              lib_name_lowercase = &self.lib_name,
+                              lib_name_uppercase = &self.lib_name_upper,
+                              types_code = types_code,
+                              functions_code = functions_code)));
+    Ok(())
+  }
 }
@@ -0,0 +1,315 @@
+//! Building the C wrapper library without CMake. Sources are compiled
+//! directly with the system C++ compiler and archived into a static library.
+//!
+//! The tools and flags are taken from environment variables in the same way
+//! Rust build scripts conventionally do. For each variable (`CXX`, `CXXFLAGS`
+//! and `AR`), the first found of the following is used:
+//! `<var>_<target>`, `<var>_<target_with_underscores>`,
+//! `TARGET_<var>` (or `HOST_<var>` if not cross-compiling) and `<var>`.
+
+use errors::{Result, ChainErr};
+use file_utils::{PathBufWithAdded, create_dir_all, create_file, file_to_string, files_equal,
+                 copy_file, read_dir, remove_file, path_to_str, os_str_to_str};
+use launcher::BuildProfile;
+use log;
+use utils::{is_msvc, parallel_map, run_command};
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::SystemTime;
+
+pub struct CppCompilerBuilder<'a> {
+  /// Directory containing generated `src` and `include` directories
+  pub source_dir: &'a PathBuf,
+  pub build_dir: &'a PathBuf,
+  pub install_dir: &'a PathBuf,
+  /// Name of the library, also used for the `<NAME>_LIBRARY` define
+  pub lib_name: &'a str,
+  /// Paths of sources to compile relative to `source_dir`.
+  /// If `None`, all `.cpp` files in `src` are compiled.
+  pub sources: Option<Vec<String>>,
+  pub include_dirs: &'a [PathBuf],
+  pub framework_dirs: &'a [PathBuf],
+  /// Flags from `Config::add_cpp_compiler_flag`
+  pub cpp_compiler_flags: &'a [String],
+  pub build_profile: BuildProfile,
+  pub num_jobs: i32,
+  /// Command prepended to compiler invokations (e.g. `"ccache"`)
+  pub compiler_launcher: Option<&'a String>,
+  /// Hash of the headers of the C++ library and its dependencies
+  /// (see `build_cache::parse_key`). All objects are rebuilt if it changes.
+  pub dependency_key: &'a str,
+  pub pipe_output: bool,
+}
+
+/// Target triples of the build. Both are `None` if the generator
+/// was not started from a build script.
+struct BuildTarget {
+  target: Option<String>,
+  host: Option<String>,
+}
+
+impl BuildTarget {
+  fn from_env() -> BuildTarget {
+    BuildTarget {
+      target: ::std::env::var("TARGET").ok(),
+      host: ::std::env::var("HOST").ok(),
+    }
+  }
+
+  fn is_msvc(&self) -> bool {
+    match self.target {
+      Some(ref target) => target.contains("msvc"),
+      None => is_msvc(),
+    }
+  }
+
+  fn is_windows(&self) -> bool {
+    match self.target {
+      Some(ref target) => target.contains("windows"),
+      None => cfg!(windows),
+    }
+  }
+
+  /// Returns value of environment variable `var` for the target.
+  fn env_var(&self, var: &str) -> Option<String> {
+    let mut names = Vec::new();
+    if let Some(ref target) = self.target {
+      names.push(format!("{}_{}", var, target));
+      names.push(format!("{}_{}", var, target.replace("-", "_")));
+      let kind = if self.host.as_ref() == Some(target) {
+        "HOST"
+      } else {
+        "TARGET"
+      };
+      names.push(format!("{}_{}", kind, var));
+    }
+    names.push(var.to_string());
+    for name in names {
+      if let Ok(value) = ::std::env::var(&name) {
+        log::noisy(format!("Using env var {}={}", name, value));
+        return Some(value);
+      }
+    }
+    None
+  }
+}
+
+/// Returns the target and values of environment variables that select
+/// the tools and flags of the build, for calculating the cache key.
+pub fn build_env_vars() -> Vec<(&'static str, Option<String>)> {
+  let target = BuildTarget::from_env();
+  let mut result = vec![("TARGET", target.target.clone())];
+  for &name in &["CXX", "CXXFLAGS", "AR"] {
+    result.push((name, target.env_var(name)));
+  }
+  result
+}
+
+/// Returns modification time of a file.
+fn modified(path: &Path) -> Result<SystemTime> {
+  let metadata = try!(path.metadata()
+    .chain_err(|| format!("failed to get metadata: {}", path.display())));
+  metadata.modified().chain_err(|| format!("failed to get modification time: {}", path.display()))
+}
+
+/// Copies `src` to `dst` unless the files are already equal.
+fn install_file(src: &Path, dst: &Path) -> Result<()> {
+  if dst.exists() && try!(files_equal(src, dst)) {
+    return Ok(());
+  }
+  copy_file(src, dst)
+}
+
+impl<'a> CppCompilerBuilder<'a> {
+  /// Returns arguments of the compiler, except for the input
+  /// and output files.
+  fn compiler_args(&self, target: &BuildTarget) -> Result<Vec<String>> {
+    let mut args = Vec::new();
+    let include_prefix;
+    let define_prefix;
+    if target.is_msvc() {
+      // Rust always links to release version of MSVC runtime
+      args.extend(["/nologo", "/c", "/EHsc", "/MD"].iter().map(|x| x.to_string()));
+      match self.build_profile {
+        BuildProfile::Debug => args.push("/Z7".to_string()),
+        BuildProfile::Release => args.push("/O2".to_string()),
+      }
+      include_prefix = "/I";
+      define_prefix = "/D";
+    } else {
+      args.push("-c".to_string());
+      if !target.is_windows() {
+        args.push("-fPIC".to_string());
+      }
+      match self.build_profile {
+        BuildProfile::Debug => args.push("-g".to_string()),
+        BuildProfile::Release => args.push("-O2".to_string()),
+      }
+      args.extend(["-Wall", "-Wextra", "-Werror=return-type"].iter().map(|x| x.to_string()));
+      for dir in self.framework_dirs {
+        args.push(format!("-F{}", try!(path_to_str(dir))));
+      }
+      include_prefix = "-I";
+      define_prefix = "-D";
+    }
+    args.push(format!("{}{}",
+                      include_prefix,
+                      try!(path_to_str(&self.source_dir.with_added("include")))));
+    for dir in self.include_dirs {
+      args.push(format!("{}{}", include_prefix, try!(path_to_str(dir))));
+    }
+    args.push(format!("{}{}_LIBRARY", define_prefix, self.lib_name.to_uppercase()));
+    args.extend_from_slice(self.cpp_compiler_flags);
+    if let Some(flags) = target.env_var("CXXFLAGS") {
+      args.extend(flags.split_whitespace().map(|x| x.to_string()));
+    }
+    Ok(args)
+  }
+
+  /// Returns paths of all sources that should be compiled.
+  fn source_paths(&self) -> Result<Vec<PathBuf>> {
+    if let Some(ref sources) = self.sources {
+      return Ok(sources.iter().map(|x| self.source_dir.with_added(x)).collect());
+    }
+    let mut result = Vec::new();
+    for item in try!(read_dir(self.source_dir.with_added("src"))) {
+      let path = try!(item).path();
+      if path.extension().map_or(false, |x| x == "cpp") {
+        result.push(path);
+      }
+    }
+    result.sort();
+    Ok(result)
+  }
+
+  /// Returns paths of all headers of the library.
+  fn header_paths(&self) -> Result<Vec<PathBuf>> {
+    let mut result = Vec::new();
+    for item in try!(read_dir(self.source_dir.with_added("include"))) {
+      let path = try!(item).path();
+      if path.extension().map_or(false, |x| x == "h") {
+        result.push(path);
+      }
+    }
+    result.sort();
+    Ok(result)
+  }
+
+  pub fn run(self) -> Result<()> {
+    let target = BuildTarget::from_env();
+    let compiler = target.env_var("CXX").unwrap_or_else(|| {
+      if target.is_msvc() {
+        "cl.exe".to_string()
+      } else {
+        "c++".to_string()
+      }
+    });
+    let archiver = target.env_var("AR").unwrap_or_else(|| {
+      if target.is_msvc() {
+        "lib.exe".to_string()
+      } else {
+        "ar".to_string()
+      }
+    });
+    let args = try!(self.compiler_args(&target));
+    let object_extension = if target.is_msvc() { "obj" } else { "o" };
+    let headers = try!(self.header_paths());
+
+    // Objects are reused if they are newer than their source and all
+    // headers of the library, and the compiler command and headers from
+    // `include_dirs` are unchanged. Modification time of unchanged
+    // generated files is preserved.
+    let command_stamp = format!("{:?}",
+                                (&self.compiler_launcher, &compiler, &args, self.dependency_key));
+    let command_stamp_path = self.build_dir.with_added("compiler_command.txt");
+    let command_changed = !command_stamp_path.exists() ||
+                          try!(file_to_string(&command_stamp_path)) != command_stamp;
+    let mut headers_modified = None;
+    for path in &headers {
+      let time = try!(modified(path));
+      if headers_modified.map_or(true, |x| time > x) {
+        headers_modified = Some(time);
+      }
+    }
+
+    let mut jobs = Vec::new();
+    for source in try!(self.source_paths()) {
+      let stem = try!(os_str_to_str(try!(source.file_stem()
+          .chain_err(|| format!("invalid source path: {}", source.display())))))
+        .to_string();
+      let object = self.build_dir.with_added(format!("{}.{}", stem, object_extension));
+      let up_to_date = !command_changed && object.exists() && {
+        let object_modified = try!(modified(&object));
+        object_modified >= try!(modified(&source)) &&
+        headers_modified.map_or(true, |x| object_modified >= x)
+      };
+      jobs.push((source, object, up_to_date));
+    }
+    if command_changed && command_stamp_path.exists() {
+      // the command must not be saved until all objects are rebuilt
+      try!(remove_file(&command_stamp_path));
+    }
+    log::info(format!("Compiling {} of {} C++ files",
+                      jobs.iter().filter(|x| !x.2).count(),
+                      jobs.len()));
+    let compile = |&(ref source, ref object, up_to_date): &(PathBuf, PathBuf, bool)| -> Result<()> {
+      if up_to_date {
+        return Ok(());
+      }
+      let mut command = if let Some(launcher) = self.compiler_launcher {
+        let mut command = Command::new(launcher);
+        command.arg(&compiler);
+        command
+      } else {
+        Command::new(&compiler)
+      };
+      command.args(&args).arg(source);
+      if target.is_msvc() {
+        command.arg(format!("/Fo{}", try!(path_to_str(object))));
+      } else {
+        command.arg("-o").arg(object);
+      }
+      run_command(&mut command, false, self.pipe_output)
+        .map(|_| ())
+        .chain_err(|| format!("failed to compile {}", source.display()))
+    };
+    for result in parallel_map(&jobs, self.num_jobs as usize, compile) {
+      try!(result);
+    }
+    try!(try!(create_file(&command_stamp_path)).write(&command_stamp));
+
+    let lib_file_name = if target.is_msvc() {
+      format!("{}.lib", self.lib_name)
+    } else {
+      format!("lib{}.a", self.lib_name)
+    };
+    let lib_path = self.build_dir.with_added(&lib_file_name);
+    // ar adds files to the existing archive
+    if lib_path.exists() {
+      try!(remove_file(&lib_path));
+    }
+    let mut archive_command = Command::new(&archiver);
+    if target.is_msvc() {
+      archive_command.arg("/NOLOGO").arg(format!("/OUT:{}", try!(path_to_str(&lib_path))));
+    } else {
+      archive_command.arg("crs").arg(&lib_path);
+    }
+    for &(_, ref object, _) in &jobs {
+      archive_command.arg(object);
+    }
+    try!(run_command(&mut archive_command, false, self.pipe_output)
+      .chain_err(|| "failed to create static library"));
+
+    let install_lib_dir = self.install_dir.with_added("lib");
+    let install_include_dir = self.install_dir.with_added("include");
+    try!(create_dir_all(&install_lib_dir));
+    try!(create_dir_all(&install_include_dir));
+    try!(install_file(&lib_path, &install_lib_dir.with_added(&lib_file_name)));
+    for header in &headers {
+      let name = try!(header.file_name().chain_err(|| "invalid header path"));
+      try!(install_file(header, &install_include_dir.with_added(name)));
+    }
+    Ok(())
+  }
+}
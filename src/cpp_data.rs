@@ -4,8 +4,9 @@ use cpp_operator::CppOperator;
 use cpp_type::{CppType, CppTypeBase, CppTypeIndirection, CppTypeClassBase};
 use errors::{Result, unexpected};
 use log;
+use utils::{add_to_multihash, MapIfOk};
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet, hash_map};
 use std::iter::once;
 
 pub use serializable::{EnumValue, CppClassField, CppTypeKind, CppOriginLocation, CppVisibility,
@@ -159,7 +160,261 @@ impl CppTypeData {
   }
 }
 
+/// Indexes of a `CppData` used for adding inherited methods
+struct InheritanceIndex<'a> {
+  /// Classes directly derived from each class, with corresponding base specifiers
+  derived_classes: HashMap<&'a str, Vec<(&'a CppTypeData, &'a CppBaseSpecifier)>>,
+  /// First method of each class with each name
+  first_method_by_name: HashMap<(&'a str, &'a str), &'a CppMethod>,
+}
+
+impl<'a> InheritanceIndex<'a> {
+  fn new(data: &'a CppData) -> InheritanceIndex<'a> {
+    let mut derived_classes = HashMap::new();
+    for type1 in &data.types {
+      if let CppTypeKind::Class { ref bases, .. } = type1.kind {
+        for base in bases {
+          if let CppTypeBase::Class(CppTypeClassBase { ref name, .. }) = base.base_type.base {
+            add_to_multihash(&mut derived_classes, name.as_str(), (type1, base));
+          }
+        }
+      }
+    }
+    let mut first_method_by_name = HashMap::new();
+    for method in &data.methods {
+      if let Some(ref info) = method.class_membership {
+        first_method_by_name.entry((info.class_type.name.as_str(), method.name.as_str()))
+          .or_insert(method);
+      }
+    }
+    InheritanceIndex {
+      derived_classes: derived_classes,
+      first_method_by_name: first_method_by_name,
+    }
+  }
+}
+
+/// Groups methods of `data` that can be inherited by derived classes
+/// by class name. Constructors, destructors and assignment operators
+/// are not inherited.
+fn base_methods_by_class(data: &CppData) -> HashMap<&str, Vec<&CppMethod>> {
+  let mut result = HashMap::new();
+  for method in &data.methods {
+    if let Some(ref info) = method.class_membership {
+      if !info.kind.is_constructor() && !info.kind.is_destructor() &&
+         method.operator != Some(CppOperator::Assignment) {
+        add_to_multihash(&mut result, info.class_type.name.as_str(), method);
+      }
+    }
+  }
+  result
+}
+
 impl CppData {
+  /// Merges parse results obtained for different target platforms.
+  /// Items found on all platforms are considered platform-independent.
+  /// Other items are marked with names of platforms where they were found.
+  /// Values of an enum are merged and marked with their platforms.
+  /// Classes with different layouts on different platforms are not supported
+  /// because the generated Rust struct must have the same size on all platforms.
+  pub fn merge_platforms(data: Vec<(String, CppData)>) -> Result<CppData> {
+    let platforms_count = data.len();
+    let mut result = CppData::default();
+    let mut type_indexes = HashMap::new();
+    let mut method_indexes = HashMap::new();
+    let mut instantiations_indexes = HashMap::new();
+    for (platform, platform_data) in data {
+      for mut type1 in platform_data.types {
+        match type_indexes.entry(type1.name.clone()) {
+          hash_map::Entry::Occupied(entry) => {
+            let existing: &mut CppTypeData = &mut result.types[*entry.get()];
+            let is_compatible = match (&mut existing.kind, type1.kind) {
+              (&mut CppTypeKind::Enum { ref mut values, ref underlying_type, ref is_scoped },
+               CppTypeKind::Enum { values: new_values,
+                                   underlying_type: new_underlying_type,
+                                   is_scoped: new_is_scoped }) => {
+                if underlying_type == &new_underlying_type && is_scoped == &new_is_scoped {
+                  for new_value in new_values {
+                    if let Some(value) = values.iter_mut()
+                      .find(|x| x.name == new_value.name && x.value == new_value.value) {
+                      value.platforms.push(platform.clone());
+                      continue;
+                    }
+                    values.push(EnumValue { platforms: vec![platform.clone()], ..new_value });
+                  }
+                  true
+                } else {
+                  false
+                }
+              }
+              (kind, new_kind) => *kind == new_kind,
+            };
+            if !is_compatible {
+              return Err(format!("Type {} differs between platforms {} and {}. Add it to \
+                                  blocked names or parse the platforms separately.",
+                                 type1.name,
+                                 existing.platforms[0],
+                                 platform)
+                .into());
+            }
+            if !existing.platforms.contains(&platform) {
+              existing.platforms.push(platform.clone());
+            }
+          }
+          hash_map::Entry::Vacant(entry) => {
+            entry.insert(result.types.len());
+            type1.platforms = vec![platform.clone()];
+            if let CppTypeKind::Enum { ref mut values, .. } = type1.kind {
+              for value in values {
+                value.platforms = vec![platform.clone()];
+              }
+            }
+            result.types.push(type1);
+          }
+        }
+      }
+      for mut method in platform_data.methods {
+        // the same method may be found in different header paths on different platforms
+        let mut key = method.clone();
+        key.origin_location = None;
+        key.declaration_code = None;
+        match method_indexes.entry(key) {
+          hash_map::Entry::Occupied(entry) => {
+            let existing: &mut CppMethod = &mut result.methods[*entry.get()];
+            if !existing.platforms.contains(&platform) {
+              existing.platforms.push(platform.clone());
+            }
+          }
+          hash_map::Entry::Vacant(entry) => {
+            entry.insert(result.methods.len());
+            method.platforms = vec![platform.clone()];
+            result.methods.push(method);
+          }
+        }
+      }
+      for item in platform_data.skipped_items {
+        if !result.skipped_items.contains(&item) {
+          result.skipped_items.push(item);
+        }
+      }
+      for mut item in platform_data.template_instantiations {
+        for ins in &mut item.instantiations {
+          ins.platforms = vec![platform.clone()];
+        }
+        match instantiations_indexes.entry(item.class_name.clone()) {
+          hash_map::Entry::Occupied(entry) => {
+            let existing: &mut CppTemplateInstantiations =
+              &mut result.template_instantiations[*entry.get()];
+            for ins in item.instantiations {
+              if let Some(existing_ins) = existing.instantiations
+                .iter_mut()
+                .find(|x| x.template_arguments == ins.template_arguments) {
+                if existing_ins.size != ins.size || existing_ins.alignment != ins.alignment {
+                  return Err(format!("Layout of {}<{}> differs between platforms {} and {}",
+                                     item.class_name,
+                                     try!(ins.template_arguments
+                                         .iter()
+                                         .map_if_ok(|x| x.to_cpp_code(None)))
+                                       .join(", "),
+                                     existing_ins.platforms[0],
+                                     platform)
+                    .into());
+                }
+                existing_ins.platforms.push(platform.clone());
+                continue;
+              }
+              existing.instantiations.push(ins);
+            }
+          }
+          hash_map::Entry::Vacant(entry) => {
+            entry.insert(result.template_instantiations.len());
+            result.template_instantiations.push(item);
+          }
+        }
+      }
+    }
+    for type1 in &mut result.types {
+      let type_platforms_count = type1.platforms.len();
+      if let CppTypeKind::Enum { ref mut values, .. } = type1.kind {
+        for value in values {
+          if value.platforms.len() == type_platforms_count {
+            value.platforms.clear();
+          }
+        }
+      }
+      if type1.platforms.len() == platforms_count {
+        type1.platforms.clear();
+      }
+    }
+    for item in &mut result.template_instantiations {
+      for ins in &mut item.instantiations {
+        if ins.platforms.len() == platforms_count {
+          ins.platforms.clear();
+        }
+      }
+    }
+    for method in &mut result.methods {
+      if method.platforms.len() == platforms_count {
+        method.platforms.clear();
+      }
+    }
+    Ok(result)
+  }
+
+  /// Restricts platforms of class methods to platforms of their classes
+  /// and template instantiations.
+  /// Methods that are not available on any platform are removed.
+  pub fn propagate_platforms(&mut self) {
+    let type_platforms: HashMap<_, _> = self.types
+      .iter()
+      .filter(|t| !t.platforms.is_empty())
+      .map(|t| (t.name.clone(), t.platforms.clone()))
+      .collect();
+    let mut instantiation_platforms = Vec::new();
+    for item in &self.template_instantiations {
+      for ins in &item.instantiations {
+        if !ins.platforms.is_empty() {
+          instantiation_platforms.push((CppTypeClassBase {
+                                          name: item.class_name.clone(),
+                                          template_arguments: Some(ins.template_arguments
+                                            .clone()),
+                                        },
+                                        ins.platforms.clone()));
+        }
+      }
+    }
+    if type_platforms.is_empty() && instantiation_platforms.is_empty() {
+      return;
+    }
+    let mut methods = Vec::new();
+    'methods: for mut method in ::std::mem::replace(&mut self.methods, Vec::new()) {
+      let mut restrictions = Vec::new();
+      if let Some(ref info) = method.class_membership {
+        if let Some(platforms) = type_platforms.get(&info.class_type.name) {
+          restrictions.push(platforms.clone());
+        }
+        if let Some(&(_, ref platforms)) = instantiation_platforms.iter()
+          .find(|&&(ref class_type, _)| class_type == &info.class_type) {
+          restrictions.push(platforms.clone());
+        }
+      }
+      for class_platforms in restrictions {
+        if method.platforms.is_empty() {
+          method.platforms = class_platforms;
+        } else {
+          method.platforms.retain(|p| class_platforms.contains(p));
+          if method.platforms.is_empty() {
+            log::warning(format!("Method is not available on any platform: {}",
+                                 method.short_text()));
+            continue 'methods;
+          }
+        }
+      }
+      methods.push(method);
+    }
+    self.methods = methods;
+  }
+
   /// Adds destructors for every class that does not have explicitly
   /// defined destructor, allowing to create wrappings for
   /// destructors implicitly available in C++.
@@ -200,6 +455,8 @@ impl CppData {
             declaration_code: None,
             inherited_from: None,
             inheritance_chain: Vec::new(),
+            platforms: Vec::new(),
+            is_deprecated: false,
           });
         }
       }
@@ -208,107 +465,106 @@ impl CppData {
   }
 
   /// Helper function that performs a portion of add_inherited_methods implementation.
+  /// Returns methods inherited from `all_base_methods` of class `base_name`
+  /// by its derived classes (directly or indirectly).
   fn inherited_methods_from(&self,
+                            index: &InheritanceIndex,
                             base_name: &str,
                             all_base_methods: &[&CppMethod])
                             -> Result<Vec<CppMethod>> {
-    // TODO: speed up this method (#12)
     let mut new_methods = Vec::new();
-    {
-      for type1 in &self.types {
-        if let CppTypeKind::Class { ref bases, ref using_directives, .. } = type1.kind {
-          for base in bases {
-            if let CppTypeBase::Class(CppTypeClassBase { ref name, ref template_arguments }) =
-                   base.base_type.base {
-              if name == base_name {
-                log::noisy(format!("Adding inherited methods from {} to {}",
-                                   base_name,
-                                   type1.name));
-                let derived_name = &type1.name;
-                let base_template_arguments = template_arguments;
-                let base_methods = all_base_methods.into_iter().filter(|method| {
-                  if let Some(ref info) = method.class_membership {
-                    &info.class_type.template_arguments == base_template_arguments
+    let derived_classes = match index.derived_classes.get(base_name) {
+      Some(r) => r,
+      None => return Ok(new_methods),
+    };
+    for &(type1, base) in derived_classes {
+      if let CppTypeKind::Class { ref using_directives, .. } = type1.kind {
+        if let CppTypeBase::Class(CppTypeClassBase { ref template_arguments, .. }) =
+               base.base_type.base {
+          log::noisy(format!("Adding inherited methods from {} to {}",
+                             base_name,
+                             type1.name));
+          let derived_name = &type1.name;
+          let base_template_arguments = template_arguments;
+          let base_methods = all_base_methods.into_iter().filter(|method| {
+            if let Some(ref info) = method.class_membership {
+              &info.class_type.template_arguments == base_template_arguments
+            } else {
+              false
+            }
+          });
+          let mut current_new_methods = Vec::new();
+          for base_class_method in base_methods {
+            let mut using_directive_enables = false;
+            let mut using_directive_disables = false;
+            for dir in using_directives {
+              if &dir.method_name == &base_class_method.name {
+                if &dir.class_name == base_name {
+                  log::noisy(format!("UsingDirective enables inheritance of {}",
+                                     base_class_method.short_text()));
+                  using_directive_enables = true;
+                } else {
+                  log::noisy(format!("UsingDirective disables inheritance of {}",
+                                     base_class_method.short_text()));
+                  using_directive_disables = true;
+                }
+              }
+            }
+            if using_directive_disables {
+              continue;
+            }
+
+            let mut ok = true;
+            if let Some(method) = index.first_method_by_name
+              .get(&(derived_name.as_str(), base_class_method.name.as_str())) {
+              // without using directive, any method with the same name
+              // disables inheritance of base class method;
+              // with using directive, only method with the same arguments
+              // disables inheritance of base class method.
+              if !using_directive_enables || method.argument_types_equal(base_class_method) {
+                log::noisy("Method is not added because it's overriden in derived class");
+                log::noisy(format!("Base method: {}", base_class_method.short_text()));
+                log::noisy(format!("Derived method: {}\n", method.short_text()));
+                ok = false;
+              }
+            }
+            if ok {
+              let mut new_method: CppMethod = (*base_class_method).clone();
+              if let Some(ref mut info) = new_method.class_membership {
+                info.class_type = try!(type1.default_class_type());
+              } else {
+                return Err(unexpected("no class membership").into());
+              }
+              new_method.include_file = type1.include_file.clone();
+              new_method.origin_location = None;
+              new_method.declaration_code = None;
+              if new_method.inherited_from.is_none() {
+                new_method.inherited_from = Some(CppMethodInheritedFrom {
+                  doc_id: base_class_method.doc_id(),
+                  short_text: base_class_method.short_text(),
+                  declaration_code: base_class_method.declaration_code.clone(),
+                  class_type: if let Some(ref info) = base_class_method.class_membership {
+                    info.class_type.clone()
                   } else {
-                    false
-                  }
+                    return Err(unexpected("no class membership").into());
+                  },
                 });
-                let mut current_new_methods = Vec::new();
-                for base_class_method in base_methods {
-                  let mut using_directive_enables = false;
-                  let mut using_directive_disables = false;
-                  for dir in using_directives {
-                    if &dir.method_name == &base_class_method.name {
-                      if &dir.class_name == base_name {
-                        log::noisy(format!("UsingDirective enables inheritance of {}",
-                                           base_class_method.short_text()));
-                        using_directive_enables = true;
-                      } else {
-                        log::noisy(format!("UsingDirective disables inheritance of {}",
-                                           base_class_method.short_text()));
-                        using_directive_disables = true;
-                      }
-                    }
-                  }
-                  if using_directive_disables {
-                    continue;
-                  }
-
-                  let mut ok = true;
-                  for method in &self.methods {
-                    if method.class_name() == Some(derived_name) &&
-                       method.name == base_class_method.name {
-                      // without using directive, any method with the same name
-                      // disables inheritance of base class method;
-                      // with using directive, only method with the same arguments
-                      // disables inheritance of base class method.
-                      if !using_directive_enables ||
-                         method.argument_types_equal(base_class_method) {
-                        log::noisy("Method is not added because it's overriden in derived class");
-                        log::noisy(format!("Base method: {}", base_class_method.short_text()));
-                        log::noisy(format!("Derived method: {}\n", method.short_text()));
-                        ok = false;
-                      }
-                      break;
-                    }
-                  }
-                  if ok {
-                    let mut new_method: CppMethod = (*base_class_method).clone();
-                    if let Some(ref mut info) = new_method.class_membership {
-                      info.class_type = try!(type1.default_class_type());
-                    } else {
-                      return Err(unexpected("no class membership").into());
-                    }
-                    new_method.include_file = type1.include_file.clone();
-                    new_method.origin_location = None;
-                    new_method.declaration_code = None;
-                    if new_method.inherited_from.is_none() {
-                      new_method.inherited_from = Some(CppMethodInheritedFrom {
-                        doc_id: base_class_method.doc_id(),
-                        short_text: base_class_method.short_text(),
-                        declaration_code: base_class_method.declaration_code.clone(),
-                        class_type: if let Some(ref info) = base_class_method.class_membership {
-                          info.class_type.clone()
-                        } else {
-                          return Err(unexpected("no class membership").into());
-                        },
-                      });
-                    }
-                    new_method.inheritance_chain.push(base.clone());
-                    log::noisy(format!("Method added: {}", new_method.short_text()));
-                    log::noisy(format!("Base method: {} ({:?})\n",
-                                       base_class_method.short_text(),
-                                       base_class_method.origin_location));
-                    current_new_methods.push(new_method.clone());
-                  }
-                }
-                new_methods.append(&mut try!(self.inherited_methods_from(derived_name,
-                                                                         &current_new_methods.iter()
-                                                                           .collect::<Vec<_>>())));
-                new_methods.append(&mut current_new_methods);
               }
+              new_method.inheritance_chain.push(base.clone());
+              log::noisy(format!("Method added: {}", new_method.short_text()));
+              log::noisy(format!("Base method: {} ({:?})\n",
+                                 base_class_method.short_text(),
+                                 base_class_method.origin_location));
+              current_new_methods.push(new_method);
             }
           }
+          if !current_new_methods.is_empty() {
+            new_methods.append(&mut try!(self.inherited_methods_from(index,
+                                                                     derived_name,
+                                                                     &current_new_methods.iter()
+                                                                       .collect::<Vec<_>>())));
+            new_methods.append(&mut current_new_methods);
+          }
         }
       }
     }
@@ -323,44 +579,57 @@ impl CppData {
   pub fn add_inherited_methods(&mut self, dependencies: &[&CppData]) -> Result<()> {
     log::info("Adding inherited methods");
     let mut all_new_methods = Vec::new();
-    for (is_self, cpp_data) in dependencies.into_iter()
-      .map(|x| (false, x))
-      .chain(once((true, &(self as &_)))) {
-      for type1 in &cpp_data.types {
-        if type1.is_class() {
-          let mut interesting_cpp_datas: Vec<&CppData> = vec![cpp_data];
-          if !is_self {
-            interesting_cpp_datas.push(self);
-          }
-          for cpp_data2 in interesting_cpp_datas {
-            let base_methods = cpp_data2.methods
-              .iter()
-              .filter(|method| {
-                if let Some(ref info) = method.class_membership {
-                  &info.class_type.name == &type1.name && !info.kind.is_constructor() &&
-                  !info.kind.is_destructor() &&
-                  method.operator != Some(CppOperator::Assignment)
-                } else {
-                  false
-                }
-              });
-            all_new_methods.append(&mut try!(self.inherited_methods_from(&type1.name,
-                                                                    &base_methods.collect::<Vec<_>>())));
+    {
+      let index = InheritanceIndex::new(self);
+      let dependencies_base_methods: Vec<_> =
+        dependencies.iter().map(|x| base_methods_by_class(x)).collect();
+      let self_base_methods = base_methods_by_class(self);
+      for (is_self, cpp_data, base_methods) in dependencies.iter()
+        .zip(dependencies_base_methods.iter())
+        .map(|(x, y)| (false, *x, y))
+        .chain(once((true, &*self, &self_base_methods))) {
+        for type1 in &cpp_data.types {
+          if type1.is_class() {
+            let mut interesting_base_methods = vec![base_methods];
+            if !is_self {
+              interesting_base_methods.push(&self_base_methods);
+            }
+            for base_methods in interesting_base_methods {
+              if let Some(methods) = base_methods.get(type1.name.as_str()) {
+                all_new_methods.append(&mut try!(self.inherited_methods_from(&index,
+                                                                             &type1.name,
+                                                                             methods)));
+              }
+            }
           }
         }
       }
     }
-    while let Some(method) = all_new_methods.pop() {
-      let mut duplicates = Vec::new();
-      while let Some(index) = all_new_methods.iter()
-        .position(|m| m.class_name() == method.class_name() && m.name == method.name) {
-        duplicates.push(all_new_methods.remove(index));
+    // Methods with the same class and name are processed together. Groups are
+    // processed in reverse order of positions of their last methods.
+    let mut groups: Vec<(usize, Vec<CppMethod>)> = Vec::new();
+    {
+      let mut group_indexes: HashMap<(Option<String>, String), usize> = HashMap::new();
+      for (position, method) in all_new_methods.into_iter().enumerate() {
+        let key = (method.class_name().cloned(), method.name.clone());
+        match group_indexes.entry(key) {
+          hash_map::Entry::Occupied(entry) => {
+            let group = &mut groups[*entry.get()];
+            group.0 = position;
+            group.1.push(method);
+          }
+          hash_map::Entry::Vacant(entry) => {
+            entry.insert(groups.len());
+            groups.push((position, vec![method]));
+          }
+        }
       }
-      if duplicates.is_empty() {
-        self.methods.push(method);
+    }
+    groups.sort_by(|a, b| b.0.cmp(&a.0));
+    for (_, mut duplicates) in groups {
+      if duplicates.len() == 1 {
+        self.methods.append(&mut duplicates);
       } else {
-        duplicates.push(method);
-
         let mut allow_method = false;
 
         let mut lowest_visibility = CppVisibility::Public;
@@ -682,6 +951,7 @@ impl CppData {
     self.generate_methods_with_omitted_args();
     try!(self.instantiate_templates(dependencies));
     try!(self.add_inherited_methods(dependencies));
+    self.propagate_platforms();
     Ok(())
   }
 }
@@ -145,7 +145,7 @@ pub enum IndirectionChange {
   /// C++ argument is a reference (like QPoint&)
   /// and FFI argument is a pointer (like QPoint*)
   ReferenceToPointer,
-  /// C++ argument is QFlags<T>
+  /// C++ argument is a flags container (like QFlags<T>)
   /// and FFI argument is uint
   QFlagsToUInt,
 }
@@ -1,18 +1,19 @@
 use caption_strategy::MethodCaptionStrategy;
+use coverage_report::{SkippedItem, SkipStage};
 use cpp_data::{CppData, CppVisibility};
 use cpp_ffi_data::{CppAndFfiMethod, c_base_name};
 use cpp_method::{CppMethod, CppMethodKind};
 use errors::{Result, ChainErr, unexpected};
 use log;
-use utils::add_to_multihash;
+use utils::{add_to_multihash, parallel_map};
 use config::CppFfiGeneratorFilterFn;
 
 use std::collections::{HashSet, HashMap};
 
 struct CGenerator<'a> {
   cpp_data: &'a CppData,
-  c_lib_name: String,
   filters: Vec<&'a Box<CppFfiGeneratorFilterFn>>,
+  flags_containers: &'a [String],
 }
 
 #[derive(Debug, Clone)]
@@ -22,43 +23,90 @@ pub struct CppFfiHeaderData {
   pub methods: Vec<CppAndFfiMethod>,
 }
 
+impl CppFfiHeaderData {
+  /// Returns platforms on which at least one method of this header is available.
+  /// Returns empty vector if some of the methods are available on all platforms.
+  pub fn platforms(&self) -> Vec<String> {
+    let mut result: Vec<String> = Vec::new();
+    for method in &self.methods {
+      if method.cpp_method.platforms.is_empty() {
+        return Vec::new();
+      }
+      for platform in &method.cpp_method.platforms {
+        if !result.contains(platform) {
+          result.push(platform.clone());
+        }
+      }
+    }
+    result
+  }
+}
+
 pub struct CppAndFfiData {
   pub cpp_data: CppData,
   pub cpp_ffi_headers: Vec<CppFfiHeaderData>,
 }
 
-/// Runs FFI generator
+/// Runs FFI generator. Instantiations of class templates listed in
+/// `flags_containers` are passed through FFI as integers, and methods
+/// of these templates are not wrapped. Headers are processed
+/// in up to `num_jobs` threads.
 pub fn run(cpp_data: &CppData,
            c_lib_name: String,
-           filters: Vec<&Box<CppFfiGeneratorFilterFn>>)
+           filters: Vec<&Box<CppFfiGeneratorFilterFn>>,
+           flags_containers: &[String],
+           num_jobs: usize,
+           skipped_items: &mut Vec<SkippedItem>)
            -> Result<Vec<CppFfiHeaderData>> {
   let generator = CGenerator {
     cpp_data: cpp_data,
-    c_lib_name: c_lib_name,
     filters: filters,
+    flags_containers: flags_containers,
   };
 
-  let mut c_headers = Vec::new();
   let mut include_name_list: Vec<_> = generator.cpp_data.all_include_files().into_iter().collect();
   include_name_list.sort();
 
-  for include_file in &include_name_list {
+  // filters are not required to be thread safe,
+  // so they are applied before processing headers in parallel
+  let mut headers = Vec::new();
+  for include_file in include_name_list {
     let mut include_file_base_name = include_file.clone();
 
     if let Some(index) = include_file_base_name.find('.') {
       include_file_base_name = include_file_base_name[0..index].to_string();
     }
-    let methods = try!(generator.process_methods(include_file,
-                                                 &include_file_base_name,
-                                                 generator.cpp_data
-                                                   .methods
-                                                   .iter()
-                                                   .filter(|x| &x.include_file == include_file)));
+    let mut methods = Vec::new();
+    for method in generator.cpp_data.methods.iter().filter(|x| x.include_file == include_file) {
+      if let Some(reason) = try!(generator.skip_reason(method)) {
+        skipped_items.push(SkippedItem::method(method, SkipStage::FfiGeneratorFilter, reason));
+      } else {
+        methods.push(method);
+      }
+    }
+    headers.push((include_file, include_file_base_name, methods));
+  }
+
+  let results = parallel_map(&headers, num_jobs, |header| {
+    let (ref include_file, ref include_file_base_name, ref methods) = *header;
+    let mut header_skipped_items = Vec::new();
+    process_methods(&c_lib_name,
+                    flags_containers,
+                    include_file,
+                    include_file_base_name,
+                    methods,
+                    &mut header_skipped_items)
+      .map(|methods| (methods, header_skipped_items))
+  });
+  let mut c_headers = Vec::new();
+  for ((include_file, include_file_base_name, _), result) in headers.into_iter().zip(results) {
+    let (methods, mut header_skipped_items) = try!(result);
+    skipped_items.append(&mut header_skipped_items);
     if methods.is_empty() {
       log::info(format!("Skipping empty include file {}", include_file));
     } else {
       c_headers.push(CppFfiHeaderData {
-        include_file: include_file.clone(),
+        include_file: include_file,
         include_file_base_name: include_file_base_name,
         methods: methods,
       });
@@ -71,19 +119,19 @@ pub fn run(cpp_data: &CppData,
 }
 
 impl<'a> CGenerator<'a> {
-  /// Returns false if the method is excluded from processing
-  /// for some reason
-  fn should_process_method(&self, method: &CppMethod) -> Result<bool> {
+  /// Returns the reason if the method is excluded from processing,
+  /// or `None` if the method should be processed
+  fn skip_reason(&self, method: &CppMethod) -> Result<Option<&'static str>> {
     let class_name = method.class_name().unwrap_or(&String::new()).clone();
     for filter in &self.filters {
       let allowed = try!(filter(method).chain_err(|| "cpp_ffi_generator_filter failed"));
       if !allowed {
         log::info(format!("Skipping blacklisted method: \n{}\n", method.short_text()));
-        return Ok(false);
+        return Ok(Some("blocked by cpp_ffi_generator_filter"));
       }
     }
-    if class_name == "QFlags" {
-      return Ok(false);
+    if self.flags_containers.iter().any(|x| x == &class_name) {
+      return Ok(Some("flags containers are passed as integers"));
     }
     if let Some(ref membership) = method.class_membership {
       if membership.kind == CppMethodKind::Constructor &&
@@ -91,123 +139,126 @@ impl<'a> CGenerator<'a> {
         log::noisy(format!("Method is skipped:\n{}\nConstructors are not allowed for abstract \
                             classes.\n",
                            method.short_text()));
-        return Ok(false);
+        return Ok(Some("constructors are not allowed for abstract classes"));
       }
       if membership.visibility == CppVisibility::Private {
-        return Ok(false);
+        return Ok(Some("private method"));
       }
       if membership.visibility == CppVisibility::Protected {
         log::noisy(format!("Skipping protected method: \n{}\n", method.short_text()));
-        return Ok(false);
+        return Ok(Some("protected method"));
       }
       if membership.is_signal {
         log::warning(format!("Skipping signal: \n{}\n", method.short_text()));
-        return Ok(false);
+        return Ok(Some("signals are not supported"));
       }
     }
     if method.template_arguments.is_some() {
       log::noisy(format!("Skipping template method: \n{}\n", method.short_text()));
-      return Ok(false);
+      return Ok(Some("template methods are not supported"));
     }
     if method.template_arguments_values.is_some() {
       // TODO: re-enable after template test compilation (#24) is implemented
       log::noisy(format!("Skipping template method: \n{}\n", method.short_text()));
-      return Ok(false);
+      return Ok(Some("template method instantiations are not supported"));
     }
     if method.all_involved_types()
       .iter()
       .any(|x| x.base.is_or_contains_template_parameter()) {
       log::noisy(format!("Skipping method containing template parameters: \n{}\n",
                          method.short_text()));
-      return Ok(false);
+      return Ok(Some("method's types contain template parameters"));
     }
-    Ok(true)
+    Ok(None)
   }
+}
 
-  /// Generates FFI wrappers for all specified methods,
-  /// resolving all name conflicts using additional method captions.
-  fn process_methods<'b, I: Iterator<Item = &'b CppMethod>>(&self,
-                                                            include_file: &str,
-                                                            include_file_base_name: &str,
-                                                            methods: I)
-                                                            -> Result<Vec<CppAndFfiMethod>> {
-    log::info(format!("Generating C++ FFI methods for header: {}", include_file));
-    let mut hash_name_to_methods: HashMap<String, Vec<_>> = HashMap::new();
-    for method in methods {
-      if !try!(self.should_process_method(method)) {
-        continue;
+/// Generates FFI wrappers for all specified methods,
+/// resolving all name conflicts using additional method captions.
+fn process_methods(c_lib_name: &str,
+                   flags_containers: &[String],
+                   include_file: &str,
+                   include_file_base_name: &str,
+                   methods: &[&CppMethod],
+                   skipped_items: &mut Vec<SkippedItem>)
+                   -> Result<Vec<CppAndFfiMethod>> {
+  log::info(format!("Generating C++ FFI methods for header: {}", include_file));
+  let mut hash_name_to_methods: HashMap<String, Vec<_>> = HashMap::new();
+  for &method in methods {
+    match method.to_ffi_signatures(flags_containers) {
+      Err(msg) => {
+        log::warning(format!("Unable to produce C function for method:\n{}\nError:{}\n",
+                             method.short_text(),
+                             msg));
+        skipped_items.push(SkippedItem::method(method, SkipStage::FfiSignature, msg.to_string()));
       }
-      match method.to_ffi_signatures() {
-        Err(msg) => {
-          log::warning(format!("Unable to produce C function for method:\n{}\nError:{}\n",
-                               method.short_text(),
-                               msg));
-        }
-        Ok(results) => {
-          for result in results {
-            match c_base_name(&result.cpp_method,
-                              &result.allocation_place,
-                              include_file_base_name) {
-              Err(msg) => {
-                log::warning(format!("Unable to produce C function for method:\n{}\nError:{}\n",
-                                     method.short_text(),
-                                     msg));
-              }
-              Ok(name) => {
+      Ok(results) => {
+        for result in results {
+          match c_base_name(&result.cpp_method,
+                            &result.allocation_place,
+                            include_file_base_name) {
+            Err(msg) => {
+              log::warning(format!("Unable to produce C function for method:\n{}\nError:{}\n",
+                                   method.short_text(),
+                                   msg));
+              skipped_items.push(SkippedItem::method(method,
+                                                     SkipStage::FfiSignature,
+                                                     msg.to_string()));
+            }
+            Ok(name) => {
 
-                add_to_multihash(&mut hash_name_to_methods,
-                                 format!("{}_{}", &self.c_lib_name, name),
-                                 result);
-              }
+              add_to_multihash(&mut hash_name_to_methods,
+                               format!("{}_{}", c_lib_name, name),
+                               result);
             }
           }
         }
       }
     }
+  }
 
-    let mut processed_methods = Vec::new();
-    for (key, mut values) in hash_name_to_methods {
-      if values.len() == 1 {
-        processed_methods.push(CppAndFfiMethod::new(values.remove(0), key.clone()));
-        continue;
-      }
-      let mut found_strategy = None;
-      for strategy in MethodCaptionStrategy::all() {
-        let mut type_captions = HashSet::new();
-        let mut ok = true;
-        for value in &values {
-          let caption = try!(value.c_signature.caption(strategy.clone()));
-          if type_captions.contains(&caption) {
-            ok = false;
-            break;
-          }
-          type_captions.insert(caption);
-        }
-        if ok {
-          found_strategy = Some(strategy);
+  let mut processed_methods = Vec::new();
+  for (key, mut values) in hash_name_to_methods {
+    if values.len() == 1 {
+      processed_methods.push(CppAndFfiMethod::new(values.remove(0), key.clone()));
+      continue;
+    }
+    let mut found_strategy = None;
+    for strategy in MethodCaptionStrategy::all() {
+      let mut type_captions = HashSet::new();
+      let mut ok = true;
+      for value in &values {
+        let caption = try!(value.c_signature.caption(strategy.clone()));
+        if type_captions.contains(&caption) {
+          ok = false;
           break;
         }
+        type_captions.insert(caption);
       }
-      if let Some(strategy) = found_strategy {
-        for x in values {
-          let caption = try!(x.c_signature.caption(strategy.clone()));
-          let final_name = if caption.is_empty() {
-            key.clone()
-          } else {
-            format!("{}_{}", key, caption)
-          };
-          processed_methods.push(CppAndFfiMethod::new(x, final_name));
-        }
-      } else {
-        log::error(format!("values dump: {:?}\n", values));
-        log::error("All type caption strategies have failed! Involved functions:");
-        for value in values {
-          log::error(format!("  {}", value.cpp_method.short_text()));
-        }
-        return Err(unexpected("all type caption strategies have failed").into());
+      if ok {
+        found_strategy = Some(strategy);
+        break;
+      }
+    }
+    if let Some(strategy) = found_strategy {
+      for x in values {
+        let caption = try!(x.c_signature.caption(strategy.clone()));
+        let final_name = if caption.is_empty() {
+          key.clone()
+        } else {
+          format!("{}_{}", key, caption)
+        };
+        processed_methods.push(CppAndFfiMethod::new(x, final_name));
+      }
+    } else {
+      log::error(format!("values dump: {:?}\n", values));
+      log::error("All type caption strategies have failed! Involved functions:");
+      for value in values {
+        log::error(format!("  {}", value.cpp_method.short_text()));
       }
+      return Err(unexpected("all type caption strategies have failed").into());
     }
-    processed_methods.sort_by(|a, b| a.c_name.cmp(&b.c_name));
-    Ok(processed_methods)
   }
+  processed_methods.sort_by(|a, b| a.c_name.cmp(&b.c_name));
+  Ok(processed_methods)
 }
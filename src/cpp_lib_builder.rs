@@ -1,5 +1,6 @@
 use errors::Result;
 use file_utils::path_to_str;
+use launcher::BuildProfile;
 use utils::{is_msvc, add_env_path_item, run_command};
 
 use std::process::Command;
@@ -12,35 +13,99 @@ pub struct CppLibBuilder<'a> {
   pub num_jobs: i32,
   pub linker_env_library_dirs: Option<&'a Vec<PathBuf>>,
   pub pipe_output: bool,
+  /// Variables passed to CMake as `-D` options
+  pub cmake_vars: Vec<(String, String)>,
+  /// Profile used to choose `CMAKE_BUILD_TYPE` if it is not set in `cmake_vars`
+  pub build_profile: BuildProfile,
+  /// CMake generator (e.g. `"Ninja"`). If `None`, the platform's default is used.
+  pub generator: Option<&'a String>,
+  /// Command prepended to compiler invokations (e.g. `"ccache"`)
+  pub compiler_launcher: Option<&'a String>,
+}
+
+/// Returns true if the build tool of `generator` accepts `-j` option.
+pub fn generator_supports_jobs(generator: &str) -> bool {
+  generator.contains("Ninja") || generator.ends_with("JOM") ||
+  (generator.ends_with("Makefiles") && !generator.starts_with("NMake"))
+}
+
+/// Returns value of `CMAKE_BUILD_TYPE` set by the user in `cmake_vars`, if any.
+/// If the variable is set multiple times, the last value is used, as CMake does.
+pub fn user_build_type(cmake_vars: &[(String, String)]) -> Option<&String> {
+  cmake_vars.iter().rev().find(|x| x.0 == "CMAKE_BUILD_TYPE").map(|x| &x.1)
 }
 
 impl<'a> CppLibBuilder<'a> {
-  pub fn run(self) -> Result<()> {
-    let mut cmake_command = Command::new("cmake");
-    cmake_command.arg(self.cmake_source_dir)
-      .arg(format!("-DCMAKE_INSTALL_PREFIX={}",
-                   try!(path_to_str(&self.install_dir))))
-      .current_dir(self.build_dir);
+  /// Returns the CMake generator that should be used.
+  fn generator(&self) -> Option<String> {
+    match self.generator {
+      Some(generator) => Some(generator.clone()),
+      None if is_msvc() => Some("NMake Makefiles".to_string()),
+      None => None,
+    }
+  }
+
+  /// Returns the build type passed to CMake and the build tool.
+  fn build_type(&self) -> String {
+    if let Some(build_type) = user_build_type(&self.cmake_vars) {
+      return build_type.clone();
+    }
     if is_msvc() {
-      cmake_command.arg("-G").arg("NMake Makefiles");
       // Rust always links to release version of MSVC runtime, so
       // link will fail if C library is built in debug mode
-      cmake_command.arg("-DCMAKE_BUILD_TYPE=Release");
+      "Release".to_string()
+    } else {
+      match self.build_profile {
+        BuildProfile::Debug => "Debug".to_string(),
+        BuildProfile::Release => "Release".to_string(),
+      }
     }
-    // TODO: enable release mode on other platforms if cargo is in release mode
-    // (maybe build C library in both debug and release in separate folders)
-    try!(run_command(&mut cmake_command, false, self.pipe_output));
+  }
 
-    let make_command_name = if is_msvc() { "nmake" } else { "make" }.to_string();
-    let mut make_args = Vec::new();
-    if !is_msvc() {
-      // nmake doesn't support multiple jobs
-      // TODO: allow to use jom
-      make_args.push(format!("-j{}", self.num_jobs));
+  /// Returns arguments of the CMake configuration command.
+  pub fn cmake_args(&self) -> Result<Vec<String>> {
+    let mut args = vec![try!(path_to_str(self.cmake_source_dir)).to_string(),
+                        format!("-DCMAKE_INSTALL_PREFIX={}",
+                                try!(path_to_str(&self.install_dir))),
+                        format!("-DCMAKE_BUILD_TYPE={}", self.build_type())];
+    if let Some(generator) = self.generator() {
+      args.push("-G".to_string());
+      args.push(generator);
+    }
+    if let Some(launcher) = self.compiler_launcher {
+      args.push(format!("-DCMAKE_CXX_COMPILER_LAUNCHER={}", launcher));
     }
-    make_args.push("install".to_string());
-    let mut make_command = Command::new(make_command_name);
-    make_command.args(&make_args)
+    // user-defined variables are added last, so they can override the values above
+    for &(ref name, ref value) in &self.cmake_vars {
+      args.push(format!("-D{}={}", name, value));
+    }
+    Ok(args)
+  }
+
+  /// Returns arguments of the CMake command that runs the build tool.
+  pub fn build_args(&self) -> Vec<String> {
+    // build tool is invoked through CMake, so any generator can be used
+    let mut args: Vec<String> = ["--build", ".", "--target", "install", "--config"]
+      .iter()
+      .map(|x| x.to_string())
+      .collect();
+    args.push(self.build_type());
+    // nmake doesn't support multiple jobs
+    if self.generator().map_or(true, |x| generator_supports_jobs(&x)) {
+      args.push("--".to_string());
+      args.push(format!("-j{}", self.num_jobs));
+    }
+    args
+  }
+
+  pub fn run(self) -> Result<()> {
+    let mut cmake_command = Command::new("cmake");
+    cmake_command.args(&try!(self.cmake_args()))
+      .current_dir(self.build_dir);
+    try!(run_command(&mut cmake_command, false, self.pipe_output));
+
+    let mut make_command = Command::new("cmake");
+    make_command.args(&self.build_args())
       .current_dir(self.build_dir);
     if let Some(linker_env_library_dirs) = self.linker_env_library_dirs {
       if !linker_env_library_dirs.is_empty() {
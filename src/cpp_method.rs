@@ -63,11 +63,11 @@ impl CppMethod {
 
   /// Checks if this method would need
   /// to have 2 wrappers with 2 different return value allocation places
-  pub fn needs_allocation_place_variants(&self) -> bool {
+  pub fn needs_allocation_place_variants(&self, flags_containers: &[String]) -> bool {
     if self.is_constructor() || self.is_destructor() {
       return true;
     }
-    if self.return_type.needs_allocation_place_variants() {
+    if self.return_type.needs_allocation_place_variants(flags_containers) {
       return true;
     }
     false
@@ -77,8 +77,12 @@ impl CppMethod {
   /// - converts all types to FFI types;
   /// - adds "this" argument explicitly if present;
   /// - adds "output" argument for return value if allocation_place is Stack.
+  ///
+  /// `flags_containers` is the list of class templates that are
+  /// passed through FFI as integers (see `CppType::to_cpp_ffi_type`).
   pub fn c_signature(&self,
-                     allocation_place: ReturnValueAllocationPlace)
+                     allocation_place: ReturnValueAllocationPlace,
+                     flags_containers: &[String])
                      -> Result<CppFfiFunctionSignature> {
     if self.allows_variadic_arguments {
       return Err("Variable arguments are not supported".into());
@@ -97,13 +101,14 @@ impl CppMethod {
               is_const2: false,
               indirection: CppTypeIndirection::Ptr,
             }
-            .to_cpp_ffi_type(CppTypeRole::NotReturnType)),
+            .to_cpp_ffi_type(CppTypeRole::NotReturnType, flags_containers)),
           meaning: CppFfiArgumentMeaning::This,
         });
       }
     }
     for (index, arg) in self.arguments.iter().enumerate() {
-      let c_type = try!(arg.argument_type.to_cpp_ffi_type(CppTypeRole::NotReturnType));
+      let c_type = try!(arg.argument_type
+        .to_cpp_ffi_type(CppTypeRole::NotReturnType, flags_containers));
       r.arguments.push(CppFfiFunctionArgument {
         name: arg.name.clone(),
         argument_type: c_type,
@@ -120,8 +125,9 @@ impl CppMethod {
     } else {
       self.return_type.clone()
     };
-    let c_type = try!(real_return_type.to_cpp_ffi_type(CppTypeRole::ReturnType));
-    if real_return_type.needs_allocation_place_variants() {
+    let c_type = try!(real_return_type.to_cpp_ffi_type(CppTypeRole::ReturnType,
+                                                                flags_containers));
+    if real_return_type.needs_allocation_place_variants(flags_containers) {
       match allocation_place {
         ReturnValueAllocationPlace::Stack => {
           r.arguments.push(CppFfiFunctionArgument {
@@ -147,15 +153,17 @@ impl CppMethod {
 
   /// Generates either one or two FFI signatures for this method,
   /// depending on its return type.
-  pub fn to_ffi_signatures(&self) -> Result<Vec<CppMethodWithFfiSignature>> {
-    let places = if self.needs_allocation_place_variants() {
+  pub fn to_ffi_signatures(&self,
+                           flags_containers: &[String])
+                           -> Result<Vec<CppMethodWithFfiSignature>> {
+    let places = if self.needs_allocation_place_variants(flags_containers) {
       vec![ReturnValueAllocationPlace::Heap, ReturnValueAllocationPlace::Stack]
     } else {
       vec![ReturnValueAllocationPlace::NotApplicable]
     };
     let mut results = Vec::new();
     for place in places {
-      let c_signature = try!(self.c_signature(place.clone()));
+      let c_signature = try!(self.c_signature(place.clone(), flags_containers));
       results.push(CppMethodWithFfiSignature {
         cpp_method: self.clone(),
         allocation_place: place,
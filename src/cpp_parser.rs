@@ -1,3 +1,4 @@
+use coverage_report::{SkippedItem, SkipStage};
 use cpp_data::{CppData, CppTypeData, CppTypeKind, CppClassField, EnumValue, CppOriginLocation,
                CppVisibility, CppTemplateInstantiation, CppTemplateInstantiations,
                CppClassUsingDirective, CppBaseSpecifier, TemplateArgumentsDeclaration};
@@ -104,6 +105,18 @@ fn get_full_name(entity: Entity) -> Result<String> {
   }
 }
 
+/// Returns true if `entity` is an enum declared
+/// as `enum class` or `enum struct`.
+fn is_scoped_enum(entity: Entity) -> bool {
+  if let Some(range) = entity.get_range() {
+    let tokens = range.tokenize();
+    tokens.len() > 1 && tokens[0].get_spelling() == "enum" &&
+    (tokens[1].get_spelling() == "class" || tokens[1].get_spelling() == "struct")
+  } else {
+    false
+  }
+}
+
 #[derive(Clone, Debug)]
 pub struct CppParserConfig {
   /// Include dirs passed to clang
@@ -216,7 +229,7 @@ pub fn run(config: CppParserConfig, dependencies_data: &[&CppData]) -> Result<Cp
     Ok((parser, methods))
   }));
   log::info("Checking integrity...");
-  let (good_methods, good_types) = parser.check_integrity(methods);
+  let (good_methods, good_types, skipped_items) = parser.check_integrity(methods);
   parser.types = good_types;
   log::info("Searching for template instantiations...");
   let template_instantiations = parser.find_template_instantiations(&good_methods);
@@ -274,6 +287,13 @@ pub fn run(config: CppParserConfig, dependencies_data: &[&CppData]) -> Result<Cp
                                template_args)
               .into());
           }
+          let alignment = fields[field_num].alignment;
+          if alignment.is_none() {
+            return Err(format!("AllFields parse result: failed to get alignment of {}<{:?}>",
+                               class_name,
+                               template_args)
+              .into());
+          }
           if !final_template_instantiations.iter().any(|x| &x.class_name == class_name) {
             // first encounter of this template class
             if let Some(type_info) = parser.find_type(|x| &x.name == class_name) {
@@ -310,6 +330,12 @@ pub fn run(config: CppParserConfig, dependencies_data: &[&CppData]) -> Result<Cp
               } else {
                 return Err("template instantiation's size is None".into());
               },
+              alignment: if let Some(alignment) = alignment {
+                alignment
+              } else {
+                return Err("template instantiation's alignment is None".into());
+              },
+              platforms: Vec::new(),
             });
           } else {
             return Err(unexpected("final_template_instantiations must contain this class now")
@@ -327,6 +353,7 @@ pub fn run(config: CppParserConfig, dependencies_data: &[&CppData]) -> Result<Cp
     types: parser.types,
     methods: good_methods,
     template_instantiations: final_template_instantiations,
+    skipped_items: skipped_items,
   })
 }
 
@@ -1150,6 +1177,8 @@ impl<'a> CppParser<'a> {
       declaration_code: declaration_code,
       inherited_from: None,
       inheritance_chain: Vec::new(),
+      platforms: Vec::new(),
+      is_deprecated: entity.get_availability() == Availability::Deprecated,
     })
   }
 
@@ -1167,14 +1196,24 @@ impl<'a> CppParser<'a> {
         values.push(EnumValue {
           name: try!(child.get_name().chain_err(|| "failed to get name of enum variant")),
           value: val.0,
+          platforms: Vec::new(),
         });
       }
     }
+    let underlying_clang_type = try!(entity.get_enum_underlying_type()
+      .chain_err(|| "failed to get underlying type of enum"));
+    let underlying_type = try!(self.parse_type(underlying_clang_type, None, None)
+      .chain_err(|| "failed to parse underlying type of enum"));
     Ok(CppTypeData {
       name: try!(get_full_name(entity)),
       include_file: include_file,
       origin_location: try!(get_origin_location(entity)),
-      kind: CppTypeKind::Enum { values: values },
+      kind: CppTypeKind::Enum {
+        values: values,
+        underlying_type: underlying_type,
+        is_scoped: is_scoped_enum(entity),
+      },
+      platforms: Vec::new(),
     })
   }
 
@@ -1192,6 +1231,10 @@ impl<'a> CppParser<'a> {
         Ok(size) => Some(size as i32),
         Err(_) => None,
       },
+      alignment: match field_clang_type.get_alignof() {
+        Ok(alignment) => Some(alignment as i32),
+        Err(_) => None,
+      },
       name: field_name,
       field_type: field_type,
       visibility: match entity.get_accessibility().unwrap_or(Accessibility::Public) {
@@ -1277,6 +1320,13 @@ impl<'a> CppParser<'a> {
     if template_arguments.is_none() && size.is_none() {
       return Err("Failed to request size, but the class is not a template class".into());
     }
+    let alignment = match entity.get_type() {
+      Some(type1) => type1.get_alignof().ok().map(|x| x as i32),
+      None => None,
+    };
+    if template_arguments.is_none() && alignment.is_none() {
+      return Err("Failed to request alignment, but the class is not a template class".into());
+    }
     if let Some(parent) = entity.get_semantic_parent() {
       if get_template_arguments(parent).is_some() {
         return Err("Types nested into template types are not supported".into());
@@ -1288,11 +1338,13 @@ impl<'a> CppParser<'a> {
       origin_location: get_origin_location(entity).unwrap(),
       kind: CppTypeKind::Class {
         size: size,
+        alignment: alignment,
         bases: bases,
         fields: fields,
         using_directives: using_directives,
         template_arguments: template_arguments,
       },
+      platforms: Vec::new(),
     })
   }
 
@@ -1510,20 +1562,29 @@ impl<'a> CppParser<'a> {
     Ok(())
   }
 
-  fn check_integrity(&self, methods: Vec<CppMethod>) -> (Vec<CppMethod>, Vec<CppTypeData>) {
+  /// Removes methods using unavailable types and base classes
+  /// that are not available. Returns remaining methods and types
+  /// and the list of removed methods.
+  fn check_integrity(&self,
+                     methods: Vec<CppMethod>)
+                     -> (Vec<CppMethod>, Vec<CppTypeData>, Vec<SkippedItem>) {
     log::info("Checking data integrity");
+    let mut skipped_items = Vec::new();
     let good_methods = methods.into_iter()
       .filter(|method| {
-        if let Err(msg) = self.check_type_integrity(&method.return_type
-          .clone()) {
-          log::warning(format!("Method is removed: {}: {}", method.short_text(), msg));
-          return false;
-        }
+        let mut result = self.check_type_integrity(&method.return_type.clone());
         for arg in &method.arguments {
-          if let Err(msg) = self.check_type_integrity(&arg.argument_type) {
-            log::warning(format!("Method is removed: {}: {}", method.short_text(), msg));
-            return false;
+          if result.is_err() {
+            break;
           }
+          result = self.check_type_integrity(&arg.argument_type);
+        }
+        if let Err(msg) = result {
+          log::warning(format!("Method is removed: {}: {}", method.short_text(), msg));
+          skipped_items.push(SkippedItem::method(method,
+                                                 SkipStage::ParserIntegrityCheck,
+                                                 msg.to_string()));
+          return false;
         }
         true
       })
@@ -1541,6 +1602,14 @@ impl<'a> CppParser<'a> {
                                  t.name,
                                  base,
                                  msg));
+            skipped_items.push(SkippedItem {
+              name: base.base_type.to_cpp_pseudo_code(),
+              class_name: Some(t.name.clone()),
+              include_file: t.include_file.clone(),
+              is_type: true,
+              stage: SkipStage::ParserIntegrityCheck,
+              reason: format!("base class of {} is not available: {}", t.name, msg),
+            });
           } else {
             valid_bases.push(base.clone());
           }
@@ -1550,7 +1619,7 @@ impl<'a> CppParser<'a> {
       }
       good_types.push(good_type);
     }
-    (good_methods, good_types)
+    (good_methods, good_types, skipped_items)
   }
 
   #[cfg_attr(feature="clippy", allow(block_in_if_condition_stmt))]
@@ -328,9 +328,14 @@ impl CppType {
 
   /// Converts this C++ type to its adaptation for FFI interface,
   /// removing all features not supported by C ABI
-  /// (e.g. references and passing objects by value)
+  /// (e.g. references and passing objects by value).
+  /// Instantiations of class templates listed in `flags_containers`
+  /// are converted to `unsigned int`.
   #[cfg_attr(feature="clippy", allow(collapsible_if))]
-  pub fn to_cpp_ffi_type(&self, role: CppTypeRole) -> Result<CppFfiType> {
+  pub fn to_cpp_ffi_type(&self,
+                         role: CppTypeRole,
+                         flags_containers: &[String])
+                         -> Result<CppFfiType> {
     let err = || format!("Can't express type to FFI: {:?}", self);
     match self.base {
       CppTypeBase::TemplateParameter { .. } => {
@@ -406,10 +411,10 @@ impl CppType {
       }
     }
     if let CppTypeBase::Class(CppTypeClassBase { ref name, .. }) = self.base {
-      if name == "QFlags" {
+      if flags_containers.iter().any(|x| x == name) {
         if !(self.indirection == CppTypeIndirection::None ||
              (self.indirection == CppTypeIndirection::Ref && self.is_const)) {
-          return Err(Error::from(format!("QFlags type can only be values or const references: \
+          return Err(Error::from(format!("flags type can only be values or const references: \
                                           {:?}",
                                          self)))
             .chain_err(&err);
@@ -467,9 +472,9 @@ impl CppType {
 
   /// Checks if a function with this return type would need
   /// to have 2 wrappers with 2 different return value allocation places
-  pub fn needs_allocation_place_variants(&self) -> bool {
+  pub fn needs_allocation_place_variants(&self, flags_containers: &[String]) -> bool {
     if let CppTypeBase::Class(CppTypeClassBase { ref name, .. }) = self.base {
-      if name == "QFlags" {
+      if flags_containers.iter().any(|x| x == name) {
         return false; // converted to uint in FFI
       }
     }
@@ -47,6 +47,16 @@ pub fn rust_method_variant(args: &RustMethodArgumentsVariant,
           return_type = return_type_text)
 }
 
+/// Generates "Safety" section of documentation
+/// for an `unsafe` function.
+pub fn safety_doc(unsafe_reasons: &[String]) -> String {
+  if unsafe_reasons.is_empty() {
+    return String::new();
+  }
+  format!("\n\n# Safety\n\nThis function is unsafe because it:\n\n{}",
+          unsafe_reasons.iter().map(|x| format!("- {}\n", x)).join(""))
+}
+
 pub fn wrap_inline_cpp_code(code: &str) -> String {
   format!("<span style='color: green;'>```{}```</span>", code)
 }
@@ -138,3 +148,25 @@ pub fn method_doc(doc_items: Vec<DocItem>, cpp_method_name: &str) -> String {
   }
   doc.join("")
 }
+
+/// Generates documentation section for a method that exposes C++ arguments
+/// with default values as nested `Option`s.
+pub fn default_arguments_doc(optional_arguments: &[String]) -> String {
+  if optional_arguments.is_empty() {
+    return String::new();
+  }
+  if optional_arguments.len() == 1 {
+    return format!("\n\nArgument `{}` has a default value in C++ and can be omitted by passing \
+                    `None`.\n",
+                   optional_arguments[0]);
+  }
+  let mut pattern = format!("Some({})", optional_arguments[optional_arguments.len() - 1]);
+  for name in optional_arguments.iter().rev().skip(1) {
+    pattern = format!("Some(({}, {}))", name, pattern);
+  }
+  format!("\n\nArguments {} have default values in C++. They are passed in `optional_args` \
+           as `{}`. Passing `None` at any level omits that argument and all following \
+           arguments.\n",
+          optional_arguments.iter().map(|x| format!("`{}`", x)).join(", "),
+          pattern)
+}
@@ -5,9 +5,14 @@ use std::path::{Path, PathBuf};
 use std::fs;
 use std::io::Read;
 
+/// Moves `src` file or directory to `dst`, replacing only files with
+/// different content. Items of `dst` directory not present in `src` are removed.
 pub fn move_files(src: &PathBuf, dst: &PathBuf) -> Result<()> {
   let err = || format!("failed: move_files({:?}, {:?})", src, dst);
   if src.as_path().is_dir() {
+    if dst.as_path().is_file() {
+      try!(remove_file(dst).chain_err(&err));
+    }
     if !dst.as_path().is_dir() {
       log::noisy(format!("New dir created: {}", dst.display()));
       try!(create_dir(dst).chain_err(&err));
@@ -35,6 +40,9 @@ pub fn move_files(src: &PathBuf, dst: &PathBuf) -> Result<()> {
     }
     try!(remove_dir_all(src).chain_err(&err));
   } else {
+    if dst.as_path().is_dir() {
+      try!(remove_dir_all(dst).chain_err(&err));
+    }
     try!(move_one_file(src, dst).chain_err(&err));
   }
   Ok(())
@@ -56,12 +64,29 @@ pub fn copy_recursively(src: &PathBuf, dst: &PathBuf) -> Result<()> {
   Ok(())
 }
 
+/// Returns true if files at `path1` and `path2` have the same content.
+pub fn files_equal<P: AsRef<Path>, P2: AsRef<Path>>(path1: P, path2: P2) -> Result<bool> {
+  let size1 = try!(fs::metadata(path1.as_ref())
+      .chain_err(|| format!("Failed to get metadata: {:?}", path1.as_ref())))
+    .len();
+  let size2 = try!(fs::metadata(path2.as_ref())
+      .chain_err(|| format!("Failed to get metadata: {:?}", path2.as_ref())))
+    .len();
+  if size1 != size2 {
+    return Ok(false);
+  }
+  let bytes1 = try!(try!(open_file(path1)).read_all_bytes());
+  let bytes2 = try!(try!(open_file(path2)).read_all_bytes());
+  Ok(bytes1 == bytes2)
+}
+
+/// Moves file from `old_path` to `new_path`. If `new_path` already
+/// has the same content, it's left untouched to preserve its modification time,
+/// and `old_path` is removed.
 pub fn move_one_file(old_path: &PathBuf, new_path: &PathBuf) -> Result<()> {
   let err = || format!("failed: move_one_file({:?}, {:?})", old_path, new_path);
   let is_changed = if new_path.as_path().is_file() {
-    let string1 = try!(file_to_string(old_path).chain_err(&err));
-    let string2 = try!(file_to_string(new_path).chain_err(&err));
-    string1 != string2
+    !try!(files_equal(old_path, new_path).chain_err(&err))
   } else {
     true
   };
@@ -159,6 +184,14 @@ impl FileWrapper {
     Ok(r)
   }
 
+  pub fn read_all_bytes(&mut self) -> Result<Vec<u8>> {
+    let mut r = Vec::new();
+    try!(self.file
+      .read_to_end(&mut r)
+      .chain_err(|| format!("Failed to read from file: {:?}", self.path)));
+    Ok(r)
+  }
+
   pub fn write<S: AsRef<str>>(&mut self, text: S) -> Result<()> {
     use std::io::Write;
     self.file
@@ -0,0 +1,291 @@
+extern crate regex;
+use self::regex::Regex;
+
+use coverage_report::{SkippedItem, SkipStage};
+use cpp_data::{CppData, CppTypeData, CppVisibility};
+use cpp_method::CppMethod;
+use errors::{Result, ChainErr};
+use log;
+use string_utils::JoinWithString;
+
+/// Action performed by a `FilterRule` on matching items
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FilterAction {
+  Allow,
+  Block,
+}
+
+/// A declarative rule that allows or blocks C++ methods and types.
+/// A rule matches an item if all of its specified conditions match.
+/// Rules are checked in order of declaration, and the first matching rule
+/// decides whether the item is kept. Items not matched by any rule are kept.
+///
+/// Patterns are globs (`*` matches any sequence of characters,
+/// `?` matches any single character) or regular expressions
+/// if prefixed with `regex:`. Patterns must match the whole text.
+///
+/// Conditions on signature, access level, deprecated status and argument types
+/// only apply to methods, so rules containing them never match types.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FilterRule {
+  pub action: FilterAction,
+  /// Pattern for qualified name of a type (e.g. `QObject`)
+  /// or a method (e.g. `QObject::tr`)
+  pub name: Option<String>,
+  /// Pattern for method's signature
+  /// (e.g. `QString QObject::tr(const char*, const char*, int)`)
+  pub signature: Option<String>,
+  /// Pattern for include file name
+  pub include_file: Option<String>,
+  /// Access level of a class method. Free functions are considered public.
+  pub access: Option<CppVisibility>,
+  /// Deprecated status of a method
+  pub deprecated: Option<bool>,
+  /// Pattern for the type of any of method's arguments (e.g. `QString*`)
+  pub argument_type: Option<String>,
+}
+
+impl FilterRule {
+  /// Creates a rule that matches all items.
+  /// Conditions can be added by setting the fields.
+  pub fn new(action: FilterAction) -> FilterRule {
+    FilterRule {
+      action: action,
+      name: None,
+      signature: None,
+      include_file: None,
+      access: None,
+      deprecated: None,
+      argument_type: None,
+    }
+  }
+
+  /// Returns short description of the rule for reporting.
+  pub fn caption(&self) -> String {
+    let mut parts = vec![match self.action {
+                           FilterAction::Allow => "allow",
+                           FilterAction::Block => "block",
+                         }
+                         .to_string()];
+    for &(key, ref value) in &[("name", &self.name),
+                               ("signature", &self.signature),
+                               ("include_file", &self.include_file),
+                               ("argument_type", &self.argument_type)] {
+      if let Some(ref value) = **value {
+        parts.push(format!("{}={:?}", key, value));
+      }
+    }
+    if let Some(ref access) = self.access {
+      parts.push(format!("access={:?}", access));
+    }
+    if let Some(deprecated) = self.deprecated {
+      parts.push(format!("deprecated={}", deprecated));
+    }
+    parts.join(" ")
+  }
+}
+
+/// Converts a filter pattern to a regular expression.
+fn pattern_to_regex(pattern: &str) -> Result<Regex> {
+  let regex_text = if pattern.starts_with("regex:") {
+    format!("^(?:{})$", &pattern["regex:".len()..])
+  } else {
+    let mut text = "^".to_string();
+    for c in pattern.chars() {
+      match c {
+        '*' => text.push_str(".*"),
+        '?' => text.push('.'),
+        _ => text.push_str(&regex::quote(&c.to_string())),
+      }
+    }
+    text.push('$');
+    text
+  };
+  Regex::new(&regex_text).chain_err(|| format!("invalid pattern in filter rule: {}", pattern))
+}
+
+fn compile(pattern: &Option<String>) -> Result<Option<Regex>> {
+  match *pattern {
+    Some(ref pattern) => Ok(Some(try!(pattern_to_regex(pattern)))),
+    None => Ok(None),
+  }
+}
+
+fn regex_matches(regex: &Option<Regex>, text: &str) -> bool {
+  match *regex {
+    Some(ref regex) => regex.is_match(text),
+    None => true,
+  }
+}
+
+/// Returns signature of the method used for matching `FilterRule::signature`.
+pub fn method_signature(method: &CppMethod) -> String {
+  let is_const = method.class_membership.as_ref().map_or(false, |x| x.is_const);
+  format!("{} {}({}){}",
+          method.return_type.to_cpp_pseudo_code(),
+          method.full_name(),
+          method.arguments.iter().map(|x| x.argument_type.to_cpp_pseudo_code()).join(", "),
+          if is_const { " const" } else { "" })
+}
+
+/// A `FilterRule` with compiled patterns
+#[derive(Debug)]
+struct CompiledRule {
+  rule: FilterRule,
+  name: Option<Regex>,
+  signature: Option<Regex>,
+  include_file: Option<Regex>,
+  argument_type: Option<Regex>,
+}
+
+impl CompiledRule {
+  fn is_method_only(&self) -> bool {
+    self.rule.signature.is_some() || self.rule.access.is_some() ||
+    self.rule.deprecated.is_some() || self.rule.argument_type.is_some()
+  }
+
+  fn matches_method(&self, method: &CppMethod) -> bool {
+    if !regex_matches(&self.name, &method.full_name()) ||
+       !regex_matches(&self.include_file, &method.include_file) {
+      return false;
+    }
+    if self.signature.is_some() && !regex_matches(&self.signature, &method_signature(method)) {
+      return false;
+    }
+    if let Some(ref access) = self.rule.access {
+      let method_access = method.class_membership
+        .as_ref()
+        .map_or(CppVisibility::Public, |x| x.visibility.clone());
+      if &method_access != access {
+        return false;
+      }
+    }
+    if let Some(deprecated) = self.rule.deprecated {
+      if method.is_deprecated != deprecated {
+        return false;
+      }
+    }
+    if self.argument_type.is_some() &&
+       !method.arguments
+      .iter()
+      .any(|x| regex_matches(&self.argument_type, &x.argument_type.to_cpp_pseudo_code())) {
+      return false;
+    }
+    true
+  }
+
+  fn matches_type(&self, type_data: &CppTypeData) -> bool {
+    !self.is_method_only() && regex_matches(&self.name, &type_data.name) &&
+    regex_matches(&self.include_file, &type_data.include_file)
+  }
+}
+
+/// Filter rules prepared for applying to the parser's output.
+/// Patterns are compiled once on construction.
+#[derive(Debug, Default)]
+pub struct FilterRules {
+  rules: Vec<CompiledRule>,
+}
+
+impl FilterRules {
+  /// Prepares `rules`. Returns an error if any of the patterns is invalid.
+  pub fn new(rules: Vec<FilterRule>) -> Result<FilterRules> {
+    let mut compiled = Vec::new();
+    for rule in rules {
+      compiled.push(CompiledRule {
+        name: try!(compile(&rule.name)),
+        signature: try!(compile(&rule.signature)),
+        include_file: try!(compile(&rule.include_file)),
+        argument_type: try!(compile(&rule.argument_type)),
+        rule: rule,
+      });
+    }
+    Ok(FilterRules { rules: compiled })
+  }
+
+  /// Returns the rule that blocks `method`, or `None` if the method is allowed.
+  pub fn blocking_method_rule(&self, method: &CppMethod) -> Option<&FilterRule> {
+    self.rules
+      .iter()
+      .find(|x| x.matches_method(method))
+      .and_then(|x| if x.rule.action == FilterAction::Block {
+        Some(&x.rule)
+      } else {
+        None
+      })
+  }
+
+  /// Returns the rule that blocks the type, or `None` if the type is allowed.
+  pub fn blocking_type_rule(&self, type_data: &CppTypeData) -> Option<&FilterRule> {
+    self.rules
+      .iter()
+      .find(|x| x.matches_type(type_data))
+      .and_then(|x| if x.rule.action == FilterAction::Block {
+        Some(&x.rule)
+      } else {
+        None
+      })
+  }
+
+  /// Removes blocked types and methods from `data`. Methods of
+  /// removed types are also removed. Each removed item is reported
+  /// along with the rule that removed it and added to `data.skipped_items`.
+  pub fn apply(&self, data: &mut CppData) {
+    let mut removed_types = Vec::new();
+    let mut skipped_items = Vec::new();
+    data.types.retain(|type_data| {
+      if let Some(rule) = self.blocking_type_rule(type_data) {
+        log::info(format!("Type {} is removed by filter rule: {}",
+                          type_data.name,
+                          rule.caption()));
+        removed_types.push(type_data.name.clone());
+        skipped_items.push(SkippedItem {
+          name: type_data.name.clone(),
+          class_name: Some(type_data.name.clone()),
+          include_file: type_data.include_file.clone(),
+          is_type: true,
+          stage: SkipStage::FilterRule,
+          reason: rule.caption(),
+        });
+        false
+      } else {
+        true
+      }
+    });
+    data.skipped_items.append(&mut skipped_items);
+    self.retain_methods(data, &removed_types);
+  }
+
+  /// Removes blocked methods from `data` without checking types.
+  /// Used after post-processing to filter inherited methods and
+  /// other methods that were not declared in the headers.
+  pub fn apply_to_methods(&self, data: &mut CppData) {
+    self.retain_methods(data, &[]);
+  }
+
+  /// Removes methods blocked by the rules and methods of `removed_types`.
+  fn retain_methods(&self, data: &mut CppData, removed_types: &[String]) {
+    let mut skipped_items = Vec::new();
+    data.methods.retain(|method| {
+      if let Some(class_name) = method.class_name() {
+        if removed_types.contains(class_name) {
+          log::noisy(format!("Method {} is removed because its class is removed",
+                             method.short_text()));
+          skipped_items.push(SkippedItem::method(method,
+                                                 SkipStage::FilterRule,
+                                                 "class is removed by a filter rule"));
+          return false;
+        }
+      }
+      if let Some(rule) = self.blocking_method_rule(method) {
+        log::info(format!("Method {} is removed by filter rule: {}",
+                          method.short_text(),
+                          rule.caption()));
+        skipped_items.push(SkippedItem::method(method, SkipStage::FilterRule, rule.caption()));
+        return false;
+      }
+      true
+    });
+    data.skipped_items.append(&mut skipped_items);
+  }
+}
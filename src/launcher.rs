@@ -1,31 +1,61 @@
 extern crate num_cpus;
 
-use config::Config;
+use build_cache;
+use config::{Config, CppBuildBackend};
+use config_file;
+use coverage_report::{self, SkippedItem};
 use cpp_code_generator::CppCodeGenerator;
+use cpp_compiler_builder::CppCompilerBuilder;
+use cpp_data::CppData;
 use cpp_ffi_generator;
 use cpp_lib_builder::CppLibBuilder;
 use cpp_parser;
 use dependency_info::DependencyInfo;
 use errors::{Result, ChainErr};
 use file_utils::{PathBufWithAdded, move_files, create_dir_all, load_json, save_json, canonicalize,
-                 remove_dir_all, remove_dir, read_dir, path_to_str};
+                 remove_dir_all, remove_dir, remove_file, read_dir, path_to_str,
+                 os_string_into_string};
+use filter_rules::FilterRules;
 use log;
 use qt_doc_parser::QtDocData;
-use rust_code_generator::{RustCodeGeneratorDependency, RustLinkItem, RustLinkKind};
+use rename_rules::RenameRules;
+use rust_code_generator::{RustCodeGeneratorDependency, RustLinkItem, RustLinkKind,
+                          CLibBuildConfig};
 use rust_code_generator;
 use rust_generator;
 use rust_info::{InputCargoTomlData, RustExportInfo};
 use string_utils::JoinWithString;
-use utils::{is_msvc, MapIfOk};
+use utils::{is_msvc, run_command, add_env_path_item, MapIfOk};
 
 use std::path::PathBuf;
+use std::process::Command;
 use std;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BuildProfile {
   Debug,
   Release,
 }
 
+impl BuildProfile {
+  /// Returns lowercase name of the profile
+  pub fn name(&self) -> &'static str {
+    match *self {
+      BuildProfile::Debug => "debug",
+      BuildProfile::Release => "release",
+    }
+  }
+}
+
+/// Way the generator was started
+pub enum InvokationMethod {
+  /// Called from a build script of the crate being generated
+  BuildScript,
+  /// Called from the `cpp_to_rust` binary. If `build_crate` is true,
+  /// the generated crate is built, tested and documented with cargo.
+  CommandLine { build_crate: bool },
+}
+
 pub struct BuildEnvironment {
   pub config: Config,
   pub output_dir_path: PathBuf,
@@ -34,16 +64,26 @@ pub struct BuildEnvironment {
   pub num_jobs: Option<i32>,
   pub build_profile: BuildProfile,
   pub pipe_output: bool,
+  /// Enabled Cargo features of the crate
+  pub cargo_features: Vec<String>,
+  pub invokation_method: InvokationMethod,
+  /// Config file applied instead of `cpp_to_rust.toml` of the crate.
+  /// If `None`, `cpp_to_rust.toml` is applied if it exists.
+  pub config_file_path: Option<PathBuf>,
 }
 
 pub fn run_from_build_script(config: Config) -> Result<()> {
   let mut dependency_paths = Vec::new();
+  let mut cargo_features = Vec::new();
   for (name, value) in std::env::vars_os() {
     if let Ok(name) = name.into_string() {
       if name.starts_with("DEP_") && name.ends_with("_CPP_TO_RUST_DATA_PATH") {
         log::info(format!("Found dependency: {}", value.to_string_lossy()));
         dependency_paths.push(PathBuf::from(value));
       }
+      if name.starts_with("CARGO_FEATURE_") {
+        cargo_features.push(name["CARGO_FEATURE_".len()..].to_lowercase());
+      }
     }
   }
   run(BuildEnvironment {
@@ -65,6 +105,9 @@ pub fn run_from_build_script(config: Config) -> Result<()> {
     },
     dependency_paths: dependency_paths,
     pipe_output: false,
+    cargo_features: cargo_features,
+    invokation_method: InvokationMethod::BuildScript,
+    config_file_path: None,
   })
 }
 
@@ -72,7 +115,7 @@ pub fn run_from_build_script(config: Config) -> Result<()> {
 
 // TODO: simplify this function
 #[cfg_attr(feature="clippy", allow(cyclomatic_complexity))]
-pub fn run(env: BuildEnvironment) -> Result<()> {
+pub fn run(mut env: BuildEnvironment) -> Result<()> {
   // canonicalize paths
   if !env.source_dir_path.as_path().exists() {
     return Err(format!("source dir doesn't exist: {}",
@@ -93,6 +136,26 @@ pub fn run(env: BuildEnvironment) -> Result<()> {
       .into());
   }
   let input_cargo_toml_data = try!(InputCargoTomlData::from_file(&input_cargo_toml_path));
+  if let Some(ref metadata) = input_cargo_toml_data.cpp_to_rust_metadata {
+    log::info("Applying config from [package.metadata.cpp_to_rust]");
+    try!(config_file::apply_table(&mut env.config, metadata, &source_dir_path)
+      .chain_err(|| "invalid [package.metadata.cpp_to_rust] section in Cargo.toml"));
+  }
+  let config_file_path = match env.config_file_path {
+    Some(ref path) => Some(path.clone()),
+    None => {
+      let path = source_dir_path.with_added(config_file::FILE_NAME);
+      if path.exists() { Some(path) } else { None }
+    }
+  };
+  if let Some(config_file_path) = config_file_path {
+    log::info(format!("Applying config from {}", config_file_path.display()));
+    try!(config_file::apply_file(&mut env.config, &config_file_path));
+  }
+  if let Ok(value) = std::env::var("CPP_TO_RUST_BUILD_BACKEND") {
+    env.config.set_cpp_build_backend(try!(CppBuildBackend::from_name(&value)
+      .chain_err(|| "invalid value of CPP_TO_RUST_BUILD_BACKEND env var")));
+  }
   if env.config.linked_libs().iter().any(|x| x == &input_cargo_toml_data.name) {
     return Err(format!("Rust crate name ({}) must not be the same as linked library name \
             because it can cause library name conflict and linker failure.",
@@ -146,11 +209,13 @@ pub fn run(env: BuildEnvironment) -> Result<()> {
   // TODO: move other effects of this var to qt_build_tools
   let is_qt_library = link_items.iter().any(|x| x.name.starts_with("Qt"));
 
+  let mut doc_data_path = None;
   let qt_doc_data = if is_qt_library {
     // TODO: find a better way to specify doc source (#35)
     let env_var_name = format!("{}_DOC_DATA", "QT5CORE".to_uppercase());
     if let Ok(env_var_value) = std::env::var(&env_var_name) {
       log::info(format!("Loading Qt doc data from {}", &env_var_value));
+      doc_data_path = Some(PathBuf::from(&env_var_value));
       match QtDocData::new(&PathBuf::from(&env_var_value)) {
         Ok(r) => Some(r),
         Err(msg) => {
@@ -182,14 +247,46 @@ pub fn run(env: BuildEnvironment) -> Result<()> {
   for dep in &dependencies {
     dependency_cpp_types.extend_from_slice(&dep.cpp_data.types);
   }
-  let c_lib_is_shared = is_msvc();
+  let build_backend = env.config.cpp_build_backend();
+  // the compiler backend only produces static libraries
+  let c_lib_is_shared = is_msvc() && build_backend == CppBuildBackend::CMake;
+  if env.config.sys_crate() && build_backend != CppBuildBackend::CMake {
+    return Err("the C wrapper library of a -sys crate is built by its build script \
+                using CMake, so only the CMake build backend is supported with sys_crate"
+      .into());
+  }
+
+  log::info("Calculating cache keys.");
+  let parse_key = try!(build_cache::parse_key(&env.config, &dependencies));
+  let output_key = try!(build_cache::output_key(&parse_key,
+                                                &env.config,
+                                                &source_dir_path,
+                                                &output_dir_path,
+                                                &dependencies,
+                                                &env.cargo_features,
+                                                env.build_profile,
+                                                doc_data_path.as_ref()));
+  let parse_key_path = output_dir_path.with_added("cpp_data.key");
+  let output_key_path = output_dir_path.with_added("output.key");
   if output_dir_path.with_added("skip_processing").as_path().exists() {
     log::info("Processing skipped!");
+  } else if build_cache::is_up_to_date(&output_key_path, &output_key) &&
+            output_dir_path.with_added("rust_export_info.json").exists() &&
+            (env.config.sys_crate() || c_lib_install_path.exists()) {
+    log::info("Generated files are up to date. Processing skipped.");
   } else {
+    try!(build_cache::remove_key(&output_key_path));
     let parse_result_cache_file_path = output_dir_path.with_added("cpp_data.json");
-    let loaded_parse_result = if std::env::var("CPP_TO_RUST_DEV_CACHE").is_ok() &&
-                                 parse_result_cache_file_path.as_path().is_file() {
-      match load_json(&parse_result_cache_file_path) {
+    let skipped_items_cache_file_path = output_dir_path.with_added("cpp_skipped_items.json");
+    // CPP_TO_RUST_DEV_CACHE forces reusing the parse result even if the key is different
+    let loaded_parse_result = if (std::env::var("CPP_TO_RUST_DEV_CACHE").is_ok() ||
+                                  build_cache::is_up_to_date(&parse_key_path, &parse_key)) &&
+                                 parse_result_cache_file_path.as_path().is_file() &&
+                                 skipped_items_cache_file_path.as_path().is_file() {
+      match load_json(&parse_result_cache_file_path).and_then(|mut r: CppData| {
+        r.skipped_items = try!(load_json(&skipped_items_cache_file_path));
+        Ok(r)
+      }) {
         Ok(r) => {
           log::info(format!("C++ data is loaded from file: {}",
                             parse_result_cache_file_path.display()));
@@ -208,31 +305,64 @@ pub fn run(env: BuildEnvironment) -> Result<()> {
     let parse_result = if let Some(r) = loaded_parse_result {
       r
     } else {
-      log::info("Parsing C++ headers.");
-      let mut parse_result =
-        try!(cpp_parser::run(cpp_parser::CppParserConfig {
-                               include_paths: include_dirs.clone(),
-                               framework_paths: framework_dirs.clone(),
-                               include_directives: Vec::from(env.config.include_directives()),
-                               target_include_paths: target_include_dirs,
-                               tmp_cpp_path: output_dir_path.with_added("1.cpp"),
-                               name_blacklist: Vec::from(env.config.cpp_parser_blocked_names()),
-                               flags: Vec::from(env.config.cpp_parser_flags()),
-                             },
-                             &dependencies.iter().map(|x| &x.cpp_data).collect::<Vec<_>>())
-          .chain_err(|| "C++ parser failed"));
+      let parse = |extra_flags: Vec<String>| -> Result<CppData> {
+        let mut flags = Vec::from(env.config.cpp_parser_flags());
+        flags.extend(extra_flags);
+        cpp_parser::run(cpp_parser::CppParserConfig {
+                          include_paths: include_dirs.clone(),
+                          framework_paths: framework_dirs.clone(),
+                          include_directives: Vec::from(env.config.include_directives()),
+                          target_include_paths: target_include_dirs.clone(),
+                          tmp_cpp_path: output_dir_path.with_added("1.cpp"),
+                          name_blacklist: Vec::from(env.config.cpp_parser_blocked_names()),
+                          flags: flags,
+                        },
+                        &dependencies.iter().map(|x| &x.cpp_data).collect::<Vec<_>>())
+          .chain_err(|| "C++ parser failed")
+      };
+      let mut parse_result = if env.config.target_platforms().is_empty() {
+        log::info("Parsing C++ headers.");
+        try!(parse(Vec::new()))
+      } else {
+        let mut platform_results = Vec::new();
+        for platform in env.config.target_platforms() {
+          log::info(format!("Parsing C++ headers for platform: {}", platform.name));
+          let mut flags = vec![format!("--target={}", platform.target_triple)];
+          flags.extend(platform.defines.iter().map(|x| format!("-D{}", x)));
+          platform_results.push((platform.name.clone(), try!(parse(flags))));
+        }
+        log::info("Merging parse results of all platforms.");
+        try!(CppData::merge_platforms(platform_results))
+      };
       for filter in env.config.cpp_data_filters() {
         try!(filter(&mut parse_result).chain_err(|| "cpp_data_filter failed"));
       }
+      let filter_rules = try!(FilterRules::new(Vec::from(env.config.filter_rules())));
+      filter_rules.apply(&mut parse_result);
       log::info("Post-processing parse result.");
       try!(parse_result.post_process(&dependencies.iter().map(|x| &x.cpp_data).collect::<Vec<_>>()));
+      // rules also apply to inherited methods and methods added by post-processing
+      filter_rules.apply_to_methods(&mut parse_result);
 
       try!(save_json(&parse_result_cache_file_path, &parse_result));
+      try!(save_json(&skipped_items_cache_file_path, &parse_result.skipped_items));
+      try!(build_cache::save_key(&parse_key_path, &parse_key));
       log::info(format!("Header parse result is saved to file: {}",
                         parse_result_cache_file_path.display()));
       parse_result
     };
 
+    let mut flags_containers = Vec::from(env.config.flags_containers());
+    let mut flag_enums = Vec::from(env.config.flag_enums());
+    if is_qt_library {
+      flags_containers.push("QFlags".to_string());
+      // QUrlTwoFlags takes two enums and can't be converted to flags::Flags,
+      // but its enums are still combinable
+      flag_enums.push("QUrl::UrlFormattingOption".to_string());
+      flag_enums.push("QUrl::ComponentFormattingOption".to_string());
+    }
+
+    let rename_rules = try!(RenameRules::new(Vec::from(env.config.rename_rules())));
     let c_lib_name = format!("{}_c", &input_cargo_toml_data.name);
     let c_lib_path = c_lib_parent_path.with_added("source");
     let c_lib_tmp_path = c_lib_parent_path.with_added("source.new");
@@ -242,9 +372,14 @@ pub fn run(env: BuildEnvironment) -> Result<()> {
     try!(create_dir_all(&c_lib_tmp_path));
     log::info(format!("Generating C wrapper library ({}).", c_lib_name));
 
+    let coverage_methods = coverage_report::coverage_methods(&parse_result);
+    let mut skipped_items: Vec<SkippedItem> = parse_result.skipped_items.clone();
     let cpp_ffi_headers = try!(cpp_ffi_generator::run(&parse_result,
                                                       c_lib_name.clone(),
-                                                      env.config.cpp_ffi_generator_filters())
+                                                      env.config.cpp_ffi_generator_filters(),
+                                                      &flags_containers,
+                                                      num_jobs as usize,
+                                                      &mut skipped_items)
       .chain_err(|| "FFI generator failed"));
 
     let mut cpp_libs_for_shared_c_lib = Vec::new();
@@ -261,7 +396,8 @@ pub fn run(env: BuildEnvironment) -> Result<()> {
     let code_gen = CppCodeGenerator::new(c_lib_name.clone(),
                                          c_lib_tmp_path.clone(),
                                          c_lib_is_shared,
-                                         cpp_libs_for_shared_c_lib);
+                                         cpp_libs_for_shared_c_lib,
+                                         Vec::from(env.config.target_platforms()));
     let include_dirs_str = try!(include_dirs.iter()
       .map_if_ok(|x| -> Result<_> { Ok(try!(path_to_str(x)).to_string()) }));
     let framework_dirs_str = try!(framework_dirs.iter()
@@ -271,29 +407,21 @@ pub fn run(env: BuildEnvironment) -> Result<()> {
                                           &framework_dirs_str,
                                           env.config.cpp_compiler_flags()));
     try!(code_gen.generate_files(&cpp_ffi_headers));
+    if env.config.c_header() {
+      log::info("Generating plain C header.");
+      let mut all_types: Vec<_> = parse_result.types.iter().collect();
+      for dep in &dependencies {
+        all_types.extend(dep.cpp_data.types.iter());
+      }
+      try!(code_gen.generate_c_header(&cpp_ffi_headers, &all_types));
+    }
 
     try!(move_files(&c_lib_tmp_path, &c_lib_path));
-
-    log::info("Building C wrapper library.");
-    let c_lib_build_path = c_lib_parent_path.with_added("build");
-    try!(create_dir_all(&c_lib_build_path));
-    try!(create_dir_all(&c_lib_install_path));
-
-    try!(CppLibBuilder {
-        cmake_source_dir: &c_lib_path,
-        build_dir: &c_lib_build_path,
-        install_dir: &c_lib_install_path,
-        num_jobs: num_jobs,
-        linker_env_library_dirs: if c_lib_is_shared {
-          Some(&cpp_lib_dirs)
-        } else {
-          None
-        },
-        pipe_output: env.pipe_output,
-      }
-      .run()
-      .chain_err(|| "C wrapper build failed")
-      .into());
+    let c_lib_sources: Vec<_> = cpp_ffi_headers.iter()
+      .map(|x| {
+        (x.include_file.clone(), format!("src/{}_{}.cpp", &c_lib_name, x.include_file_base_name))
+      })
+      .collect();
 
     let crate_new_path = output_dir_path.with_added(format!("{}.new", &input_cargo_toml_data.name));
     if crate_new_path.as_path().exists() {
@@ -307,7 +435,7 @@ pub fn run(env: BuildEnvironment) -> Result<()> {
       crate_version: input_cargo_toml_data.version.clone(),
       output_path: crate_new_path.clone(),
       template_path: source_dir_path.clone(),
-      c_lib_name: c_lib_name,
+      c_lib_name: c_lib_name.clone(),
       c_lib_is_shared: c_lib_is_shared,
       link_items: link_items,
       framework_dirs: framework_dirs_str,
@@ -321,9 +449,22 @@ pub fn run(env: BuildEnvironment) -> Result<()> {
           RustCodeGeneratorDependency {
             crate_name: x.rust_export_info.crate_name.clone(),
             crate_path: x.path.clone(),
+            sys_crate_name: x.rust_export_info.sys_crate.clone(),
           }
         })
         .collect(),
+      flat_module: env.config.flat_module().cloned(),
+      module_features: env.config.module_features(),
+      platforms: Vec::from(env.config.target_platforms()),
+      sys_crate: env.config.sys_crate(),
+      c_lib_build: CLibBuildConfig {
+        generator: env.config.cmake_generator().cloned(),
+        compiler_launcher: env.config.cpp_compiler_launcher().cloned(),
+        cmake_vars: Vec::from(env.config.cmake_cache_vars()),
+        linker_env_library_dirs: try!(cpp_lib_dirs.iter()
+          .map_if_ok(|x| path_to_str(x).map(|x| x.to_string()))),
+      },
+      num_jobs: num_jobs as usize,
     };
     let mut dependency_rust_types = Vec::new();
     for dep in &dependencies {
@@ -338,12 +479,133 @@ pub fn run(env: BuildEnvironment) -> Result<()> {
                                              rust_generator::RustGeneratorConfig {
                                                crate_name: input_cargo_toml_data.name.clone(),
                                                remove_qt_prefix: is_qt_library,
+                                               rename_rules: rename_rules,
+                                               module_layout: env.config.module_layout(),
+                                               overload_strategy: env.config.overload_strategy(),
+                                               module_mapping: Vec::from(env.config
+                                                 .module_mapping()),
                                                qt_doc_data: qt_doc_data,
+                                               default_pointer_policy: env.config
+                                                 .default_pointer_policy(),
+                                               pointer_policies: Vec::from(env.config
+                                                 .pointer_policies()),
+                                               unsafe_methods: Vec::from(env.config
+                                                 .unsafe_methods()),
+                                               return_lifetimes: Vec::from(env.config
+                                                 .return_lifetimes()),
+                                               default_enum_representation: env.config
+                                                 .default_enum_representation(),
+                                               enum_representations: Vec::from(env.config
+                                                 .enum_representations()),
+                                               flag_enums: flag_enums,
+                                               flags_containers: flags_containers,
+                                               num_jobs: num_jobs as usize,
                                              })
       .chain_err(|| "Rust data generator failed"));
+
+    // sources of the C wrapper library to build, if not all of them are required
+    let mut c_lib_selected_sources = None;
+    if env.config.module_features() {
+      let enabled_features: Vec<_> = rust_data.module_features
+        .iter()
+        .filter(|x| env.cargo_features.contains(&x.name))
+        .collect();
+      if enabled_features.is_empty() {
+        log::warning("No module features are enabled. Building all C wrapper sources.");
+      } else {
+        log::info(format!("Enabled module features: {}",
+                          enabled_features.iter().map(|x| &x.name).join(", ")));
+        c_lib_selected_sources = Some(c_lib_sources.iter()
+          .filter(|&&(ref include_file, _)| {
+            enabled_features.iter().any(|x| x.include_files.contains(include_file))
+          })
+          .map(|&(_, ref path)| path.clone())
+          .collect::<Vec<_>>());
+      }
+    }
+    if env.config.sys_crate() {
+      log::info("C wrapper library will be built by the build script of the -sys crate.");
+    } else {
+      log::info(format!("Building C wrapper library ({}).", env.build_profile.name()));
+      // each profile has its own build dir, so switching profiles
+      // doesn't cause a full rebuild
+      let c_lib_build_path = c_lib_parent_path.with_added(format!("build_{}",
+                                                                  env.build_profile.name()));
+      try!(create_dir_all(&c_lib_build_path));
+      try!(create_dir_all(&c_lib_install_path));
+
+      match build_backend {
+        CppBuildBackend::CMake => {
+          let mut cmake_vars = Vec::new();
+          // CMake caches variables between runs, so the variable is passed
+          // even if all sources are built
+          cmake_vars.push(("C_LIB_SOURCES".to_string(),
+                           c_lib_selected_sources.map_or(String::new(), |x| x.join(";"))));
+          cmake_vars.extend_from_slice(env.config.cmake_cache_vars());
+          try!(CppLibBuilder {
+              cmake_source_dir: &c_lib_path,
+              build_dir: &c_lib_build_path,
+              install_dir: &c_lib_install_path,
+              num_jobs: num_jobs,
+              linker_env_library_dirs: if c_lib_is_shared {
+                Some(&cpp_lib_dirs)
+              } else {
+                None
+              },
+              pipe_output: env.pipe_output,
+              cmake_vars: cmake_vars,
+              build_profile: env.build_profile,
+              generator: env.config.cmake_generator(),
+              compiler_launcher: env.config.cpp_compiler_launcher(),
+            }
+            .run()
+            .chain_err(|| "C wrapper build failed")
+            .into());
+        }
+        CppBuildBackend::Compiler => {
+          try!(CppCompilerBuilder {
+              source_dir: &c_lib_path,
+              build_dir: &c_lib_build_path,
+              install_dir: &c_lib_install_path,
+              lib_name: &c_lib_name,
+              sources: c_lib_selected_sources,
+              include_dirs: &include_dirs,
+              framework_dirs: &framework_dirs,
+              cpp_compiler_flags: env.config.cpp_compiler_flags(),
+              build_profile: env.build_profile,
+              num_jobs: num_jobs,
+              compiler_launcher: env.config.cpp_compiler_launcher(),
+              dependency_key: &parse_key,
+              pipe_output: env.pipe_output,
+            }
+            .run()
+            .chain_err(|| "C wrapper build failed"));
+        }
+      }
+    }
+
     log::info(format!("Generating Rust crate ({}).", &input_cargo_toml_data.name));
     try!(rust_code_generator::run(rust_config, &rust_data)
       .chain_err(|| "Rust code generator failed"));
+    if env.config.sys_crate() {
+      log::info(format!("FFI declarations are generated in {}_sys crate ({}). The crate must \
+                         depend on it.",
+                        &input_cargo_toml_data.name,
+                        output_dir_path.with_added("sys").display()));
+    }
+    if env.config.module_features() {
+      log::info(format!("Module features are listed in {}. When the crate is generated by \
+                         its build script, the same features must be declared in \
+                         Cargo.toml of the crate.",
+                        output_dir_path.with_added("Cargo.toml").display()));
+    }
+    {
+      skipped_items.extend(rust_data.skipped_items.iter().cloned());
+      let report = coverage_report::generate(coverage_methods, skipped_items, &rust_data.modules);
+      try!(report.save(&output_dir_path));
+      log::info(format!("API coverage report is saved to file: {}",
+                        output_dir_path.with_added("coverage.md").display()));
+    }
     {
       let rust_export_path = output_dir_path.with_added("rust_export_info.json");
       try!(save_json(&rust_export_path,
@@ -352,68 +614,113 @@ pub fn run(env: BuildEnvironment) -> Result<()> {
                        rust_types: rust_data.processed_types,
                        linked_libs: Vec::from(env.config.linked_libs()),
                        linked_frameworks: Vec::from(env.config.linked_frameworks()),
+                       rename_rules: Vec::from(env.config.rename_rules()),
+                       flat_module: env.config.flat_module().cloned(),
+                       sys_crate: if env.config.sys_crate() {
+                         Some(format!("{}_sys", &input_cargo_toml_data.name))
+                       } else {
+                         None
+                       },
                      }));
       log::info(format!("Rust export info is saved to file: {}",
                         rust_export_path.display()));
     }
 
+    // Only changed files are replaced, so that cargo doesn't rebuild
+    // unchanged modules. The output dir contains other files as well,
+    // so names of the crate's top level items are saved to remove
+    // items that are no longer generated on the next run.
+    let crate_items_path = output_dir_path.with_added("crate_items.json");
+    let old_crate_items: Vec<String> = if crate_items_path.exists() {
+      try!(load_json(&crate_items_path))
+    } else {
+      Vec::new()
+    };
+    let mut crate_items = Vec::new();
     for item in try!(read_dir(&crate_new_path)) {
       let item = try!(item);
       try!(move_files(&crate_new_path.with_added(item.file_name()),
                       &output_dir_path.with_added(item.file_name())));
+      crate_items.push(try!(os_string_into_string(item.file_name())));
     }
     try!(remove_dir(&crate_new_path));
+    for name in old_crate_items {
+      let path = output_dir_path.with_added(&name);
+      if !crate_items.contains(&name) && path.exists() {
+        log::noisy(format!("Old item removed: {}", path.display()));
+        if path.is_dir() {
+          try!(remove_dir_all(&path));
+        } else {
+          try!(remove_file(&path));
+        }
+      }
+    }
+    crate_items.sort();
+    try!(save_json(&crate_items_path, &crate_items));
+    try!(build_cache::save_key(&output_key_path, &output_key));
   }
 
 
-  // match env.invokation_method {
-  // InvokationMethod::CommandLine => {
-  // log::info("Compiling Rust crate.");
-  // let mut all_cpp_lib_dirs = cpp_lib_dirs.clone();
-  // if c_lib_is_shared {
-  // all_cpp_lib_dirs.push(c_lib_lib_path.clone());
-  // }
-  // if output_dir_path.with_added("Cargo.lock").exists() {
-  // try!(remove_file(output_dir_path.with_added("Cargo.lock")));
-  // }
-  // for cargo_cmd in &["build", "test", "doc"] {
-  // let mut command = Command::new("cargo");
-  // command.arg(cargo_cmd);
-  // command.arg("--verbose");
-  // command.arg(format!("-j{}", num_jobs));
-  // command.current_dir(&output_dir_path);
-  // if !all_cpp_lib_dirs.is_empty() {
-  // for name in &["LIBRARY_PATH", "LD_LIBRARY_PATH", "LIB", "PATH"] {
-  // let value = try!(add_env_path_item(name, all_cpp_lib_dirs.clone()));
-  // command.env(name, value);
-  // }
-  // }
-  // if !framework_dirs.is_empty() {
-  // command.env("DYLD_FRAMEWORK_PATH",
-  // try!(add_env_path_item("DYLD_FRAMEWORK_PATH", framework_dirs.clone())));
-  // }
-  // if is_msvc() && *cargo_cmd == "test" {
-  // cargo doesn't pass this flag to rustc when it compiles qt_core,
-  // so it's compiled with static std and the tests fail with
-  // "cannot satisfy dependencies so `std` only shows up once" error.
-  // command.env("RUSTFLAGS", "-C prefer-dynamic");
-  // }
-  // try!(run_command(&mut command, false, env.pipe_output)
-  // .chain_err(|| "failed to build generated crate"));
-  // }
-  // log::info("Completed successfully.");
-  // }
-  // InvokationMethod::BuildScript => {
-  println!("cargo:rustc-link-search={}",
-           try!(path_to_str(&c_lib_lib_path)));
-  for dir in &cpp_lib_dirs {
-    println!("cargo:rustc-link-search=native={}", try!(path_to_str(dir)));
-  }
-  println!("cargo:cpp_to_rust_data_path={}",
-           try!(path_to_str(&output_dir_path)));
-  for dir in &framework_dirs {
-    println!("cargo:rustc-link-search=framework={}",
-             try!(path_to_str(dir)));
+  match env.invokation_method {
+    InvokationMethod::CommandLine { build_crate } => {
+      if build_crate {
+        log::info("Compiling Rust crate.");
+        let mut all_cpp_lib_dirs = cpp_lib_dirs.clone();
+        // cargo adds library directories of the -sys crate's build script itself
+        if c_lib_is_shared && !env.config.sys_crate() {
+          all_cpp_lib_dirs.push(c_lib_lib_path.clone());
+        }
+        if output_dir_path.with_added("Cargo.lock").exists() {
+          try!(remove_file(output_dir_path.with_added("Cargo.lock")));
+        }
+        for cargo_cmd in &["build", "test", "doc"] {
+          let mut command = Command::new("cargo");
+          command.arg(cargo_cmd);
+          command.arg("--verbose");
+          command.arg(format!("-j{}", num_jobs));
+          if let BuildProfile::Release = env.build_profile {
+            command.arg("--release");
+          }
+          command.current_dir(&output_dir_path);
+          if !all_cpp_lib_dirs.is_empty() {
+            for name in &["LIBRARY_PATH", "LD_LIBRARY_PATH", "LIB", "PATH"] {
+              let value = try!(add_env_path_item(name, all_cpp_lib_dirs.clone()));
+              command.env(name, value);
+            }
+          }
+          if !framework_dirs.is_empty() {
+            command.env("DYLD_FRAMEWORK_PATH",
+                        try!(add_env_path_item("DYLD_FRAMEWORK_PATH", framework_dirs.clone())));
+          }
+          if is_msvc() && *cargo_cmd == "test" {
+            // cargo doesn't pass this flag to rustc when it compiles qt_core,
+            // so it's compiled with static std and the tests fail with
+            // "cannot satisfy dependencies so `std` only shows up once" error.
+            command.env("RUSTFLAGS", "-C prefer-dynamic");
+          }
+          try!(run_command(&mut command, false, env.pipe_output)
+            .chain_err(|| format!("cargo {} failed for generated crate", cargo_cmd)));
+          log::info(format!("cargo {} succeeded.", cargo_cmd));
+        }
+      }
+      log::info(format!("Completed successfully. Generated crate: {}",
+                        output_dir_path.display()));
+    }
+    InvokationMethod::BuildScript => {
+      if !env.config.sys_crate() {
+        println!("cargo:rustc-link-search={}",
+                 try!(path_to_str(&c_lib_lib_path)));
+      }
+      for dir in &cpp_lib_dirs {
+        println!("cargo:rustc-link-search=native={}", try!(path_to_str(dir)));
+      }
+      println!("cargo:cpp_to_rust_data_path={}",
+               try!(path_to_str(&output_dir_path)));
+      for dir in &framework_dirs {
+        println!("cargo:rustc-link-search=framework={}",
+                 try!(path_to_str(dir)));
+      }
+    }
   }
   Ok(())
 }
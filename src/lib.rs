@@ -19,8 +19,12 @@ extern crate error_chain;
 
 mod cpp_ffi_generator;
 mod cpp_code_generator;
+mod cpp_compiler_builder;
+mod build_cache;
 mod caption_strategy;
 pub mod config;
+pub mod config_file;
+mod coverage_report;
 pub mod cpp_data;
 mod cpp_ffi_data;
 mod cpp_lib_builder;
@@ -29,11 +33,13 @@ pub mod cpp_type;
 pub mod cpp_operator;
 mod dependency_info;
 mod doc_formatter;
+mod filter_rules;
 pub mod errors;
 pub mod file_utils;
-mod launcher;
+pub mod launcher;
 pub mod log;
 mod qt_doc_parser;
+mod rename_rules;
 mod rust_generator;
 mod rust_code_generator;
 mod rust_info;
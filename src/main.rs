@@ -0,0 +1,101 @@
+//! Command line interface of the generator. Generates a crate
+//! from a config file without a build script:
+//!
+//! ```text
+//! cpp_to_rust --config cpp_to_rust.toml --source-dir foo --output-dir out --build
+//! ```
+
+extern crate cpp_to_rust;
+extern crate clap;
+
+use clap::{App, Arg, ArgMatches};
+use cpp_to_rust::config::Config;
+use cpp_to_rust::errors::Result;
+use cpp_to_rust::launcher::{self, BuildEnvironment, BuildProfile, InvokationMethod};
+
+use std::path::PathBuf;
+
+fn run(matches: ArgMatches) -> Result<()> {
+  let num_jobs = if let Some(value) = matches.value_of("jobs") {
+    Some(try!(value.parse().map_err(|_| format!("invalid number of jobs: {}", value))))
+  } else {
+    None
+  };
+  launcher::run(BuildEnvironment {
+    config: Config::new(),
+    source_dir_path: PathBuf::from(matches.value_of("source-dir").unwrap()),
+    output_dir_path: PathBuf::from(matches.value_of("output-dir").unwrap()),
+    dependency_paths: matches.values_of("dependency")
+      .map(|values| values.map(PathBuf::from).collect())
+      .unwrap_or_else(Vec::new),
+    num_jobs: num_jobs,
+    build_profile: if matches.is_present("release") {
+      BuildProfile::Release
+    } else {
+      BuildProfile::Debug
+    },
+    pipe_output: false,
+    cargo_features: matches.values_of("features")
+      .map(|values| values.map(|x| x.to_string()).collect())
+      .unwrap_or_else(Vec::new),
+    invokation_method: InvokationMethod::CommandLine { build_crate: matches.is_present("build") },
+    config_file_path: matches.value_of("config").map(PathBuf::from),
+  })
+}
+
+fn main() {
+  let matches = App::new("cpp_to_rust")
+    .version(env!("CARGO_PKG_VERSION"))
+    .about("Generates a Rust crate wrapping a C++ library")
+    .arg(Arg::with_name("config")
+      .short("c")
+      .long("config")
+      .value_name("FILE")
+      .help("Config file (TOML or JSON) applied instead of cpp_to_rust.toml \
+             of the crate. [package.metadata.cpp_to_rust] is always applied.")
+      .takes_value(true))
+    .arg(Arg::with_name("source-dir")
+      .short("s")
+      .long("source-dir")
+      .value_name("DIR")
+      .help("Directory containing Cargo.toml and source files of the crate")
+      .takes_value(true)
+      .required(true))
+    .arg(Arg::with_name("output-dir")
+      .short("o")
+      .long("output-dir")
+      .value_name("DIR")
+      .help("Directory for the generated crate")
+      .takes_value(true)
+      .required(true))
+    .arg(Arg::with_name("dependency")
+      .short("d")
+      .long("dependency")
+      .value_name("DIR")
+      .help("Output directory of a previously generated dependency crate")
+      .takes_value(true)
+      .multiple(true))
+    .arg(Arg::with_name("features")
+      .long("features")
+      .value_name("NAME")
+      .help("Enabled module features")
+      .takes_value(true)
+      .multiple(true))
+    .arg(Arg::with_name("jobs")
+      .short("j")
+      .long("jobs")
+      .value_name("N")
+      .help("Number of parallel jobs")
+      .takes_value(true))
+    .arg(Arg::with_name("release")
+      .long("release")
+      .help("Build in release mode"))
+    .arg(Arg::with_name("build")
+      .long("build")
+      .help("Build, test and document the generated crate with cargo"))
+    .get_matches();
+  if let Err(err) = run(matches) {
+    err.display_report();
+    std::process::exit(1);
+  }
+}
@@ -30,7 +30,6 @@ impl QtDocIndexItem {
 #[derive(Debug)]
 pub struct QtDocData {
   index: Vec<QtDocIndexItem>,
-  files: HashMap<String, Document>,
   method_docs: HashMap<String, Vec<QtDocForMethod>>,
 }
 
@@ -122,7 +121,6 @@ impl QtDocData {
     let mut result = QtDocData {
       index: try!(index_reader.decode()
         .map_if_ok(|x| -> Result<_> { Ok(QtDocIndexItem::from_line(try!(x))) })),
-      files: HashMap::new(),
       method_docs: HashMap::new(),
     };
     let dir_path = data_folder.with_added("html");
@@ -135,8 +133,7 @@ impl QtDocData {
       let html_content = try!(file_to_string(&file_path));
       let doc = Document::from(html_content.as_ref());
       let file_name = try!(os_str_to_str(&item.file_name())).to_string();
-      result.method_docs.insert(file_name.clone(), try!(QtDocData::all_method_docs(&doc)));
-      result.files.insert(file_name, doc);
+      result.method_docs.insert(file_name, try!(QtDocData::all_method_docs(&doc)));
 
     }
     Ok(result)
@@ -0,0 +1,91 @@
+extern crate regex;
+use self::regex::Regex;
+
+use errors::{Result, ChainErr};
+
+pub use serializable::{RenameRule, RenameTarget};
+
+/// Rename rules prepared for applying to identifiers.
+/// Regular expressions are compiled once on construction.
+#[derive(Debug, Default)]
+pub struct RenameRules {
+  rules: Vec<RenameRule>,
+  regexes: Vec<Option<Regex>>,
+}
+
+fn target_matches(rule_target: &Option<RenameTarget>, target: RenameTarget) -> bool {
+  match *rule_target {
+    Some(rule_target) => rule_target == target,
+    None => true,
+  }
+}
+
+impl RenameRules {
+  /// Prepares `rules`. Returns an error if any of the regular
+  /// expressions is invalid.
+  pub fn new(rules: Vec<RenameRule>) -> Result<RenameRules> {
+    let mut regexes = Vec::new();
+    for rule in &rules {
+      regexes.push(if let RenameRule::Regex { ref pattern, .. } = *rule {
+        Some(try!(Regex::new(pattern)
+          .chain_err(|| format!("invalid regular expression in rename rule: {}", pattern))))
+      } else {
+        None
+      });
+    }
+    Ok(RenameRules {
+      rules: rules,
+      regexes: regexes,
+    })
+  }
+
+  /// Returns the list of rules.
+  pub fn rules(&self) -> &[RenameRule] {
+    &self.rules
+  }
+
+  /// Returns Rust name explicitly set for the C++ entity, if any.
+  pub fn explicit_name(&self, target: RenameTarget, cpp_name: &str) -> Option<&String> {
+    for rule in &self.rules {
+      if let RenameRule::Explicit { target: ref rule_target, cpp_name: ref rule_cpp_name,
+                                    ref rust_name } = *rule {
+        if *rule_target == target && rule_cpp_name == cpp_name {
+          return Some(rust_name);
+        }
+      }
+    }
+    None
+  }
+
+  /// Applies prefix, suffix and regex rules to a C++ identifier
+  /// in order of their declaration. Prefixes and suffixes are not removed
+  /// if nothing would remain of the identifier.
+  pub fn apply(&self, target: RenameTarget, name: &str) -> String {
+    let mut result = name.to_string();
+    for (rule, regex) in self.rules.iter().zip(self.regexes.iter()) {
+      match *rule {
+        RenameRule::RemovePrefix { target: ref rule_target, ref prefix } => {
+          if target_matches(rule_target, target) && result.starts_with(prefix.as_str()) &&
+             result.len() > prefix.len() {
+            result = result[prefix.len()..].to_string();
+          }
+        }
+        RenameRule::RemoveSuffix { target: ref rule_target, ref suffix } => {
+          if target_matches(rule_target, target) && result.ends_with(suffix.as_str()) &&
+             result.len() > suffix.len() {
+            let new_len = result.len() - suffix.len();
+            result.truncate(new_len);
+          }
+        }
+        RenameRule::Regex { target: ref rule_target, ref replacement, .. } => {
+          if target_matches(rule_target, target) {
+            let regex = regex.as_ref().expect("regex must be compiled for Regex rule");
+            result = regex.replace_all(&result, replacement.as_str());
+          }
+        }
+        RenameRule::Explicit { .. } => {}
+      }
+    }
+    result
+  }
+}
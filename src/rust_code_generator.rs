@@ -1,16 +1,19 @@
+use config::{EnumRepresentation, TargetPlatform};
+use cpp_lib_builder::{generator_supports_jobs, user_build_type};
 use errors::{Result, ChainErr, unexpected};
 use file_utils::{PathBufWithAdded, copy_recursively, file_to_string, copy_file, create_file,
                  path_to_str, create_dir_all, remove_file, read_dir, os_str_to_str,
                  os_string_into_string};
 use log;
-use rust_generator::RustGeneratorOutput;
+use rust_generator::{RustGeneratorOutput, RustProcessedTypeInfo};
 use rust_info::{RustTypeDeclarationKind, RustTypeWrapperKind, RustModule, RustMethod,
                 RustMethodArguments, RustMethodArgumentsVariant, RustMethodScope,
-                RustMethodArgument, TraitName};
+                RustMethodArgument, RustModuleFeature, TraitName};
 use rust_type::{RustName, RustType, RustTypeIndirection, RustFFIFunction, RustToCTypeConversion};
 use string_utils::{JoinWithString, CaseOperations};
-use utils::{is_msvc, MapIfOk};
+use utils::{is_msvc, parallel_map, MapIfOk};
 
+use std::collections::HashSet;
 use std::path::PathBuf;
 
 extern crate rustfmt;
@@ -19,6 +22,8 @@ extern crate toml;
 pub struct RustCodeGeneratorDependency {
   pub crate_name: String,
   pub crate_path: PathBuf,
+  /// Name of the dependency's `-sys` crate, if it has one
+  pub sys_crate_name: Option<String>,
 }
 
 pub enum RustLinkKind {
@@ -30,6 +35,21 @@ pub struct RustLinkItem {
   pub kind: RustLinkKind,
 }
 
+/// Settings of the C wrapper library build performed by
+/// the build script of the `-sys` crate.
+#[derive(Default)]
+pub struct CLibBuildConfig {
+  /// CMake generator (e.g. `"Ninja"`). If `None`, the platform's default is used.
+  pub generator: Option<String>,
+  /// Command prepended to compiler invokations (e.g. `"ccache"`)
+  pub compiler_launcher: Option<String>,
+  /// Variables passed to CMake as `-D` options
+  pub cmake_vars: Vec<(String, String)>,
+  /// Library directories added to the linker environment
+  /// if the C wrapper is a shared library
+  pub linker_env_library_dirs: Vec<String>,
+}
+
 pub struct RustCodeGeneratorConfig {
   pub crate_name: String,
   pub crate_version: String,
@@ -42,6 +62,19 @@ pub struct RustCodeGeneratorConfig {
   pub framework_dirs: Vec<String>,
   pub rustfmt_config_path: Option<PathBuf>,
   pub dependencies: Vec<RustCodeGeneratorDependency>,
+  /// Name of the module re-exporting all types, if enabled
+  pub flat_module: Option<String>,
+  /// Gate modules behind Cargo features
+  pub module_features: bool,
+  /// Target platforms referenced by platform lists of items
+  pub platforms: Vec<TargetPlatform>,
+  /// Put FFI declarations and plain struct definitions
+  /// into a separate `-sys` crate
+  pub sys_crate: bool,
+  /// Build settings of the C wrapper library used if `sys_crate` is enabled
+  pub c_lib_build: CLibBuildConfig,
+  /// Number of threads used for generating and formatting module files
+  pub num_jobs: usize,
 }
 
 fn format_doc(doc: &str) -> String {
@@ -60,6 +93,55 @@ fn format_doc(doc: &str) -> String {
     .join("")
 }
 
+/// Returns true if the argument is passed by value in Rust and
+/// by non-const pointer in FFI, so its binding must be mutable.
+fn is_mut_value_argument(arg: &RustMethodArgument) -> bool {
+  if arg.argument_type.rust_api_type.is_option() ||
+     arg.argument_type.rust_api_to_c_conversion != RustToCTypeConversion::ValueToPtr {
+    return false;
+  }
+  if let RustType::Common { ref indirection, .. } = arg.argument_type.rust_api_type {
+    if *indirection == RustTypeIndirection::None {
+      if let RustType::Common { ref is_const, .. } = arg.argument_type.rust_ffi_type {
+        return !is_const;
+      }
+    }
+  }
+  false
+}
+
+/// Formats value of an enum variant as a literal of `integer_type`.
+/// Values of unsigned enums are stored as `i64`, so
+/// large values need to be reinterpreted.
+fn enum_value_to_code(value: i64, integer_type: &str) -> String {
+  if integer_type.starts_with('u') {
+    (value as u64).to_string()
+  } else {
+    value.to_string()
+  }
+}
+
+/// Returns `cfg` attribute enabling an item if any of `features` is enabled.
+fn feature_cfg_attribute(features: &[&String]) -> String {
+  if features.len() == 1 {
+    format!("#[cfg(feature = \"{}\")]\n", features[0])
+  } else {
+    format!("#[cfg(any({}))]\n",
+            features.iter().map(|x| format!("feature = \"{}\"", x)).join(", "))
+  }
+}
+
+/// Returns attributes for an `unsafe fn` definition.
+/// Generated function bodies contain `unsafe` blocks that
+/// are redundant inside an `unsafe fn`.
+fn unsafe_fn_attributes(is_unsafe: bool) -> &'static str {
+  if is_unsafe {
+    "#[allow(unused_unsafe)]\n"
+  } else {
+    ""
+  }
+}
+
 pub fn rust_type_to_code(rust_type: &RustType, crate_name: &str) -> String {
   match *rust_type {
     RustType::Void => "()".to_string(),
@@ -123,6 +205,10 @@ pub fn rust_type_to_code(rust_type: &RustType, crate_name: &str) -> String {
                 return_type => format!(" -> {}", rust_type_to_code(return_type, crate_name)),
               })
     }
+    RustType::Tuple(ref items) => {
+      format!("({})",
+              items.iter().map(|item| rust_type_to_code(item, crate_name)).join(", "))
+    }
   }
 }
 
@@ -140,14 +226,29 @@ pub fn run(config: RustCodeGeneratorConfig, data: &RustGeneratorOutput) -> Resul
     config: config,
     rustfmt_config: rustfmt_config,
   };
-  try!(generator.generate_template());
-  for module in &data.modules {
-    try!(generator.generate_module_file(module));
+  let module_features = if generator.config.module_features {
+    &data.module_features[..]
+  } else {
+    &[]
+  };
+  try!(generator.generate_template(module_features));
+  try!(generator.generate_flags_file());
+  for result in parallel_map(&data.modules,
+                             generator.config.num_jobs,
+                             |module| generator.generate_module_file(module)) {
+    try!(result);
   }
   let mut module_names: Vec<_> = data.modules.iter().map(|x| &x.name).collect();
   module_names.sort();
-  try!(generator.generate_ffi_file(&data.ffi_functions));
-  try!(generator.generate_lib_file(&module_names));
+  if generator.config.sys_crate {
+    try!(generator.generate_sys_crate(data));
+  } else {
+    try!(generator.generate_ffi_file(&data.ffi_functions, module_features));
+  }
+  if let Some(ref name) = generator.config.flat_module {
+    try!(generator.generate_flat_module_file(name, &data.processed_types, module_features));
+  }
+  try!(generator.generate_lib_file(&module_names, module_features));
   Ok(())
 }
 
@@ -157,8 +258,97 @@ pub struct RustCodeGenerator {
 }
 
 impl RustCodeGenerator {
-  /// Generates cargo file and skeleton of the crate
-  pub fn generate_template(&self) -> Result<()> {
+  /// Returns name of the `-sys` crate.
+  fn sys_crate_name(&self) -> String {
+    format!("{}_sys", self.config.crate_name)
+  }
+
+  /// Returns code adding link search paths for framework directories
+  /// to a build script.
+  fn framework_dirs_code(&self) -> String {
+    self.config
+      .framework_dirs
+      .iter()
+      .map(|x| {
+        format!("  println!(\"cargo:rustc-link-search=framework={{}}\", \"{}\");",
+                x)
+      })
+      .join("\n")
+  }
+
+  /// Generates build script of the main crate that links to the C wrapper
+  /// library built by the generator.
+  fn generate_build_rs(&self) -> Result<()> {
+    let mut build_rs_file = try!(create_file(self.config.output_path.with_added("build.rs")));
+    build_rs_file.write(format!(include_str!("../templates/crate/build.rs"),
+                                extra = self.framework_dirs_code()))
+  }
+
+  /// Generates build script of the `-sys` crate that builds
+  /// the C wrapper library with CMake and links to it.
+  fn generate_sys_build_rs(&self, sys_path: &PathBuf) -> Result<()> {
+    let build = &self.config.c_lib_build;
+    let mut cmake_args = String::new();
+    let mut make_args = String::new();
+    match build.generator {
+      Some(ref generator) => {
+        cmake_args.push_str(&format!("  cmake_command.arg(\"-G\").arg({:?});\n", generator));
+        if generator_supports_jobs(generator) {
+          make_args.push_str("  make_command.arg(\"--\").arg(format!(\"-j{}\", \
+                              env::var(\"NUM_JOBS\").unwrap()));\n");
+        }
+      }
+      None => {
+        cmake_args.push_str("  if is_msvc {\n    \
+                             cmake_command.arg(\"-G\").arg(\"NMake Makefiles\");\n  }\n");
+        // nmake doesn't support multiple jobs
+        make_args.push_str("  if !is_msvc {\n    make_command.arg(\"--\").arg(format!(\"-j{}\", \
+                            env::var(\"NUM_JOBS\").unwrap()));\n  }\n");
+      }
+    }
+    if let Some(ref launcher) = build.compiler_launcher {
+      cmake_args.push_str(&format!("  cmake_command.arg({:?});\n",
+                                   format!("-DCMAKE_CXX_COMPILER_LAUNCHER={}", launcher)));
+    }
+    // user-defined variables are added last, so they can override the values above
+    for &(ref name, ref value) in &build.cmake_vars {
+      cmake_args.push_str(&format!("  cmake_command.arg({:?});\n",
+                                   format!("-D{}={}", name, value)));
+    }
+    if self.config.c_lib_is_shared && !build.linker_env_library_dirs.is_empty() {
+      make_args.push_str(&format!("  for name in &[\"LIBRARY_PATH\", \"LD_LIBRARY_PATH\", \
+                                   \"LIB\"] {{\n    let mut paths = vec![{}];\n    \
+                                   if let Some(value) = env::var_os(name) {{\n      \
+                                   paths.extend(env::split_paths(&value));\n    }}\n    \
+                                   make_command.env(name, env::join_paths(paths).unwrap());\n  \
+                                   }}\n",
+                                  build.linker_env_library_dirs
+                                    .iter()
+                                    .map(|x| format!("PathBuf::from({:?})", x))
+                                    .join(", ")));
+    }
+    let build_type = match user_build_type(&build.cmake_vars) {
+      Some(build_type) => format!("  let build_type = {:?};\n", build_type),
+      None => {
+        "  // Rust always links to release version of MSVC runtime, so\n  \
+         // link will fail if C library is built in debug mode\n  \
+         let build_type = if is_msvc || env::var(\"PROFILE\").unwrap() == \"release\" {\n    \
+         \"Release\"\n  } else {\n    \"Debug\"\n  };\n"
+          .to_string()
+      }
+    };
+    let mut build_rs_file = try!(create_file(sys_path.with_added("build.rs")));
+    build_rs_file.write(format!(include_str!("../templates/crate/build_sys.rs"),
+                                build_type = build_type,
+                                cmake_args = cmake_args,
+                                make_args = make_args,
+                                extra = self.framework_dirs_code()))
+  }
+
+  /// Generates cargo file and skeleton of the crate.
+  /// `module_features` are added to the `[features]` section
+  /// and enabled by default.
+  pub fn generate_template(&self, module_features: &[RustModuleFeature]) -> Result<()> {
     if let Some(ref path) = self.config.rustfmt_config_path {
       try!(copy_file(path, self.config.output_path.with_added("rustfmt.toml")));
     } else {
@@ -166,17 +356,9 @@ impl RustCodeGenerator {
       try!(rustfmt_file.write(include_str!("../templates/crate/rustfmt.toml")));
     };
 
-    {
-      let mut build_rs_file = try!(create_file(self.config.output_path.with_added("build.rs")));
-      let extra = self.config
-        .framework_dirs
-        .iter()
-        .map(|x| {
-          format!("  println!(\"cargo:rustc-link-search=framework={{}}\", \"{}\");",
-                  x)
-        })
-        .join("\n");
-      try!(build_rs_file.write(format!(include_str!("../templates/crate/build.rs"), extra = extra)));
+    // with a `-sys` crate, the C wrapper library is built and linked by its build script
+    if !self.config.sys_crate {
+      try!(self.generate_build_rs());
     }
 
     let cargo_toml_data = toml::Value::Table({
@@ -192,8 +374,10 @@ impl RustCodeGenerator {
           .map(|x| toml::Value::String(x.clone()))
           .collect();
         table.insert("authors".to_string(), toml::Value::Array(authors));
-        table.insert("build".to_string(),
-                     toml::Value::String("build.rs".to_string()));
+        if !self.config.sys_crate {
+          table.insert("build".to_string(),
+                       toml::Value::String("build.rs".to_string()));
+        }
         table
       });
       let dependencies = toml::Value::Table({
@@ -207,11 +391,34 @@ impl RustCodeGenerator {
                            toml::Value::String(try!(path_to_str(&dep.crate_path)).to_string()));
           table.insert(dep.crate_name.clone(), toml::Value::Table(table_dep));
         }
+        if self.config.sys_crate {
+          let mut table_dep = toml::Table::new();
+          table_dep.insert("path".to_string(), toml::Value::String("sys".to_string()));
+          table.insert(self.sys_crate_name(), toml::Value::Table(table_dep));
+        }
         table
       });
       let mut table = toml::Table::new();
       table.insert("package".to_string(), package);
       table.insert("dependencies".to_string(), dependencies);
+      if !module_features.is_empty() {
+        let features = toml::Value::Table({
+          let mut table = toml::Table::new();
+          table.insert("default".to_string(),
+                       toml::Value::Array(module_features.iter()
+                         .map(|x| toml::Value::String(x.name.clone()))
+                         .collect()));
+          for feature in module_features {
+            table.insert(feature.name.clone(),
+                         toml::Value::Array(feature.dependencies
+                           .iter()
+                           .map(|x| toml::Value::String(x.clone()))
+                           .collect()));
+          }
+          table
+        });
+        table.insert("features".to_string(), features);
+      }
       if is_msvc() {
         // LNK1189 (too many members) in MSVC with static linking,
         // so we use dynamic linking
@@ -245,20 +452,103 @@ impl RustCodeGenerator {
     rust_type_to_code(rust_type, &self.config.crate_name)
   }
 
+  /// Returns true if `rust_type` refers to types of the crate
+  /// or its dependencies.
+  fn involves_crate_types(&self, rust_type: &RustType) -> bool {
+    rust_type.referenced_names().into_iter().any(|name| {
+      name.crate_name().map_or(false, |crate_name| {
+        crate_name == &self.config.crate_name ||
+        self.config.dependencies.iter().any(|dep| &dep.crate_name == crate_name)
+      })
+    })
+  }
+
+  /// Converts `code` between `rust_ffi_type` and the corresponding type of the `-sys` crate.
+  /// If `is_return` is true, `code` is the value returned by the `-sys` crate's function,
+  /// and it's converted to `rust_ffi_type`. Otherwise, `code` has `rust_ffi_type`.
+  fn sys_ffi_value(&self,
+                   code: String,
+                   rust_ffi_type: &RustType,
+                   is_return: bool)
+                   -> Result<String> {
+    if rust_ffi_type.is_pointer() {
+      return Ok(if is_return {
+        format!("{} as {}", code, self.rust_type_to_code(rust_ffi_type))
+      } else {
+        format!("({}) as _", code)
+      });
+    }
+    if let RustType::FunctionPointer { .. } = *rust_ffi_type {
+      // function pointers only differ in pointer types of their arguments
+      return Ok(if is_return {
+        format!("::std::mem::transmute::<_, {}>({})",
+                self.rust_type_to_code(rust_ffi_type),
+                code)
+      } else {
+        format!("::std::mem::transmute({})", code)
+      });
+    }
+    // enums are passed through FFI as integers, and classes are passed by pointer
+    Err(unexpected(format!("type of the crate is passed through FFI by value: {:?}",
+                           rust_ffi_type))
+      .into())
+  }
+
+  /// Replaces types of dependencies in `rust_type` with
+  /// corresponding types of their `-sys` crates.
+  fn sys_rust_type(&self, rust_type: &RustType) -> RustType {
+    match *rust_type {
+      RustType::Void => RustType::Void,
+      RustType::Common { ref base,
+                         ref generic_arguments,
+                         is_const,
+                         is_const2,
+                         ref indirection } => {
+        let mut base = base.clone();
+        let sys_crate_name = self.config
+          .dependencies
+          .iter()
+          .find(|dep| base.crate_name() == Some(&dep.crate_name))
+          .and_then(|dep| dep.sys_crate_name.clone());
+        if let Some(sys_crate_name) = sys_crate_name {
+          base.parts[0] = sys_crate_name;
+        }
+        RustType::Common {
+          base: base,
+          generic_arguments: generic_arguments.as_ref()
+            .map(|args| args.iter().map(|x| self.sys_rust_type(x)).collect()),
+          is_const: is_const,
+          is_const2: is_const2,
+          indirection: indirection.clone(),
+        }
+      }
+      RustType::FunctionPointer { ref return_type, ref arguments } => {
+        RustType::FunctionPointer {
+          return_type: Box::new(self.sys_rust_type(return_type)),
+          arguments: arguments.iter().map(|x| self.sys_rust_type(x)).collect(),
+        }
+      }
+      RustType::Tuple(ref items) => {
+        RustType::Tuple(items.iter().map(|x| self.sys_rust_type(x)).collect())
+      }
+    }
+  }
+
   fn rust_ffi_function_to_code(&self, func: &RustFFIFunction) -> String {
+    let type_to_code = |rust_type: &RustType| if self.config.sys_crate {
+      self.rust_type_to_code(&self.sys_rust_type(rust_type))
+    } else {
+      self.rust_type_to_code(rust_type)
+    };
     let args = func.arguments
       .iter()
-      .map(|arg| {
-        format!("{}: {}",
-                arg.name,
-                self.rust_type_to_code(&arg.argument_type))
-      });
+      .map(|arg| format!("{}: {}", arg.name, type_to_code(&arg.argument_type)));
     format!("  pub fn {}({}){};\n",
             func.name,
             args.join(", "),
             match func.return_type {
               RustType::Void => String::new(),
-              _ => format!(" -> {}", self.rust_type_to_code(&func.return_type)),
+              _ => format!(" -> {}", type_to_code(&func.return_type)),
             })
   }
 
@@ -278,12 +568,25 @@ impl RustCodeGenerator {
         let mut code = arg.name.clone();
         match arg.argument_type.rust_api_to_c_conversion {
           RustToCTypeConversion::None => {}
-          RustToCTypeConversion::RefToPtr => {
+          RustToCTypeConversion::RefToPtr |
+          RustToCTypeConversion::NonNullRefToPtr => {
             code = format!("{} as {}",
                            code,
                            self.rust_type_to_code(&arg.argument_type.rust_ffi_type));
 
           }
+          RustToCTypeConversion::OptionRefToPtr => {
+            let null_fn = if let RustType::Common { ref is_const, .. } = arg.argument_type
+              .rust_ffi_type {
+              if *is_const { "null" } else { "null_mut" }
+            } else {
+              return Err(unexpected("void is not expected here at all!").into());
+            };
+            code = format!("match {} {{ Some(r) => r as {}, None => ::std::ptr::{}() }}",
+                           code,
+                           self.rust_type_to_code(&arg.argument_type.rust_ffi_type),
+                           null_fn);
+          }
           RustToCTypeConversion::ValueToPtr |
           RustToCTypeConversion::CppBoxToPtr => {
             let is_const =
@@ -311,6 +614,14 @@ impl RustCodeGenerator {
           RustToCTypeConversion::QFlagsToUInt => {
             code = format!("{}.to_int() as libc::c_uint", code);
           }
+          RustToCTypeConversion::EnumToInt => {
+            code = format!("{}::from({})",
+                           self.rust_type_to_code(&arg.argument_type.rust_ffi_type),
+                           code);
+          }
+        }
+        if self.config.sys_crate && self.involves_crate_types(&arg.argument_type.rust_ffi_type) {
+          code = try!(self.sys_ffi_value(code, &arg.argument_type.rust_ffi_type, false));
         }
         final_args[ffi_index as usize] = Some(code);
       }
@@ -339,26 +650,34 @@ impl RustCodeGenerator {
       } else {
         self.rust_type_to_code(&variant.return_type.rust_api_type)
       };
-      result.push(format!("{{\nlet mut {var}: {t} = unsafe {{ \
-                           ::NewUninitialized::new_uninitialized() }};\n",
+      // the C++ wrapper constructs the object in place, so
+      // the Rust side only provides uninitialized memory
+      result.push(format!("{{\nlet mut {var} = ::std::mem::MaybeUninit::<{t}>::uninit();\n",
                           var = return_var_name,
                           t = struct_name));
-      final_args[*i as usize] = Some(format!("&mut {}", return_var_name));
+      final_args[*i as usize] = Some(format!("{}.as_mut_ptr(){}",
+                                             return_var_name,
+                                             if self.config.sys_crate { " as _" } else { "" }));
       maybe_result_var_name = Some(return_var_name);
     }
     let final_args = try!(final_args.into_iter()
       .map_if_ok(|x| x.chain_err(|| "ffi argument is missing")));
 
-    result.push(format!("unsafe {{ ::ffi::{}({}) }}",
-                        variant.cpp_method.c_name,
-                        final_args.join(", ")));
+    let mut call = format!("::ffi::{}({})", variant.cpp_method.c_name, final_args.join(", "));
+    let ffi_return_type = &variant.return_type.rust_ffi_type;
+    if self.config.sys_crate && self.involves_crate_types(ffi_return_type) {
+      call = try!(self.sys_ffi_value(call, ffi_return_type, true));
+    }
+    result.push(format!("unsafe {{ {} }}", call));
     if let Some(ref name) = maybe_result_var_name {
-      result.push(format!("{}\n}}", name));
+      result.push(format!("\nunsafe {{ {}.assume_init() }}\n}}", name));
     }
     let mut code = result.join("");
     match variant.return_type.rust_api_to_c_conversion {
       RustToCTypeConversion::None => {}
-      RustToCTypeConversion::RefToPtr => {
+      RustToCTypeConversion::RefToPtr |
+      RustToCTypeConversion::NonNullRefToPtr |
+      RustToCTypeConversion::OptionRefToPtr => {
         let is_const =
           if let RustType::Common { ref is_const, ref is_const2, ref indirection, .. } =
                  variant.return_type
@@ -371,9 +690,23 @@ impl RustCodeGenerator {
           } else {
             panic!("void is not expected here at all!")
           };
-        code = format!("let ffi_result = {};\nunsafe {{ {}*ffi_result }}",
-                       code,
-                       if is_const { "& " } else { "&mut " });
+        let deref_code = format!("unsafe {{ {}*ffi_result }}",
+                                 if is_const { "& " } else { "&mut " });
+        code = match variant.return_type.rust_api_to_c_conversion {
+          RustToCTypeConversion::NonNullRefToPtr => {
+            format!("let ffi_result = {};\nif ffi_result.is_null() {{\n  panic!(\"{} returned \
+                     null pointer\");\n}}\n{}",
+                    code,
+                    variant.cpp_method.cpp_method.full_name(),
+                    deref_code)
+          }
+          RustToCTypeConversion::OptionRefToPtr => {
+            format!("let ffi_result = {};\nif ffi_result.is_null() {{ None }} else {{ Some({}) }}",
+                    code,
+                    deref_code)
+          }
+          _ => format!("let ffi_result = {};\n{}", code, deref_code),
+        };
       }
       RustToCTypeConversion::ValueToPtr => {
         if maybe_result_var_name.is_none() {
@@ -396,6 +729,20 @@ impl RustCodeGenerator {
                        code,
                        self.rust_type_to_code(&qflags_type));
       }
+      RustToCTypeConversion::EnumToInt => {
+        let enum_type = self.rust_type_to_code(&variant.return_type.rust_api_type);
+        code = format!("let ffi_result = {};\n\
+                        match <{} as ::std::convert::TryFrom<{}>>::try_from(ffi_result) {{\n\
+                          Ok(value) => value,\n\
+                          Err(_) => panic!(\"{} returned unknown value of {}: {{}}\", \
+                        ffi_result),\n\
+                        }}",
+                       code,
+                       enum_type,
+                       self.rust_type_to_code(&variant.return_type.rust_ffi_type),
+                       variant.cpp_method.cpp_method.full_name(),
+                       enum_type);
+      }
     }
     Ok(code)
   }
@@ -424,19 +771,11 @@ impl RustCodeGenerator {
             panic!("invalid self argument type (not Common)");
           }
         } else {
-          let mut maybe_mut_declaration = "";
-          if let RustType::Common { ref indirection, .. } = arg.argument_type
-            .rust_api_type {
-            if *indirection == RustTypeIndirection::None &&
-               arg.argument_type.rust_api_to_c_conversion == RustToCTypeConversion::ValueToPtr {
-              if let RustType::Common { ref is_const, .. } = arg.argument_type
-                .rust_ffi_type {
-                if !is_const {
-                  maybe_mut_declaration = "mut ";
-                }
-              }
-            }
-          }
+          let maybe_mut_declaration = if is_mut_value_argument(arg) {
+            "mut "
+          } else {
+            ""
+          };
 
           format!("{}{}: {}",
                   maybe_mut_declaration,
@@ -455,45 +794,170 @@ impl RustCodeGenerator {
   }
 
 
+  /// Generates a function with a single signature.
+  fn generate_function_with_body(&self,
+                                 doc: &str,
+                                 maybe_pub: &str,
+                                 maybe_unsafe: &str,
+                                 name: &str,
+                                 arguments: &[RustMethodArgument],
+                                 return_type: &RustType,
+                                 body: &str)
+                                 -> String {
+    let return_type_for_signature = if return_type == &RustType::Void {
+      String::new()
+    } else {
+      format!(" -> {}", self.rust_type_to_code(return_type))
+    };
+    let mut all_lifetimes: Vec<_> = arguments.iter()
+      .flat_map(|x| x.argument_type.rust_api_type.lifetimes())
+      .collect();
+    all_lifetimes.sort();
+    all_lifetimes.dedup();
+    let lifetimes_text = if all_lifetimes.is_empty() {
+      String::new()
+    } else {
+      format!("<{}>",
+              all_lifetimes.iter().map(|x| format!("'{}", x)).join(", "))
+    };
+
+    format!("{doc}{maybe_pub}{maybe_unsafe}fn {name}{lifetimes_text}({args}){return_type} \
+             {{\n{body}}}\n\n",
+            doc = doc,
+            maybe_pub = maybe_pub,
+            maybe_unsafe = maybe_unsafe,
+            lifetimes_text = lifetimes_text,
+            name = name,
+            args = self.arg_texts(arguments, None).join(", "),
+            return_type = return_type_for_signature,
+            body = body)
+  }
+
+  /// Returns `cfg` attribute enabling an item on any of `platforms`,
+  /// or an empty string if the item is available on all platforms.
+  fn platforms_cfg_attribute(&self, platforms: &[String]) -> Result<String> {
+    if platforms.is_empty() {
+      return Ok(String::new());
+    }
+    let conditions = try!(platforms.iter().map_if_ok(|name| -> Result<_> {
+      let platform = try!(self.config
+        .platforms
+        .iter()
+        .find(|x| &x.name == name)
+        .chain_err(|| format!("unknown platform: {}", name)));
+      Ok(platform.rust_cfg.clone())
+    }));
+    Ok(if conditions.len() == 1 {
+      format!("#[cfg({})]\n", conditions[0])
+    } else {
+      format!("#[cfg(any({}))]\n", conditions.join(", "))
+    })
+  }
+
   fn generate_rust_final_function(&self, func: &RustMethod) -> Result<String> {
     let maybe_pub = match func.scope {
       RustMethodScope::TraitImpl { .. } => "",
       _ => "pub ",
     };
+    // trait methods can't be unsafe if the trait doesn't declare them so,
+    // and the generator doesn't implement traits with unsafe methods
+    let is_unsafe = match func.scope {
+      RustMethodScope::TraitImpl { .. } => {
+        if func.is_unsafe() {
+          return Err(unexpected(format!("trait method can't be unsafe: {:?}", func.name))
+            .into());
+        }
+        false
+      }
+      _ => func.is_unsafe(),
+    };
+    let maybe_unsafe = if is_unsafe { "unsafe " } else { "" };
+    let doc = format_doc(&func.doc) + &try!(self.platforms_cfg_attribute(&func.platforms())) +
+              unsafe_fn_attributes(is_unsafe);
     Ok(match func.arguments {
       RustMethodArguments::SingleVariant(ref variant) => {
         let body = try!(self.generate_ffi_call(variant, &Vec::new()));
-        let return_type_for_signature = if variant.return_type.rust_api_type == RustType::Void {
-          String::new()
-        } else {
-          format!(" -> {}",
-                  self.rust_type_to_code(&variant.return_type.rust_api_type))
-        };
-        let all_lifetimes: Vec<_> = variant.arguments
-          .iter()
-          .filter_map(|x| x.argument_type.rust_api_type.lifetime())
-          .collect();
-        let lifetimes_text = if all_lifetimes.is_empty() {
-          String::new()
-        } else {
-          format!("<{}>",
-                  all_lifetimes.iter().map(|x| format!("'{}", x)).join(", "))
-        };
-
-        format!("{doc}{maybe_pub}fn {name}{lifetimes_text}({args}){return_type} {{\n{body}}}\n\n",
-                doc = format_doc(&func.doc),
-                maybe_pub = maybe_pub,
-                lifetimes_text = lifetimes_text,
-                name = try!(func.name.last_name()),
-                args = self.arg_texts(&variant.arguments, None).join(", "),
-                return_type = return_type_for_signature,
-                body = body)
+        self.generate_function_with_body(&doc,
+                                         maybe_pub,
+                                         maybe_unsafe,
+                                         try!(func.name.last_name()),
+                                         &variant.arguments,
+                                         &variant.return_type.rust_api_type,
+                                         &body)
+      }
+      RustMethodArguments::DefaultArguments(ref variants) => {
+        let shortest = try!(variants.first().chain_err(|| "variants can't be empty"));
+        let longest = try!(variants.last().chain_err(|| "variants can't be empty"));
+        let required_count = shortest.arguments.len();
+        let optional_arguments = &longest.arguments[required_count..];
+        if variants.len() != optional_arguments.len() + 1 {
+          return Err(unexpected(format!("DefaultArguments must contain a variant for each \
+                                         number of omitted arguments: {:?}",
+                                        func.name))
+            .into());
+        }
+        // optional arguments a, b, c are passed as Option<(a, Option<(b, Option<c>)>)>,
+        // so an argument can't be omitted unless all following arguments are omitted
+        let mut optional_type = None;
+        for arg in optional_arguments.iter().rev() {
+          let item_type = match optional_type {
+            Some(next) => RustType::Tuple(vec![arg.argument_type.rust_api_type.clone(), next]),
+            None => arg.argument_type.rust_api_type.clone(),
+          };
+          optional_type = Some(RustType::Common {
+            base: try!(RustName::new(vec!["Option".to_string()])),
+            generic_arguments: Some(vec![item_type]),
+            is_const: false,
+            is_const2: false,
+            indirection: RustTypeIndirection::None,
+          });
+        }
+        let mut optional_argument = try!(optional_arguments.first()
+            .chain_err(|| "optional arguments can't be empty"))
+          .clone();
+        if optional_arguments.len() > 1 {
+          optional_argument.name = "optional_args".to_string();
+        }
+        let optional_name = optional_argument.name.clone();
+        optional_argument.argument_type.rust_api_type =
+          try!(optional_type.chain_err(|| "optional arguments can't be empty"));
+        let mut arguments = shortest.arguments.clone();
+        arguments.push(optional_argument);
+        let mut arms = Vec::new();
+        for variant in variants {
+          let mut pattern = "None".to_string();
+          for (index, arg) in optional_arguments.iter()
+            .enumerate()
+            .take(variant.arguments.len() - required_count)
+            .rev() {
+            let binding = format!("{}{}",
+                                  if is_mut_value_argument(arg) { "mut " } else { "" },
+                                  arg.name);
+            pattern = if index + 1 == optional_arguments.len() {
+              format!("Some({})", binding)
+            } else {
+              format!("Some(({}, {}))", binding, pattern)
+            };
+          }
+          arms.push(format!("{} => {{\n{}\n}}\n",
+                            pattern,
+                            try!(self.generate_ffi_call(variant, &Vec::new()))));
+        }
+        let body = format!("match {} {{\n{}}}\n", optional_name, arms.join(""));
+        self.generate_function_with_body(&doc,
+                                         maybe_pub,
+                                         maybe_unsafe,
+                                         try!(func.name.last_name()),
+                                         &arguments,
+                                         &longest.return_type.rust_api_type,
+                                         &body)
       }
       RustMethodArguments::MultipleVariants { ref params_trait_name,
                                               ref params_trait_lifetime,
                                               ref params_trait_return_type,
                                               ref shared_arguments,
-                                              ref variant_argument_name } => {
+                                              ref variant_argument_name,
+                                              .. } => {
         let tpl_type = variant_argument_name.to_class_case();
         let body = format!("{}.exec({})",
                            variant_argument_name,
@@ -514,8 +978,9 @@ impl RustCodeGenerator {
           format!("{}::ReturnType", tpl_type)
         };
         format!(include_str!("../templates/crate/overloaded_function.rs.in"),
-                doc = format_doc(&func.doc),
+                doc = doc,
                 maybe_pub = maybe_pub,
+                maybe_unsafe = maybe_unsafe,
                 lifetime_arg = lifetime_arg,
                 lifetime = lifetime_specifier,
                 name = try!(func.name.last_name()),
@@ -529,7 +994,10 @@ impl RustCodeGenerator {
   }
 
   #[cfg_attr(feature="clippy", allow(collapsible_if))]
-  pub fn generate_lib_file(&self, modules: &[&String]) -> Result<()> {
+  pub fn generate_lib_file(&self,
+                           modules: &[&String],
+                           module_features: &[RustModuleFeature])
+                           -> Result<()> {
     let src_path = self.config.output_path.with_added("src");
     let lib_file_path = src_path.with_added("lib.rs");
     if lib_file_path.as_path().exists() {
@@ -552,8 +1020,18 @@ impl RustCodeGenerator {
         for dep in &self.config.dependencies {
           try!(lib_file.write(format!("pub extern crate {};\n\n", &dep.crate_name)));
         }
-        let mut extra_modules = vec!["ffi".to_string()];
-        try!(lib_file.write(include_str!("../templates/crate/new_uninitialized_trait.rs.in")));
+        let mut extra_modules = Vec::new();
+        if self.config.sys_crate {
+          try!(lib_file.write(format!("extern crate {} as ffi;\n\n", self.sys_crate_name())));
+        } else {
+          extra_modules.push("ffi".to_string());
+        }
+        if !self.has_template_flags_file() {
+          extra_modules.push("flags".to_string());
+        }
+        if let Some(ref name) = self.config.flat_module {
+          extra_modules.push(name.clone());
+        }
 
         if mode == &Mode::LibRs {
           if self.config.template_path.with_added("src").exists() {
@@ -589,6 +1067,9 @@ impl RustCodeGenerator {
             // some Rust methods are filtered
             try!(lib_file.write("#[allow(dead_code)]\n"));
           }
+          if module_features.iter().any(|x| &x.name == module) {
+            try!(lib_file.write(feature_cfg_attribute(&[module])));
+          }
           match *mode {
             Mode::LibInRs => {
               try!(lib_file.write(format!("{maybe_pub}mod {name} {{ \n  \
@@ -620,38 +1101,101 @@ impl RustCodeGenerator {
 
     for type1 in &data.types {
       results.push(format_doc(&type1.doc));
+      let cfg = try!(self.platforms_cfg_attribute(&type1.platforms));
       match type1.kind {
         RustTypeDeclarationKind::CppTypeWrapper { ref kind, ref methods, ref traits, .. } => {
           let r = match *kind {
-            RustTypeWrapperKind::Enum { ref values, ref is_flaggable } => {
-              let mut r =
-                format!(include_str!("../templates/crate/enum_declaration.rs.in"),
-                        name = type1.name,
-                        variants = values.iter()
-                          .map(|item| {
-                            format!("{}  {} = {}", format_doc(&item.doc), item.name, item.value)
-                          })
-                          .join(", \n"));
+            RustTypeWrapperKind::Enum { ref values,
+                                        ref is_flaggable,
+                                        ref representation,
+                                        ref integer_type } => {
+              let mut r = match *representation {
+                EnumRepresentation::RustEnum => {
+                  format!(include_str!("../templates/crate/enum_declaration.rs.in"),
+                          cfg = cfg,
+                          name = type1.name,
+                          integer_type = integer_type,
+                          variants = try!(values.iter()
+                              .map_if_ok(|item| -> Result<_> {
+                                Ok(format!("{}  {}{} = {}",
+                                           format_doc(&item.doc),
+                                           try!(self.platforms_cfg_attribute(&item.platforms)),
+                                           item.name,
+                                           enum_value_to_code(item.value, integer_type)))
+                              }))
+                            .join(", \n")) +
+                  &format!(include_str!("../templates/crate/impl_enum_conversions.rs.in"),
+                           cfg = cfg,
+                           name = type1.name,
+                           integer_type = integer_type,
+                           arms = try!(values.iter()
+                               .filter(|item| item.cpp_name.is_some())
+                               .map_if_ok(|item| -> Result<_> {
+                                 Ok(format!("      {}{} => Ok({}::{}),\n",
+                                            try!(self.platforms_cfg_attribute(&item.platforms)),
+                                            enum_value_to_code(item.value, integer_type),
+                                            type1.name,
+                                            item.name))
+                               }))
+                             .join(""))
+                }
+                EnumRepresentation::Newtype => {
+                  format!(include_str!("../templates/crate/enum_newtype_declaration.rs.in"),
+                          cfg = cfg,
+                          name = type1.name,
+                          integer_type = integer_type,
+                          constants = try!(values.iter()
+                              .map_if_ok(|item| -> Result<_> {
+                                Ok(format!("{}  {}pub const {}: {} = {}({});\n",
+                                           format_doc(&item.doc),
+                                           try!(self.platforms_cfg_attribute(&item.platforms)),
+                                           item.name,
+                                           type1.name,
+                                           type1.name,
+                                           enum_value_to_code(item.value, integer_type)))
+                              }))
+                            .join(""))
+                }
+              };
               if *is_flaggable {
+                let flags_name = |name: &str| -> Result<String> {
+                  Ok(try!(RustName::new(vec![self.config.crate_name.clone(),
+                                             "flags".to_string(),
+                                             name.to_string()]))
+                    .full_name(Some(&self.config.crate_name)))
+                };
                 r = r +
                     &format!(include_str!("../templates/crate/impl_flaggable.rs.in"),
+                             cfg = cfg,
                              name = type1.name,
-                             trait_type = try!(RustName::new(vec!["qt_core".to_string(),
-                                                                  "flags".to_string(),
-                                                                  "FlaggableEnum".to_string()]))
-                               .full_name(Some(&self.config.crate_name)));
+                             self_value = match *representation {
+                               EnumRepresentation::RustEnum => "self",
+                               EnumRepresentation::Newtype => "self.0",
+                             },
+                             trait_type = try!(flags_name("FlaggableEnum")));
+                // `BitOr` relies on the API of the generated `flags` module
+                if !self.has_template_flags_file() {
+                  r = r +
+                      &format!(include_str!("../templates/crate/impl_flaggable_bitor.rs.in"),
+                               cfg = cfg,
+                               name = type1.name,
+                               flags_type = try!(flags_name("Flags")));
+                }
               }
               r
             }
-            RustTypeWrapperKind::Struct { ref size } => {
+            RustTypeWrapperKind::Struct { ref size, ref alignment } => {
               format!(include_str!("../templates/crate/struct_declaration.rs.in"),
+                      cfg = cfg,
                       name = type1.name,
-                      size = size)
+                      size = size,
+                      alignment = alignment)
             }
           };
           results.push(r);
           if !methods.is_empty() {
-            results.push(format!("impl {} {{\n{}}}\n\n",
+            results.push(format!("{}impl {} {{\n{}}}\n\n",
+                                 cfg,
                                  type1.name,
                                  try!(methods.iter()
                                    .map_if_ok(|method| self.generate_rust_final_function(method)))
@@ -660,8 +1204,16 @@ impl RustCodeGenerator {
           for trait1 in traits {
             let trait_content = match trait1.trait_name {
               TraitName::CppDeletable { ref deleter_name } => {
-                format!("fn deleter() -> cpp_utils::Deleter<Self> {{\n  ::ffi::{}\n}}\n",
-                        deleter_name)
+                if self.config.sys_crate {
+                  // the deleter of the -sys crate accepts its plain struct type
+                  format!("fn deleter() -> cpp_utils::Deleter<Self> {{\n  unsafe {{ \
+                           ::std::mem::transmute(::ffi::{} as unsafe extern \"C\" fn(_)) \
+                           }}\n}}\n",
+                          deleter_name)
+                } else {
+                  format!("fn deleter() -> cpp_utils::Deleter<Self> {{\n  ::ffi::{}\n}}\n",
+                          deleter_name)
+                }
               }
               _ => {
                 try!(trait1.methods
@@ -671,7 +1223,8 @@ impl RustCodeGenerator {
               }
             };
 
-            results.push(format!("impl {} for {} {{\n{}}}\n\n",
+            results.push(format!("{}impl {} for {} {{\n{}}}\n\n",
+                                 cfg,
                                  trait1.trait_name.to_string(),
                                  type1.name,
                                  trait_content));
@@ -680,7 +1233,8 @@ impl RustCodeGenerator {
         RustTypeDeclarationKind::MethodParametersTrait { ref shared_arguments,
                                                          ref impls,
                                                          ref lifetime,
-                                                         ref return_type } => {
+                                                         ref return_type,
+                                                         ref is_unsafe } => {
           let arg_list = self.arg_texts(shared_arguments, lifetime.as_ref()).join(", ");
           let trait_lifetime_specifier = match *lifetime {
             Some(ref lf) => format!("<'{}>", lf),
@@ -699,11 +1253,13 @@ impl RustCodeGenerator {
           } else {
             "Self::ReturnType".to_string()
           };
-          results.push(format!("pub trait {name}{trait_lifetime_specifier} {{
+          results.push(format!("{cfg}pub trait {name}{trait_lifetime_specifier} {{
               {return_type_decl}\
-              fn exec(self, {arg_list}) -> {return_type_string};
+              {maybe_unsafe}fn exec(self, {arg_list}) -> {return_type_string};
             }}",
+                               cfg = cfg,
                                name = type1.name,
+                               maybe_unsafe = if *is_unsafe { "unsafe " } else { "" },
                                arg_list = arg_list,
                                trait_lifetime_specifier = trait_lifetime_specifier,
                                return_type_decl = return_type_decl,
@@ -764,10 +1320,18 @@ impl RustCodeGenerator {
             } else {
               format!("type ReturnType = {};", return_type_string)
             };
+            let variant_cfg =
+              try!(self.platforms_cfg_attribute(&variant.cpp_method.cpp_method.platforms));
             results.push(format!(include_str!("../templates/crate/impl_overloading_trait.rs.in"),
+                                 cfg = variant_cfg,
                                  lifetime_specifier = lifetime_specifier,
                                  trait_lifetime_specifier = trait_lifetime_specifier,
                                  trait_name = type1.name,
+                                 maybe_unsafe = if *is_unsafe {
+                                   format!("{}unsafe ", unsafe_fn_attributes(true))
+                                 } else {
+                                   String::new()
+                                 },
                                  final_arg_list = final_arg_list,
                                  impl_type = if tuple_item_types.len() == 1 {
                                    tuple_item_types[0].clone()
@@ -815,6 +1379,69 @@ impl RustCodeGenerator {
     assert!(path.as_path().is_file());
   }
 
+  /// Returns true if the crate template provides its own `flags` module.
+  fn has_template_flags_file(&self) -> bool {
+    self.config.template_path.with_added("src").with_added("flags.rs").exists()
+  }
+
+  /// Generates `flags` module containing types used for
+  /// flaggable enums, unless the crate template provides it.
+  pub fn generate_flags_file(&self) -> Result<()> {
+    if self.has_template_flags_file() {
+      return Ok(());
+    }
+    let file_path = self.config.output_path.with_added("src").with_added("flags.rs");
+    {
+      let mut file = try!(create_file(&file_path));
+      try!(file.write(include_str!("../templates/crate/flags.rs")));
+    }
+    self.call_rustfmt(&file_path);
+    Ok(())
+  }
+
+  /// Generates a module that re-exports all types of the crate
+  /// under their short names. If several types have the same name,
+  /// only the first one in alphabetical order of full names is re-exported.
+  /// Re-exports from modules gated by `module_features` and of
+  /// platform-specific types are gated as well.
+  pub fn generate_flat_module_file(&self,
+                                   name: &str,
+                                   types: &[RustProcessedTypeInfo],
+                                   module_features: &[RustModuleFeature])
+                                   -> Result<()> {
+    let mut crate_types: Vec<_> = types.iter()
+      .filter(|x| x.rust_name.crate_name() == Some(&self.config.crate_name))
+      .collect();
+    crate_types.sort_by(|a, b| a.rust_name.parts.cmp(&b.rust_name.parts));
+    let mut used_names = HashSet::new();
+    let mut code = String::new();
+    for type_info in crate_types {
+      let type_name = &type_info.rust_name;
+      let last_name = try!(type_name.last_name());
+      if used_names.contains(last_name) {
+        log::warning(format!("Type {} is not re-exported in module {} because of name conflict",
+                             type_name.full_name(None),
+                             name));
+        continue;
+      }
+      used_names.insert(last_name.clone());
+      if let Some(feature) = module_features.iter()
+        .find(|x| type_name.parts.get(1) == Some(&x.name)) {
+        code.push_str(&feature_cfg_attribute(&[&feature.name]));
+      }
+      code.push_str(&try!(self.platforms_cfg_attribute(&type_info.platforms)));
+      code.push_str(&format!("pub use {};\n",
+                             type_name.full_name(Some(&self.config.crate_name))));
+    }
+    let file_path = self.config.output_path.with_added("src").with_added(format!("{}.rs", name));
+    {
+      let mut file = try!(create_file(&file_path));
+      try!(file.write(code));
+    }
+    self.call_rustfmt(&file_path);
+    Ok(())
+  }
+
   pub fn generate_module_file(&self, data: &RustModule) -> Result<()> {
     let mut file_path = self.config.output_path.clone();
     file_path.push("src");
@@ -827,7 +1454,13 @@ impl RustCodeGenerator {
     Ok(())
   }
 
-  pub fn generate_ffi_file(&self, functions: &[(String, Vec<RustFFIFunction>)]) -> Result<()> {
+  /// Generates FFI function imports. If `module_features` are specified,
+  /// functions of each include file are only declared when a feature
+  /// using them is enabled.
+  pub fn generate_ffi_file(&self,
+                           functions: &[(String, Vec<RustFFIFunction>)],
+                           module_features: &[RustModuleFeature])
+                           -> Result<()> {
     let mut file_path = self.config.output_path.clone();
     file_path.push("src");
     file_path.push("ffi.rs");
@@ -837,37 +1470,166 @@ impl RustCodeGenerator {
       for dep in &self.config.dependencies {
         try!(file.write(format!("use {};\n\n", &dep.crate_name)));
       }
-      for item in &self.config.link_items {
-        match item.kind {
-          RustLinkKind::SharedLibrary => {
-            try!(file.write(format!("#[link(name = \"{}\")]\n", item.name)));
-          }
-          RustLinkKind::Framework => {
-            try!(file.write(format!("#[link(name = \"{}\", kind = \"framework\")]\n", item.name)));
-          }
+      try!(file.write(try!(self.ffi_declarations_code(functions, module_features))));
+    }
+    // no rustfmt for ffi file
+    Ok(())
+  }
+
+  /// Generates link attributes and the `extern` block with FFI functions.
+  fn ffi_declarations_code(&self,
+                           functions: &[(String, Vec<RustFFIFunction>)],
+                           module_features: &[RustModuleFeature])
+                           -> Result<String> {
+    let mut code = String::new();
+    for item in &self.config.link_items {
+      match item.kind {
+        RustLinkKind::SharedLibrary => {
+          code.push_str(&format!("#[link(name = \"{}\")]\n", item.name));
+        }
+        RustLinkKind::Framework => {
+          code.push_str(&format!("#[link(name = \"{}\", kind = \"framework\")]\n", item.name));
         }
       }
-      if !is_msvc() {
-        try!(file.write("#[link(name = \"stdc++\")]\n"));
-      }
-      if self.config.c_lib_is_shared {
-        try!(file.write(format!("#[link(name = \"{}\")]\n", &self.config.c_lib_name)));
+    }
+    if !is_msvc() {
+      code.push_str("#[link(name = \"stdc++\")]\n");
+    }
+    if self.config.c_lib_is_shared {
+      code.push_str(&format!("#[link(name = \"{}\")]\n", &self.config.c_lib_name));
+    } else {
+      code.push_str(&format!("#[link(name = \"{}\", kind = \"static\")]\n",
+                             &self.config.c_lib_name));
+    }
+    code.push_str("extern \"C\" {\n");
+
+    for &(ref include_file, ref functions) in functions {
+      code.push_str(&format!("  // Header: {}\n", include_file));
+      let features: Vec<_> = module_features.iter()
+        .filter(|x| x.include_files.iter().any(|f| f == include_file))
+        .map(|x| &x.name)
+        .collect();
+      let cfg_attribute = if features.is_empty() {
+        String::new()
       } else {
-        try!(file.write(format!("#[link(name = \"{}\", kind = \"static\")]\n",
-                                &self.config.c_lib_name)));
+        format!("  {}", feature_cfg_attribute(&features))
+      };
+      for function in functions {
+        code.push_str(&cfg_attribute);
+        let platforms_cfg = try!(self.platforms_cfg_attribute(&function.platforms));
+        if !platforms_cfg.is_empty() {
+          code.push_str(&format!("  {}", platforms_cfg));
+        }
+        code.push_str(&self.rust_ffi_function_to_code(function));
       }
-      try!(file.write("extern \"C\" {\n"));
+      code.push_str("\n");
+    }
+    code.push_str("}\n");
+    Ok(code)
+  }
 
-      for &(ref include_file, ref functions) in functions {
-        try!(file.write(format!("  // Header: {}\n", include_file)));
-        for function in functions {
-          try!(file.write(self.rust_ffi_function_to_code(function)));
+  /// Generates plain struct definitions and type aliases
+  /// for enums of `module` and its submodules in the `-sys` crate.
+  fn sys_module_code(&self, module: &RustModule) -> Result<String> {
+    let mut code = String::new();
+    for type1 in &module.types {
+      if let RustTypeDeclarationKind::CppTypeWrapper { ref kind, ref cpp_type_name, .. } =
+             type1.kind {
+        code.push_str(&format!("/// C++ type: `{}`\n", cpp_type_name));
+        code.push_str(&try!(self.platforms_cfg_attribute(&type1.platforms)));
+        code.push_str(&match *kind {
+          RustTypeWrapperKind::Enum { ref integer_type, .. } => {
+            format!("pub type {} = {};\n\n", type1.name, integer_type)
+          }
+          RustTypeWrapperKind::Struct { ref size, ref alignment } => {
+            format!(include_str!("../templates/crate/struct_declaration.rs.in"),
+                    cfg = "",
+                    name = type1.name,
+                    size = size,
+                    alignment = alignment)
+          }
+        });
+      }
+    }
+    for submodule in &module.submodules {
+      code.push_str(&format!("pub mod {} {{\n{}}}\n\n",
+                             submodule.name,
+                             try!(self.sys_module_code(submodule))));
+    }
+    Ok(code)
+  }
+
+  /// Generates the `-sys` crate in the `sys` subdirectory.
+  /// The crate contains raw FFI declarations and plain struct
+  /// definitions mirroring the module structure of the main crate.
+  ///
+  /// The `-sys` crate owns the C wrapper library: it declares `links`,
+  /// and its build script builds the library from `c_lib/source`
+  /// of the parent directory and links to it.
+  pub fn generate_sys_crate(&self, data: &RustGeneratorOutput) -> Result<()> {
+    let sys_path = self.config.output_path.with_added("sys");
+    let src_path = sys_path.with_added("src");
+    try!(create_dir_all(&src_path));
+    try!(self.generate_sys_build_rs(&sys_path));
+
+    let cargo_toml_data = toml::Value::Table({
+      let package = toml::Value::Table({
+        let mut table = toml::Table::new();
+        table.insert("name".to_string(), toml::Value::String(self.sys_crate_name()));
+        table.insert("version".to_string(),
+                     toml::Value::String(self.config.crate_version.clone()));
+        let authors = self.config
+          .crate_authors
+          .iter()
+          .map(|x| toml::Value::String(x.clone()))
+          .collect();
+        table.insert("authors".to_string(), toml::Value::Array(authors));
+        table.insert("build".to_string(),
+                     toml::Value::String("build.rs".to_string()));
+        table.insert("links".to_string(),
+                     toml::Value::String(self.config.c_lib_name.clone()));
+        table
+      });
+      let dependencies = toml::Value::Table({
+        let mut table = toml::Table::new();
+        table.insert("libc".to_string(), toml::Value::String("0.2".to_string()));
+        for dep in &self.config.dependencies {
+          let mut table_dep = toml::Table::new();
+          let (name, path) = match dep.sys_crate_name {
+            Some(ref sys_crate_name) => (sys_crate_name.clone(), dep.crate_path.with_added("sys")),
+            None => (dep.crate_name.clone(), dep.crate_path.clone()),
+          };
+          table_dep.insert("path".to_string(),
+                           toml::Value::String(try!(path_to_str(&path)).to_string()));
+          table.insert(name, toml::Value::Table(table_dep));
         }
-        try!(file.write("\n"));
+        table
+      });
+      let mut table = toml::Table::new();
+      table.insert("package".to_string(), package);
+      table.insert("dependencies".to_string(), dependencies);
+      table
+    });
+    let mut cargo_toml_file = try!(create_file(sys_path.with_added("Cargo.toml")));
+    try!(cargo_toml_file.write(cargo_toml_data.to_string()));
+
+    let lib_file_path = src_path.with_added("lib.rs");
+    {
+      let mut lib_file = try!(create_file(&lib_file_path));
+      try!(lib_file.write("pub extern crate libc;\n"));
+      for dep in &self.config.dependencies {
+        try!(lib_file.write(format!("pub extern crate {};\n",
+                                    dep.sys_crate_name.as_ref().unwrap_or(&dep.crate_name))));
+      }
+      try!(lib_file.write("\n"));
+      for module in &data.modules {
+        try!(lib_file.write(format!("pub mod {} {{\n{}}}\n\n",
+                                    module.name,
+                                    try!(self.sys_module_code(module)))));
       }
-      try!(file.write("}\n"));
+      try!(lib_file.write(try!(self.ffi_declarations_code(&data.ffi_functions, &[]))));
     }
-    // no rustfmt for ffi file
+    // no rustfmt for ffi declarations
     Ok(())
   }
 }
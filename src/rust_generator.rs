@@ -1,4 +1,6 @@
 use caption_strategy::TypeCaptionStrategy;
+use coverage_report::{SkippedItem, SkipStage};
+use config::{PointerPolicy, ReturnLifetime, EnumRepresentation, ModuleLayout, OverloadStrategy};
 use cpp_data::{CppTypeKind, EnumValue};
 use cpp_ffi_data::{CppAndFfiMethod, CppFfiArgumentMeaning, CppFfiType, IndirectionChange};
 use cpp_ffi_generator::CppAndFfiData;
@@ -10,15 +12,18 @@ use doc_formatter;
 use errors::{Result, ChainErr, unexpected};
 use log;
 use qt_doc_parser::{QtDocData, QtDocResultForMethod};
+use rename_rules::{RenameRules, RenameTarget};
 use rust_info::{RustTypeDeclaration, RustTypeDeclarationKind, RustTypeWrapperKind, RustModule,
                 RustMethod, RustMethodScope, RustMethodArgument, RustMethodArgumentsVariant,
-                RustMethodArguments, TraitImpl, TraitName, RustEnumValue, RustMethodSelfArgKind};
+                RustMethodArguments, TraitImpl, TraitName, RustEnumValue, RustMethodSelfArgKind,
+                RustModuleFeature, variants_platforms};
 use rust_type::{RustName, RustType, CompleteType, RustTypeIndirection, RustFFIFunction,
                 RustFFIArgument, RustToCTypeConversion};
 use string_utils::{CaseOperations, VecCaseOperations, WordIterator};
-use utils::{add_to_multihash, MapIfOk};
+use utils::{add_to_multihash, parallel_map, MapIfOk};
 
-use std::collections::{HashMap, HashSet, hash_map};
+use std::collections::{BTreeMap, HashMap, HashSet, hash_map};
+use std::sync::Mutex;
 
 pub use serializable::{RustProcessedTypeKind, RustProcessedTypeInfo};
 
@@ -79,14 +84,60 @@ fn remove_qt_prefix_and_convert_case(s: &str, case: Case, remove_qt_prefix: bool
   }
 }
 
-/// Removes ".h" from include file name and performs the same
-/// processing as `remove_qt_prefix_and_convert_case()` for snake case.
-fn include_file_to_module_name(include_file: &str, remove_qt_prefix: bool) -> String {
+/// Calculates Rust name of a module corresponding to a namespace,
+/// a class or an include file name without extension.
+fn module_rust_name(cpp_name: &str, config: &RustGeneratorConfig) -> String {
+  if let Some(rust_name) = config.rename_rules.explicit_name(RenameTarget::Module, cpp_name) {
+    return rust_name.clone();
+  }
+  remove_qt_prefix_and_convert_case(&config.rename_rules.apply(RenameTarget::Module, cpp_name),
+                                    Case::Snake,
+                                    config.remove_qt_prefix)
+}
+
+/// Returns `name`, or `name` with a numeric suffix if it's already used.
+/// `group_names` are names generated for the current method, and `used_names`
+/// are all other method names of the scope. `original_name` belongs to
+/// the current method, so it's only a conflict if present in `group_names`.
+fn unique_method_name(name: String,
+                      original_name: &str,
+                      group_names: &HashSet<String>,
+                      used_names: &HashSet<String>)
+                      -> String {
+  let is_used = |x: &String| {
+    group_names.contains(x) || (x != original_name && used_names.contains(x))
+  };
+  if !is_used(&name) {
+    return name;
+  }
+  let mut index = 2;
+  while is_used(&format!("{}{}", name, index)) {
+    index += 1;
+  }
+  log::warning(format!("Method name conflict resolved by adding index: {}{}",
+                       name,
+                       index));
+  format!("{}{}", name, index)
+}
+
+/// Returns the module configured for the include file in `module_mapping`.
+/// Otherwise, removes ".h" from include file name and performs the same
+/// processing as `module_rust_name()`. Explicit rename rules
+/// can refer to the include file name with or without extension.
+fn include_file_to_module_name(include_file: &str, config: &RustGeneratorConfig) -> String {
+  if let Some(&(_, ref module)) = config.module_mapping
+    .iter()
+    .find(|&&(ref name, _)| name == include_file) {
+    return module.clone();
+  }
+  if let Some(rust_name) = config.rename_rules.explicit_name(RenameTarget::Module, include_file) {
+    return rust_name.clone();
+  }
   let mut r = include_file.to_string();
   if let Some(index) = r.find('.') {
     r = r[0..index].to_string();
   }
-  remove_qt_prefix_and_convert_case(&r, Case::Snake, remove_qt_prefix)
+  module_rust_name(&r, config)
 }
 
 /// Adds "_" to a string if it is a reserved word in Rust
@@ -106,29 +157,46 @@ fn sanitize_rust_identifier(name: &str) -> String {
 }
 
 /// Prepares enum variants for being represented in Rust:
-/// - Converts variant names to proper case;
+/// - Applies rename rules and converts variant names to proper case;
 /// - Removes duplicate variants that have the same associated value.
 /// Rust does not allow such duplicates.
 /// - If there is only one variant, adds another variant.
 /// Rust does not allow repr(C) enums having only one variant.
-fn prepare_enum_values(values: &[EnumValue], name: &str) -> Vec<RustEnumValue> {
+fn prepare_enum_values(values: &[EnumValue],
+                       name: &str,
+                       rename_rules: &RenameRules)
+                       -> Vec<RustEnumValue> {
   let mut value_to_variant: HashMap<i64, RustEnumValue> = HashMap::new();
   for variant in values {
     let value = variant.value;
     match value_to_variant.entry(value) {
-      hash_map::Entry::Occupied(entry) => {
+      hash_map::Entry::Occupied(mut entry) => {
         log::warning(format!("warning: {}: duplicated enum variant removed: {} (previous \
                               variant: {})",
                              name,
                              variant.name,
                              entry.get().name));
+        // the remaining variant represents the value on all platforms of both variants
+        let existing = entry.get_mut();
+        if variant.platforms.is_empty() {
+          existing.platforms.clear();
+        } else if !existing.platforms.is_empty() {
+          for platform in &variant.platforms {
+            if !existing.platforms.contains(platform) {
+              existing.platforms.push(platform.clone());
+            }
+          }
+        }
       }
       hash_map::Entry::Vacant(entry) => {
         entry.insert(RustEnumValue {
-          name: sanitize_rust_identifier(&variant.name.to_class_case()),
+          name: sanitize_rust_identifier(&rename_rules.apply(RenameTarget::EnumVariant,
+                                                             &variant.name)
+            .to_class_case()),
           cpp_name: Some(variant.name.clone()),
           value: variant.value,
           doc: format!("C++ variant: {}", &variant.name),
+          platforms: variant.platforms.clone(),
         });
 
       }
@@ -149,54 +217,106 @@ fn prepare_enum_values(values: &[EnumValue], name: &str) -> Vec<RustEnumValue> {
                               doc: "This variant is added in Rust because enums with one \
                                             variant and C representation are not supported."
                                 .to_string(),
+                              platforms: Vec::new(),
                             });
   }
   let mut result: Vec<_> = value_to_variant.into_iter()
     .map(|(_val, variant)| variant)
     .collect();
   if more_than_one {
-    let new_names = {
-      let all_words: Vec<Vec<&str>> = result.iter()
-        .map(|x| WordIterator::new(&x.name).collect())
-        .collect();
-      let tmp_buffer = all_words[0].clone();
-      let mut common_prefix = &tmp_buffer[..];
-      let mut common_suffix = &tmp_buffer[..];
-      for item in &all_words {
-        while !common_prefix.is_empty() &&
-              (item.len() < common_prefix.len() || &item[..common_prefix.len()] != common_prefix) {
-          common_prefix = &common_prefix[..common_prefix.len() - 1];
-        }
-        while !common_suffix.is_empty() &&
-              (item.len() < common_suffix.len() ||
-               &item[item.len() - common_suffix.len()..] != common_suffix) {
-          common_suffix = &common_suffix[1..];
-        }
-      }
-      let new_names: Vec<_> = all_words.iter()
-        .map(|item| item[common_prefix.len()..item.len() - common_suffix.len()].join(""))
-        .collect();
-      if new_names.iter()
-        .any(|item| if let Some(ch) = item.chars().next() {
-          ch.is_digit(10)
-        } else {
-          true
-        }) {
-        None
-      } else {
-        Some(new_names)
+    remove_enum_values_common_affixes(&mut result);
+  }
+  apply_explicit_enum_value_names(&mut result, name, rename_rules);
+  result.sort_by(|a, b| a.value.cmp(&b.value));
+  result
+}
+
+/// Prepares enum variants for being represented as associated
+/// constants of a newtype:
+/// - Keeps all variants, including the ones with duplicate values;
+/// - Applies rename rules and converts variant names to upper snake case.
+fn prepare_enum_constants(values: &[EnumValue],
+                          name: &str,
+                          rename_rules: &RenameRules)
+                          -> Vec<RustEnumValue> {
+  let mut result: Vec<_> = values.iter()
+    .map(|variant| {
+      RustEnumValue {
+        name: rename_rules.apply(RenameTarget::EnumVariant, &variant.name).to_class_case(),
+        cpp_name: Some(variant.name.clone()),
+        value: variant.value,
+        doc: format!("C++ variant: {}", &variant.name),
+        platforms: variant.platforms.clone(),
       }
-    };
-    if let Some(new_names) = new_names {
-      assert_eq!(new_names.len(), result.len());
-      for i in 0..new_names.len() {
-        result[i].name = sanitize_rust_identifier(&new_names[i].clone());
+    })
+    .collect();
+  if result.len() > 1 {
+    remove_enum_values_common_affixes(&mut result);
+  }
+  for item in &mut result {
+    item.name = item.name.to_snake_case().to_uppercase();
+  }
+  apply_explicit_enum_value_names(&mut result, name, rename_rules);
+  result.sort_by(|a, b| a.value.cmp(&b.value));
+  result
+}
+
+/// Replaces names of enum variants that have an explicit rename rule.
+/// Rule's C++ name is the enum name and the variant name joined with "::".
+fn apply_explicit_enum_value_names(result: &mut [RustEnumValue],
+                                   enum_name: &str,
+                                   rename_rules: &RenameRules) {
+  for item in result {
+    if let Some(ref cpp_name) = item.cpp_name {
+      if let Some(rust_name) = rename_rules.explicit_name(RenameTarget::EnumVariant,
+                                                          &format!("{}::{}", enum_name, cpp_name)) {
+        item.name = rust_name.clone();
       }
     }
+  }
+}
 
+/// Removes words that all variant names start or end with,
+/// unless a name would become empty or start with a digit.
+fn remove_enum_values_common_affixes(result: &mut [RustEnumValue]) {
+  let new_names = {
+    let all_words: Vec<Vec<&str>> = result.iter()
+      .map(|x| WordIterator::new(&x.name).collect())
+      .collect();
+    let tmp_buffer = all_words[0].clone();
+    let mut common_prefix = &tmp_buffer[..];
+    let mut common_suffix = &tmp_buffer[..];
+    for item in &all_words {
+      while !common_prefix.is_empty() &&
+            (item.len() < common_prefix.len() || &item[..common_prefix.len()] != common_prefix) {
+        common_prefix = &common_prefix[..common_prefix.len() - 1];
+      }
+      while !common_suffix.is_empty() &&
+            (item.len() < common_suffix.len() ||
+             &item[item.len() - common_suffix.len()..] != common_suffix) {
+        common_suffix = &common_suffix[1..];
+      }
+    }
+    let new_names: Vec<_> = all_words.iter()
+      .map(|item| item[common_prefix.len()..item.len() - common_suffix.len()].join(""))
+      .collect();
+    if new_names.iter()
+      .any(|item| if let Some(ch) = item.chars().next() {
+        ch.is_digit(10)
+      } else {
+        true
+      }) {
+      None
+    } else {
+      Some(new_names)
+    }
+  };
+  if let Some(new_names) = new_names {
+    assert_eq!(new_names.len(), result.len());
+    for i in 0..new_names.len() {
+      result[i].name = sanitize_rust_identifier(&new_names[i].clone());
+    }
   }
-  result.sort_by(|a, b| a.value.cmp(&b.value));
-  result
 }
 
 
@@ -205,6 +325,8 @@ pub struct RustGenerator {
   config: RustGeneratorConfig,
   processed_types: Vec<RustProcessedTypeInfo>,
   dependency_types: Vec<RustProcessedTypeInfo>,
+  /// C++ methods that were dropped during processing
+  skipped_items: Mutex<Vec<SkippedItem>>,
 }
 
 /// Results of adapting API for Rust wrapper.
@@ -216,6 +338,10 @@ pub struct RustGeneratorOutput {
   pub ffi_functions: Vec<(String, Vec<RustFFIFunction>)>,
   /// List of processed C++ types and their corresponding Rust names
   pub processed_types: Vec<RustProcessedTypeInfo>,
+  /// Cargo features corresponding to top level modules
+  pub module_features: Vec<RustModuleFeature>,
+  /// C++ methods that could not be wrapped
+  pub skipped_items: Vec<SkippedItem>,
 }
 
 /// Config for `rust_generator` module.
@@ -225,10 +351,35 @@ pub struct RustGeneratorConfig {
   /// Flag instructing to remove leading "Q" and "Qt"
   /// from identifiers.
   pub remove_qt_prefix: bool,
+  /// Rules for converting C++ identifiers to Rust identifiers
+  pub rename_rules: RenameRules,
+  /// Placement of items into modules
+  pub module_layout: ModuleLayout,
+  /// Modules for specific include files
+  pub module_mapping: Vec<(String, String)>,
+  /// Representation of overloaded methods
+  pub overload_strategy: OverloadStrategy,
 
   pub qt_doc_data: Option<QtDocData>,
+  /// Pointer policy for methods not listed in `pointer_policies`
+  pub default_pointer_policy: PointerPolicy,
+  /// Pointer policies for specific methods and classes
+  pub pointer_policies: Vec<(String, PointerPolicy)>,
+  /// Methods and classes that must be marked as `unsafe`
+  pub unsafe_methods: Vec<String>,
+  /// Lifetimes of references returned by specific methods
+  pub return_lifetimes: Vec<(String, ReturnLifetime)>,
+  /// Representation of enums not listed in `enum_representations`
+  pub default_enum_representation: EnumRepresentation,
+  /// Representations of specific enums
+  pub enum_representations: Vec<(String, EnumRepresentation)>,
+  /// Enums explicitly declared as sets of flags
+  pub flag_enums: Vec<String>,
+  /// Class templates converted to `flags::Flags`
+  pub flags_containers: Vec<String>,
+  /// Number of threads used for generating top level modules
+  pub num_jobs: usize,
 }
-// TODO: implement removal of arbitrary prefixes (#25)
 
 /// Execute processing
 #[cfg_attr(feature="clippy", allow(extend_from_slice))]
@@ -242,61 +393,70 @@ pub fn run(input_data: CppAndFfiData,
     dependency_types: dependency_rust_types,
     input_data: input_data,
     config: config,
+    skipped_items: Mutex::new(Vec::new()),
   };
   let mut modules = Vec::new();
   {
-    let mut cpp_methods: Vec<&CppAndFfiMethod> = Vec::new();
-    for header in &generator.input_data.cpp_ffi_headers {
-      cpp_methods.extend(header.methods.iter());
-    }
-    let mut module_names_set = HashSet::new();
+    // Methods are grouped by top level module, so that
+    // top level modules can be generated in parallel.
+    let mut module_methods: HashMap<String, Vec<&CppAndFfiMethod>> = HashMap::new();
     for item in &generator.processed_types {
-      if !module_names_set.contains(&item.rust_name.parts[1]) {
-        module_names_set.insert(item.rust_name.parts[1].clone());
+      if !module_methods.contains_key(&item.rust_name.parts[1]) {
+        module_methods.insert(item.rust_name.parts[1].clone(), Vec::new());
       }
     }
-    cpp_methods = cpp_methods.into_iter()
-      .filter(|method| {
-        if let Some(ref info) = method.cpp_method.class_membership {
-          if !generator.processed_types.iter().any(|t| {
+    for header in &generator.input_data.cpp_ffi_headers {
+      for method in &header.methods {
+        let module_name = if let Some(ref info) = method.cpp_method.class_membership {
+          match generator.processed_types.iter().find(|t| {
             t.cpp_name == info.class_type.name &&
             t.cpp_template_arguments == info.class_type.template_arguments
           }) {
-            log::warning("Warning: method is skipped because class type is not \
-                                  available in Rust:");
-            log::warning(format!("{}\n", method.short_text()));
-            return false;
+            Some(type_info) => type_info.rust_name.parts[1].clone(),
+            None => {
+              log::warning("Warning: method is skipped because class type is not \
+                                    available in Rust:");
+              log::warning(format!("{}\n", method.short_text()));
+              generator.skipped_items
+                .lock()
+                .unwrap()
+                .push(SkippedItem::method(&method.cpp_method,
+                                          SkipStage::RustClassUnavailable,
+                                          "class type is not available in Rust"));
+              continue;
+            }
           }
-        }
-        true
-      })
-      .collect();
-    for method in cpp_methods.clone() {
-      if method.cpp_method.class_membership.is_none() {
-        let rust_name = try!(calculate_rust_name(&method.cpp_method.name,
-                                                 &method.cpp_method.include_file,
-                                                 true,
-                                                 method.cpp_method.operator.as_ref(),
-                                                 &generator.config));
-        if !module_names_set.contains(&rust_name.parts[1]) {
-          module_names_set.insert(rust_name.parts[1].clone());
-        }
+        } else {
+          try!(calculate_rust_name(&method.cpp_method.name,
+                                   &method.cpp_method.include_file,
+                                   true,
+                                   method.cpp_method.operator.as_ref(),
+                                   &generator.config))
+            .parts[1]
+            .clone()
+        };
+        add_to_multihash(&mut module_methods, module_name, method);
       }
     }
 
-    let mut module_names: Vec<_> = module_names_set.into_iter().collect();
-    module_names.sort();
-    let module_count = module_names.len();
-    for (i, module_name) in module_names.into_iter().enumerate() {
+    let mut module_methods: Vec<_> = module_methods.into_iter().collect();
+    module_methods.sort_by(|a, b| a.0.cmp(&b.0));
+    let module_count = module_methods.len();
+    let module_methods: Vec<_> = module_methods.into_iter().enumerate().collect();
+    let results = parallel_map(&module_methods, generator.config.num_jobs, |item| {
+      let (i, (ref module_name, ref methods)) = *item;
       log::info(format!("({}/{}) Generating module: {}",
                         i + 1,
                         module_count,
                         module_name));
       let full_module_name = try!(RustName::new(vec![generator.config.crate_name.clone(),
-                                                     module_name]));
-      let (module, tmp_cpp_methods) =
-        try!(generator.generate_module(cpp_methods, &full_module_name));
-      cpp_methods = tmp_cpp_methods;
+                                                     module_name.clone()]));
+      generator.generate_module(methods.clone(), &full_module_name)
+    });
+    let mut cpp_methods = Vec::new();
+    for result in results {
+      let (module, mut tmp_cpp_methods) = try!(result);
+      cpp_methods.append(&mut tmp_cpp_methods);
       if let Some(module) = module {
         modules.push(module);
       }
@@ -334,13 +494,139 @@ pub fn run(input_data: CppAndFfiData,
   if any_not_declared {
     return Err(unexpected("unprocessed cpp types left").into());
   }
+  let ffi_functions = generator.ffi();
+  let module_features = module_features(&modules, &ffi_functions, &generator.config.crate_name);
   Ok(RustGeneratorOutput {
-    ffi_functions: generator.ffi(),
+    ffi_functions: ffi_functions,
     modules: modules,
     processed_types: generator.processed_types,
+    module_features: module_features,
+    skipped_items: generator.skipped_items.into_inner().unwrap(),
   })
 }
 
+/// Types and FFI functions used by a module
+#[derive(Default)]
+struct ModuleUsage {
+  type_names: Vec<RustName>,
+  c_names: HashSet<String>,
+}
+
+impl ModuleUsage {
+  fn add_type(&mut self, rust_type: &RustType) {
+    self.type_names.extend(rust_type.referenced_names().into_iter().cloned());
+  }
+
+  fn add_argument(&mut self, arg: &RustMethodArgument) {
+    self.add_type(&arg.argument_type.rust_api_type);
+    self.add_type(&arg.argument_type.rust_ffi_type);
+  }
+
+  fn add_variant(&mut self, variant: &RustMethodArgumentsVariant) {
+    for arg in &variant.arguments {
+      self.add_argument(arg);
+    }
+    self.add_type(&variant.return_type.rust_api_type);
+    self.add_type(&variant.return_type.rust_ffi_type);
+    self.c_names.insert(variant.cpp_method.c_name.clone());
+  }
+
+  fn add_method(&mut self, method: &RustMethod) {
+    match method.arguments {
+      RustMethodArguments::SingleVariant(ref variant) => self.add_variant(variant),
+      RustMethodArguments::MultipleVariants { ref shared_arguments,
+                                              ref params_trait_return_type,
+                                              .. } => {
+        // variants are listed in the parameters trait declaration
+        for arg in shared_arguments {
+          self.add_argument(arg);
+        }
+        if let Some(ref return_type) = *params_trait_return_type {
+          self.add_type(return_type);
+        }
+      }
+      RustMethodArguments::DefaultArguments(ref variants) => {
+        for variant in variants {
+          self.add_variant(variant);
+        }
+      }
+    }
+  }
+
+  fn add_module(&mut self, module: &RustModule) {
+    for type1 in &module.types {
+      match type1.kind {
+        RustTypeDeclarationKind::CppTypeWrapper { ref methods, ref traits, .. } => {
+          for method in methods {
+            self.add_method(method);
+          }
+          for trait1 in traits {
+            if let TraitName::CppDeletable { ref deleter_name } = trait1.trait_name {
+              self.c_names.insert(deleter_name.clone());
+            }
+            for method in &trait1.methods {
+              self.add_method(method);
+            }
+          }
+        }
+        RustTypeDeclarationKind::MethodParametersTrait { ref shared_arguments,
+                                                         ref return_type,
+                                                         ref impls,
+                                                         .. } => {
+          for arg in shared_arguments {
+            self.add_argument(arg);
+          }
+          if let Some(ref return_type) = *return_type {
+            self.add_type(return_type);
+          }
+          for variant in impls {
+            self.add_variant(variant);
+          }
+        }
+      }
+    }
+    for method in &module.functions {
+      self.add_method(method);
+    }
+    for submodule in &module.submodules {
+      self.add_module(submodule);
+    }
+  }
+}
+
+/// Computes a Cargo feature for each top level module. A feature depends on
+/// features of other modules of the crate whose types its module uses.
+pub fn module_features(modules: &[RustModule],
+                       ffi_functions: &[(String, Vec<RustFFIFunction>)],
+                       crate_name: &str)
+                       -> Vec<RustModuleFeature> {
+  let module_names: HashSet<_> = modules.iter().map(|m| m.name.clone()).collect();
+  let mut result = Vec::new();
+  for module in modules {
+    let mut usage = ModuleUsage::default();
+    usage.add_module(module);
+    let mut dependencies: Vec<_> = usage.type_names
+      .iter()
+      .filter(|name| name.parts.len() > 2 && name.parts[0] == crate_name)
+      .map(|name| name.parts[1].clone())
+      .filter(|name| name != &module.name && module_names.contains(name))
+      .collect();
+    dependencies.sort();
+    dependencies.dedup();
+    let include_files = ffi_functions.iter()
+      .filter(|&&(_, ref functions)| functions.iter().any(|f| usage.c_names.contains(&f.name)))
+      .map(|&(ref include_file, _)| include_file.clone())
+      .collect();
+    result.push(RustModuleFeature {
+      name: module.name.clone(),
+      dependencies: dependencies,
+      include_files: include_files,
+    });
+  }
+  result.sort_by(|a, b| a.name.cmp(&b.name));
+  result
+}
+
 /// Generates `RustName` for specified function or type name,
 /// including crate name and modules list.
 fn calculate_rust_name(name: &str,
@@ -355,25 +641,33 @@ fn calculate_rust_name(name: &str,
   let last_part = if let Some(operator) = operator {
     try!(operator_rust_name(operator))
   } else {
-    remove_qt_prefix_and_convert_case(&original_last_part,
-                                      if is_function {
-                                        Case::Snake
-                                      } else {
-                                        Case::Class
-                                      },
-                                      config.remove_qt_prefix)
+    let (target, case) = if is_function {
+      (RenameTarget::Method, Case::Snake)
+    } else {
+      (RenameTarget::Type, Case::Class)
+    };
+    if let Some(rust_name) = config.rename_rules.explicit_name(target, name) {
+      rust_name.clone()
+    } else {
+      remove_qt_prefix_and_convert_case(&config.rename_rules.apply(target, &original_last_part),
+                                        case,
+                                        config.remove_qt_prefix)
+    }
   };
 
+  // items of the global namespace are placed by include file in all layouts
+  let use_include_file = config.module_layout == ModuleLayout::IncludeFile ||
+                         split_parts.is_empty();
   let mut parts = Vec::new();
   parts.push(config.crate_name.clone());
-  parts.push(include_file_to_module_name(include_file, config.remove_qt_prefix));
+  if use_include_file {
+    parts.push(include_file_to_module_name(include_file, config));
+  }
   for part in split_parts {
-    parts.push(remove_qt_prefix_and_convert_case(&part.to_string(),
-                                                 Case::Snake,
-                                                 config.remove_qt_prefix));
+    parts.push(module_rust_name(part, config));
   }
 
-  if parts.len() > 2 && parts[1] == parts[2] {
+  if use_include_file && parts.len() > 2 && parts[1] == parts[2] {
     // special case
     parts.remove(2);
   }
@@ -397,22 +691,31 @@ fn process_types(input_data: &CppAndFfiData,
       cpp_name: type_info.name.clone(),
       cpp_template_arguments: None,
       kind: match type_info.kind {
-        CppTypeKind::Class { ref size, .. } => {
+        CppTypeKind::Class { ref size, ref alignment, .. } => {
           RustProcessedTypeKind::Class {
             size: try!(size.chain_err(|| "size must be present")),
+            alignment: try!(alignment.chain_err(|| "alignment must be present")),
             is_deletable: input_data.cpp_data.has_public_destructor(&CppTypeClassBase {
               name: type_info.name.clone(),
               template_arguments: None,
             }),
           }
         }
-        CppTypeKind::Enum { ref values } => RustProcessedTypeKind::Enum { values: values.clone() },
+        CppTypeKind::Enum { ref values, ref underlying_type, ref is_scoped } => {
+          RustProcessedTypeKind::Enum {
+            values: values.clone(),
+            integer_type: try!(enum_integer_type(underlying_type)
+              .chain_err(|| format!("failed to process enum {}", type_info.name))),
+            is_scoped: *is_scoped,
+          }
+        }
       },
       rust_name: try!(calculate_rust_name(&type_info.name,
                                           &type_info.include_file,
                                           false,
                                           None,
                                           config)),
+      platforms: type_info.platforms.clone(),
     });
   }
   let template_final_name =
@@ -424,9 +727,11 @@ fn process_types(input_data: &CppAndFfiData,
         for x in args {
           let rust_type = try!(complete_type(result,
                                              dependency_types,
-                                             &try!(x.to_cpp_ffi_type(CppTypeRole::NotReturnType)),
+                                             &try!(x.to_cpp_ffi_type(CppTypeRole::NotReturnType,
+                                                                     &config.flags_containers)),
                                              &CppFfiArgumentMeaning::Argument(0),
-                                             &ReturnValueAllocationPlace::NotApplicable));
+                                             &ReturnValueAllocationPlace::NotApplicable,
+                                             PointerPolicy::RawPointer));
           arg_captions.push(try!(rust_type.rust_api_type.caption()).to_class_case());
         }
       } else {
@@ -437,8 +742,8 @@ fn process_types(input_data: &CppAndFfiData,
     };
   let mut name_failed_items = Vec::new();
   for template_instantiations in &input_data.cpp_data.template_instantiations {
-    if template_instantiations.class_name == "QFlags" {
-      // special processing is implemented for QFlags
+    if config.flags_containers.iter().any(|x| x == &template_instantiations.class_name) {
+      // flags containers are represented by flags::Flags
       continue;
     }
     for ins in &template_instantiations.instantiations {
@@ -447,6 +752,7 @@ fn process_types(input_data: &CppAndFfiData,
         cpp_template_arguments: Some(ins.template_arguments.clone()),
         kind: RustProcessedTypeKind::Class {
           size: ins.size,
+          alignment: ins.alignment,
           is_deletable: input_data.cpp_data.has_public_destructor(&CppTypeClassBase {
             name: template_instantiations.class_name.clone(),
             template_arguments: Some(ins.template_arguments.clone()),
@@ -457,6 +763,7 @@ fn process_types(input_data: &CppAndFfiData,
                                             false,
                                             None,
                                             config)),
+        platforms: ins.platforms.clone(),
       });
     }
   }
@@ -507,15 +814,33 @@ struct ProcessFunctionsResult {
 
 /// Generates `CompleteType` from `CppFfiType`, adding
 /// Rust API type, Rust FFI type and conversion between them.
+/// `pointer_policy` determines how pointers to class types are
+/// represented in Rust API.
 fn complete_type(processed_types: &[RustProcessedTypeInfo],
                  dependency_types: &[RustProcessedTypeInfo],
                  cpp_ffi_type: &CppFfiType,
                  argument_meaning: &CppFfiArgumentMeaning,
-                 allocation_place: &ReturnValueAllocationPlace)
+                 allocation_place: &ReturnValueAllocationPlace,
+                 pointer_policy: PointerPolicy)
                  -> Result<CompleteType> {
   let rust_ffi_type = try!(ffi_type(processed_types, dependency_types, &cpp_ffi_type.ffi_type));
   let mut rust_api_type = rust_ffi_type.clone();
   let mut rust_api_to_c_conversion = RustToCTypeConversion::None;
+  if let CppTypeBase::Enum { ref name } = cpp_ffi_type.ffi_type.base {
+    if cpp_ffi_type.ffi_type.indirection == CppTypeIndirection::None {
+      let info = try!(find_type_info(processed_types, dependency_types, |x| &x.cpp_name == name)
+        .chain_err(|| format!("type has no Rust equivalent: {}", name)));
+      rust_api_type = RustType::Common {
+        base: info.rust_name.clone(),
+        generic_arguments: None,
+        is_const: false,
+        is_const2: false,
+        indirection: RustTypeIndirection::None,
+      };
+      rust_api_to_c_conversion = RustToCTypeConversion::EnumToInt;
+    }
+  }
+  let mut wrap_in_option = false;
   if let RustType::Common { ref mut indirection,
                             ref mut base,
                             ref mut generic_arguments,
@@ -527,6 +852,29 @@ fn complete_type(processed_types: &[RustProcessedTypeInfo],
           assert!(indirection == &RustTypeIndirection::Ptr);
           *indirection = RustTypeIndirection::Ref { lifetime: None };
           rust_api_to_c_conversion = RustToCTypeConversion::RefToPtr;
+        } else if indirection == &RustTypeIndirection::Ptr &&
+                  pointer_policy != PointerPolicy::RawPointer {
+          let is_class = match find_type_info(processed_types,
+                                              dependency_types,
+                                              |x| &x.rust_name == base) {
+            Some(info) => {
+              if let RustProcessedTypeKind::Class { .. } = info.kind {
+                true
+              } else {
+                false
+              }
+            }
+            None => false,
+          };
+          if is_class {
+            *indirection = RustTypeIndirection::Ref { lifetime: None };
+            if pointer_policy == PointerPolicy::OptionalReference {
+              wrap_in_option = true;
+              rust_api_to_c_conversion = RustToCTypeConversion::OptionRefToPtr;
+            } else {
+              rust_api_to_c_conversion = RustToCTypeConversion::NonNullRefToPtr;
+            }
+          }
         }
       }
       IndirectionChange::ValueToPointer => {
@@ -595,14 +943,23 @@ fn complete_type(processed_types: &[RustProcessedTypeInfo],
       IndirectionChange::QFlagsToUInt => {}
     }
   }
+  if wrap_in_option {
+    rust_api_type = RustType::Common {
+      base: try!(RustName::new(vec!["Option".to_string()])),
+      generic_arguments: Some(vec![rust_api_type]),
+      indirection: RustTypeIndirection::None,
+      is_const: false,
+      is_const2: false,
+    };
+  }
   if cpp_ffi_type.conversion == IndirectionChange::QFlagsToUInt {
     rust_api_to_c_conversion = RustToCTypeConversion::QFlagsToUInt;
     let enum_type = if let CppTypeBase::Class(CppTypeClassBase { ref template_arguments, .. }) =
                            cpp_ffi_type.original_type.base {
       let args = try!(template_arguments.as_ref()
-        .chain_err(|| "QFlags type must have template arguments"));
+        .chain_err(|| "flags type must have template arguments"));
       if args.len() != 1 {
-        return Err("QFlags type must have exactly 1 template argument".into());
+        return Err("flags type must have exactly 1 template argument".into());
       }
       if let CppTypeBase::Enum { ref name } = args[0].base {
         match find_type_info(processed_types, dependency_types, |x| &x.cpp_name == name) {
@@ -610,15 +967,16 @@ fn complete_type(processed_types: &[RustProcessedTypeInfo],
           Some(info) => info.rust_name.clone(),
         }
       } else {
-        return Err(unexpected("invalid original type for QFlags").into());
+        return Err(unexpected("invalid original type for flags type").into());
       }
     } else {
-      return Err(unexpected("invalid original type for QFlags").into());
+      return Err(unexpected("invalid original type for flags type").into());
     };
+    // flags type is provided by the crate containing the enum
+    let enum_crate_name = try!(enum_type.parts.first().chain_err(|| "name.parts can't be empty"))
+      .clone();
     rust_api_type = RustType::Common {
-      base: try!(RustName::new(vec!["qt_core".to_string(),
-                                    "flags".to_string(),
-                                    "Flags".to_string()])),
+      base: try!(RustName::new(vec![enum_crate_name, "flags".to_string(), "Flags".to_string()])),
       generic_arguments: Some(vec![RustType::Common {
                                      base: enum_type,
                                      generic_arguments: None,
@@ -654,6 +1012,24 @@ fn find_type_info<'a, F>(processed_types: &'a [RustProcessedTypeInfo],
   }
 }
 
+/// Returns Rust integer type corresponding to
+/// the underlying type of a C++ enum. The type is used
+/// for `repr` of the Rust enum, so it must be a primitive type.
+fn enum_integer_type(underlying_type: &CppType) -> Result<String> {
+  let rust_type = try!(try!(ffi_type(&[], &[], underlying_type)).dealias_libc());
+  if let RustType::Common { ref base, ref indirection, .. } = rust_type {
+    if base.parts.len() == 1 && indirection == &RustTypeIndirection::None {
+      match base.parts[0].as_ref() {
+        "i8" | "u8" | "i16" | "u16" | "i32" | "u32" | "i64" | "u64" | "isize" | "usize" => {
+          return Ok(base.parts[0].clone());
+        }
+        _ => {}
+      }
+    }
+  }
+  Err(format!("unsupported enum underlying type: {:?}", underlying_type).into())
+}
+
 /// Converts `CppType` to its exact Rust equivalent (FFI-compatible)
 fn ffi_type(processed_types: &[RustProcessedTypeInfo],
             dependency_types: &[RustProcessedTypeInfo],
@@ -704,7 +1080,19 @@ fn ffi_type(processed_types: &[RustProcessedTypeInfo],
     CppTypeBase::Enum { ref name } => {
       match find_type_info(processed_types, dependency_types, |x| &x.cpp_name == name) {
         None => return Err(format!("type has no Rust equivalent: {}", name).into()),
-        Some(info) => info.rust_name.clone(),
+        Some(info) => {
+          // C++ can pass values not declared in the Rust enum,
+          // so enum values are passed as integers
+          if cpp_ffi_type.indirection == CppTypeIndirection::None {
+            if let RustProcessedTypeKind::Enum { ref integer_type, .. } = info.kind {
+              try!(RustName::new(vec![integer_type.clone()]))
+            } else {
+              return Err(unexpected(format!("not an enum: {}", name)).into());
+            }
+          } else {
+            info.rust_name.clone()
+          }
+        }
       }
     }
     CppTypeBase::Class(ref name_and_args) => {
@@ -752,6 +1140,25 @@ fn ffi_type(processed_types: &[RustProcessedTypeInfo],
   })
 }
 
+/// Gives explicit lifetimes to all reference arguments: `'l0` to the arguments
+/// at `source_indexes` and a separate lifetime to each other argument.
+/// Elided lifetimes are not allowed in some places where the arguments
+/// are used, e.g. in trait impls of overloaded methods.
+fn set_argument_lifetimes(arguments: &mut [RustMethodArgument], source_indexes: &[usize]) {
+  let mut next_lifetime_num = if source_indexes.is_empty() { 0 } else { 1 };
+  for (index, arg) in arguments.iter_mut().enumerate() {
+    if arg.argument_type.rust_api_type.is_ref() {
+      let lifetime = if source_indexes.contains(&index) {
+        "l0".to_string()
+      } else {
+        next_lifetime_num += 1;
+        format!("l{}", next_lifetime_num - 1)
+      };
+      arg.argument_type.rust_api_type = arg.argument_type.rust_api_type.with_lifetime(lifetime);
+    }
+  }
+}
+
 
 impl RustGenerator {
   /// Generates exact Rust equivalent of CppAndFfiMethod object
@@ -773,6 +1180,7 @@ impl RustGenerator {
                                  &data.c_signature.return_type.ffi_type)),
       name: data.c_name.clone(),
       arguments: args,
+      platforms: data.cpp_method.platforms.clone(),
     })
   }
 
@@ -788,41 +1196,57 @@ impl RustGenerator {
                       mut cpp_methods: Vec<&'a CppAndFfiMethod>)
                       -> Result<(ProcessTypeResult, Vec<&'a CppAndFfiMethod>)> {
     Ok(match info.kind {
-      RustProcessedTypeKind::Enum { ref values } => {
-        let mut is_flaggable = false;
+      RustProcessedTypeKind::Enum { ref values, ref integer_type, ref is_scoped } => {
         let template_arg_sample = CppType {
           is_const: false,
           is_const2: false,
           indirection: CppTypeIndirection::None,
           base: CppTypeBase::Enum { name: info.cpp_name.clone() },
         };
-
-        for flag_owner_name in &["QFlags", "QUrlTwoFlags"] {
-          if let Some(instantiations) = self.input_data
-            .cpp_data
-            .template_instantiations
-            .iter()
-            .find(|x| &x.class_name == &flag_owner_name.to_string()) {
-            if instantiations.instantiations
+        let is_in_flags_container = self.input_data
+          .cpp_data
+          .template_instantiations
+          .iter()
+          .filter(|x| self.config.flags_containers.iter().any(|name| name == &x.class_name))
+          .any(|x| {
+            x.instantiations
               .iter()
-              .any(|ins| ins.template_arguments.iter().any(|arg| arg == &template_arg_sample)) {
-              is_flaggable = true;
-              break;
-            }
+              .any(|ins| ins.template_arguments.iter().any(|arg| arg == &template_arg_sample))
+          });
+        let is_flaggable = is_in_flags_container ||
+                           self.config.flag_enums.iter().any(|x| x == &info.cpp_name);
+
+        let representation = match self.config
+          .enum_representations
+          .iter()
+          .find(|&&(ref name, _)| name == &info.cpp_name) {
+          Some(&(_, representation)) => representation,
+          None => self.config.default_enum_representation,
+        };
+        let values = match representation {
+          EnumRepresentation::RustEnum => {
+            prepare_enum_values(values, &info.cpp_name, &self.config.rename_rules)
           }
-
-        }
-
+          EnumRepresentation::Newtype => {
+            prepare_enum_constants(values, &info.cpp_name, &self.config.rename_rules)
+          }
+        };
         // TODO: export Qt doc for enum and its variants (#35)
         let doc = format!("C++ type: {}",
-                          doc_formatter::wrap_inline_cpp_code(&info.cpp_name));
+                          doc_formatter::wrap_inline_cpp_code(&if *is_scoped {
+                            format!("enum class {}", info.cpp_name)
+                          } else {
+                            info.cpp_name.clone()
+                          }));
         (ProcessTypeResult {
           main_type: RustTypeDeclaration {
             name: try!(info.rust_name.last_name()).clone(),
             kind: RustTypeDeclarationKind::CppTypeWrapper {
               kind: RustTypeWrapperKind::Enum {
-                values: prepare_enum_values(values, &info.cpp_name),
+                values: values,
                 is_flaggable: is_flaggable,
+                representation: representation,
+                integer_type: integer_type.clone(),
               },
               cpp_type_name: info.cpp_name.clone(),
               cpp_template_arguments: None,
@@ -830,12 +1254,13 @@ impl RustGenerator {
               traits: Vec::new(),
             },
             doc: doc,
+            platforms: info.platforms.clone(),
           },
           overloading_types: Vec::new(),
         },
          cpp_methods)
       }
-      RustProcessedTypeKind::Class { ref size, .. } => {
+      RustProcessedTypeKind::Class { ref size, ref alignment, .. } => {
         let methods_scope = RustMethodScope::Impl { type_name: info.rust_name.clone() };
         let class_type = CppTypeClassBase {
           name: info.cpp_name.clone(),
@@ -869,13 +1294,17 @@ impl RustGenerator {
           main_type: RustTypeDeclaration {
             name: try!(info.rust_name.last_name()).clone(),
             kind: RustTypeDeclarationKind::CppTypeWrapper {
-              kind: RustTypeWrapperKind::Struct { size: *size },
+              kind: RustTypeWrapperKind::Struct {
+                size: *size,
+                alignment: *alignment,
+              },
               cpp_type_name: info.cpp_name.clone(),
               cpp_template_arguments: info.cpp_template_arguments.clone(),
               methods: functions_result.methods,
               traits: functions_result.trait_impls,
             },
             doc: doc,
+            platforms: info.platforms.clone(),
           },
           overloading_types: functions_result.overloading_types,
         },
@@ -987,6 +1416,7 @@ impl RustGenerator {
                        scope: &RustMethodScope,
                        generate_doc: bool)
                        -> Result<RustMethod> {
+    let pointer_policy = self.pointer_policy(&method.cpp_method);
     let mut arguments = Vec::new();
     for (arg_index, arg) in method.c_signature.arguments.iter().enumerate() {
       if arg.meaning != CppFfiArgumentMeaning::ReturnValue {
@@ -994,7 +1424,8 @@ impl RustGenerator {
                                           &self.dependency_types,
                                           &arg.argument_type,
                                           &arg.meaning,
-                                          &method.allocation_place));
+                                          &method.allocation_place,
+                                          pointer_policy));
         arguments.push(RustMethodArgument {
           ffi_index: Some(arg_index as i32),
           argument_type: arg_type,
@@ -1018,7 +1449,8 @@ impl RustGenerator {
                           &self.dependency_types,
                           &arg.argument_type,
                           &arg.meaning,
-                          &method.allocation_place)),
+                          &method.allocation_place,
+                          pointer_policy)),
        Some(arg_index as i32))
     } else {
       // none of the arguments has return value meaning,
@@ -1027,28 +1459,14 @@ impl RustGenerator {
                                            &self.dependency_types,
                                            &method.c_signature.return_type,
                                            &CppFfiArgumentMeaning::ReturnValue,
-                                           &method.allocation_place));
+                                           &method.allocation_place,
+                                           pointer_policy));
       (return_type, None)
     };
     if return_type.rust_api_type.is_ref() {
-      let mut next_lifetime_num = 0;
-      for arg in &mut arguments {
-        if arg.argument_type.rust_api_type.is_ref() {
-          arg.argument_type.rust_api_type =
-            arg.argument_type.rust_api_type.with_lifetime(format!("l{}", next_lifetime_num));
-          next_lifetime_num += 1;
-        }
-      }
-      let return_lifetime = if next_lifetime_num == 0 {
-        log::warning(format!("Method returns a reference but doesn't receive a reference: {}",
-                             method.short_text()));
-        log::warning("Assuming static lifetime of return value.");
-        "static".to_string()
-      } else {
-        "l0".to_string()
-      };
-      return_type.rust_api_type = return_type.rust_api_type.with_lifetime(return_lifetime);
+      try!(self.set_return_lifetime(method, &mut arguments, &mut return_type));
     }
+    let unsafe_reasons = self.unsafe_reasons(&method.cpp_method, &arguments, &return_type);
 
     let doc = if generate_doc {
       let doc_item = doc_formatter::DocItem {
@@ -1069,14 +1487,156 @@ impl RustGenerator {
         cpp_method: method.clone(),
         return_type: return_type,
         return_type_ffi_index: return_arg_index,
+        unsafe_reasons: unsafe_reasons,
       }),
       doc: doc,
     })
   }
 
+  /// Returns pointer policy for the method. Policy set for the method
+  /// takes priority over policy set for its class.
+  fn pointer_policy(&self, method: &CppMethod) -> PointerPolicy {
+    let method_name = method.doc_id();
+    if let Some(&(_, policy)) = self.config
+      .pointer_policies
+      .iter()
+      .find(|&&(ref name, _)| name == &method_name) {
+      return policy;
+    }
+    if let Some(ref info) = method.class_membership {
+      if let Some(&(_, policy)) = self.config
+        .pointer_policies
+        .iter()
+        .find(|&&(ref name, _)| name == &info.class_type.name) {
+        return policy;
+      }
+    }
+    self.config.default_pointer_policy
+  }
+
+  /// Ties lifetime of the returned reference to the arguments
+  /// that own the referenced data. The lifetime set in the config
+  /// for this method is used if present. Otherwise the return value
+  /// is tied to `self` or, if there is no `self` reference, to all
+  /// reference arguments. If there are no suitable arguments,
+  /// the return type is replaced with a raw pointer.
+  /// Other reference arguments receive separate explicit lifetimes.
+  fn set_return_lifetime(&self,
+                         method: &CppAndFfiMethod,
+                         arguments: &mut [RustMethodArgument],
+                         return_type: &mut CompleteType)
+                         -> Result<()> {
+    let method_name = method.cpp_method.doc_id();
+    let annotation = self.config
+      .return_lifetimes
+      .iter()
+      .find(|&&(ref name, _)| name == &method_name)
+      .map(|&(_, ref lifetime)| lifetime.clone());
+    let is_ref_arg = |arg: &RustMethodArgument| arg.argument_type.rust_api_type.is_ref();
+    let source_indexes: Vec<usize> = match annotation {
+      Some(ReturnLifetime::Static) => {
+        return_type.rust_api_type = return_type.rust_api_type.with_lifetime("static".to_string());
+        set_argument_lifetimes(arguments, &[]);
+        return Ok(());
+      }
+      Some(ReturnLifetime::SelfArgument) => {
+        match arguments.iter().position(|arg| arg.name == "self" && is_ref_arg(arg)) {
+          Some(index) => vec![index],
+          None => {
+            return Err(format!("return lifetime is tied to self, but method doesn't \
+                                take self by reference: {}",
+                               method.short_text())
+              .into())
+          }
+        }
+      }
+      Some(ReturnLifetime::Argument(cpp_index)) => {
+        let ffi_index = method.c_signature
+          .arguments
+          .iter()
+          .position(|arg| arg.meaning == CppFfiArgumentMeaning::Argument(cpp_index as i8));
+        match arguments.iter().position(|arg| {
+          ffi_index.is_some() && arg.ffi_index == ffi_index.map(|x| x as i32) && is_ref_arg(arg)
+        }) {
+          Some(index) => vec![index],
+          None => {
+            return Err(format!("return lifetime is tied to argument {}, but it's not \
+                                a reference: {}",
+                               cpp_index,
+                               method.short_text())
+              .into())
+          }
+        }
+      }
+      None => {
+        if let Some(index) = arguments.iter()
+          .position(|arg| arg.name == "self" && is_ref_arg(arg)) {
+          vec![index]
+        } else {
+          arguments.iter()
+            .enumerate()
+            .filter(|&(_, arg)| is_ref_arg(arg))
+            .map(|(index, _)| index)
+            .collect()
+        }
+      }
+    };
+    if source_indexes.is_empty() {
+      log::warning(format!("Method returns a reference but doesn't receive a reference: {}",
+                           method.short_text()));
+      log::warning("Returning raw pointer instead.");
+      return_type.rust_api_type = return_type.rust_ffi_type.clone();
+      return_type.rust_api_to_c_conversion = RustToCTypeConversion::None;
+      return Ok(());
+    }
+    set_argument_lifetimes(arguments, &source_indexes);
+    return_type.rust_api_type = return_type.rust_api_type.with_lifetime("l0".to_string());
+    Ok(())
+  }
+
+  /// Returns the list of reasons why the Rust wrapper of the method
+  /// must be `unsafe`. The function is safe if the list is empty.
+  fn unsafe_reasons(&self,
+                    method: &CppMethod,
+                    arguments: &[RustMethodArgument],
+                    return_type: &CompleteType)
+                    -> Vec<String> {
+    fn is_raw_pointer(t: &RustType) -> bool {
+      if let RustType::Common { ref indirection, .. } = *t {
+        match *indirection {
+          RustTypeIndirection::Ptr |
+          RustTypeIndirection::PtrPtr |
+          RustTypeIndirection::PtrRef { .. } => true,
+          _ => false,
+        }
+      } else {
+        false
+      }
+    }
+    let mut reasons = Vec::new();
+    let method_name = method.doc_id();
+    let is_listed = self.config.unsafe_methods.iter().any(|name| {
+      name == &method_name ||
+      method.class_membership.as_ref().map_or(false, |info| name == &info.class_type.name)
+    });
+    if is_listed {
+      reasons.push("is explicitly marked as unsafe in the generator config".to_string());
+    }
+    for arg in arguments {
+      if is_raw_pointer(&arg.argument_type.rust_api_type) {
+        reasons.push(format!("takes raw pointer argument `{}` that must be valid or null",
+                             arg.name));
+      }
+    }
+    if is_raw_pointer(&return_type.rust_api_type) {
+      reasons.push("returns a raw pointer that is not owned by the caller".to_string());
+    }
+    reasons
+  }
+
   /// Returns method name. For class member functions, the name doesn't
   /// include class name and scope. For free functions, the name includes
-  /// modules.
+  /// modules. Rename rules are applied in both cases.
   fn method_rust_name(&self, method: &CppAndFfiMethod) -> Result<RustName> {
     let mut name = if method.cpp_method.class_membership.is_none() {
       try!(calculate_rust_name(&method.cpp_method.name,
@@ -1089,8 +1649,15 @@ impl RustGenerator {
         "new".to_string()
       } else if let Some(ref operator) = method.cpp_method.operator {
         try!(operator_rust_name(operator))
+      } else if let Some(rust_name) = self.config
+        .rename_rules
+        .explicit_name(RenameTarget::Method, &method.cpp_method.full_name()) {
+        rust_name.clone()
       } else {
-        method.cpp_method.name.to_snake_case()
+        self.config
+          .rename_rules
+          .apply(RenameTarget::Method, &method.cpp_method.name)
+          .to_snake_case()
       };
       try!(RustName::new(vec![x]))
     };
@@ -1266,7 +1833,18 @@ impl RustGenerator {
           inherited_from: cpp_method.inherited_from.clone(),
         });
       }
-      let doc = doc_formatter::method_doc(doc_items, &cpp_method_name);
+      let mut unsafe_reasons = Vec::new();
+      for variant in &args_variants {
+        for reason in &variant.unsafe_reasons {
+          let text = format!("{} (variant `{}`)",
+                             reason,
+                             variant.cpp_method.cpp_method.short_text());
+          unsafe_reasons.push(text);
+        }
+      }
+      let is_unsafe = !unsafe_reasons.is_empty();
+      let doc = doc_formatter::method_doc(doc_items, &cpp_method_name) +
+                &doc_formatter::safety_doc(&unsafe_reasons);
 
       // overloaded methods
       let shared_arguments_for_trait = match self_argument {
@@ -1305,6 +1883,7 @@ impl RustGenerator {
       } else {
         None
       };
+      let platforms = variants_platforms(&args_variants);
       type_declaration = Some(RustTypeDeclaration {
         name: trait_name.clone(),
         kind: RustTypeDeclarationKind::MethodParametersTrait {
@@ -1312,11 +1891,13 @@ impl RustGenerator {
           impls: args_variants,
           lifetime: trait_lifetime.clone(),
           return_type: trait_return_type.clone(),
+          is_unsafe: is_unsafe,
         },
         doc: format!("This trait represents a set of arguments accepted by [{name}]({link}) \
                       method.",
                      name = method_name_with_scope,
                      link = method_link),
+        platforms: platforms.clone(),
       });
 
       RustMethod {
@@ -1328,6 +1909,8 @@ impl RustGenerator {
           params_trait_return_type: trait_return_type,
           shared_arguments: shared_arguments,
           variant_argument_name: "args".to_string(),
+          is_unsafe: is_unsafe,
+          platforms: platforms,
         },
         doc: doc,
       }
@@ -1346,7 +1929,8 @@ impl RustGenerator {
           inherited_from: args.cpp_method.cpp_method.inherited_from.clone(),
         };
         method.doc = doc_formatter::method_doc(vec![doc_item],
-                                               &args.cpp_method.cpp_method.full_name());
+                                               &args.cpp_method.cpp_method.full_name()) +
+                     &doc_formatter::safety_doc(&args.unsafe_reasons);
       } else {
         unreachable!();
       }
@@ -1356,6 +1940,133 @@ impl RustGenerator {
     Ok((method, type_declaration))
   }
 
+  /// Converts overloaded methods to separate methods with distinct names
+  /// (see `OverloadStrategy::SuffixedNames`). Variants of the same C++ method
+  /// that differ by omitted default arguments are merged into one method
+  /// with `Option` arguments if their return types are the same.
+  /// Generated names are added to `used_names` that contains
+  /// names of all methods of the scope.
+  fn process_method_with_suffixes(&self,
+                                  filtered_methods: Vec<RustMethod>,
+                                  self_arg_kind_caption: Option<&'static str>,
+                                  used_names: &mut HashSet<String>)
+                                  -> Result<Vec<RustMethod>> {
+    let first_method = try!(filtered_methods.first()
+        .chain_err(|| "filtered_methods can't be empty"))
+      .clone();
+    let original_name = try!(first_method.name.last_name()).clone();
+    let mut base_name = original_name.clone();
+    if let Some(self_arg_kind_caption) = self_arg_kind_caption {
+      base_name = format!("{}_{}", base_name, self_arg_kind_caption);
+    }
+
+    // group variants by original C++ method and allocation place
+    let mut groups: Vec<(CppMethod, ReturnValueAllocationPlace, Vec<_>)> = Vec::new();
+    for method in filtered_methods {
+      if let RustMethodArguments::SingleVariant(args) = method.arguments {
+        let mut cpp_method_key = args.cpp_method.cpp_method.clone();
+        if let Some(v) = cpp_method_key.arguments_before_omitting {
+          cpp_method_key.arguments = v;
+          cpp_method_key.arguments_before_omitting = None;
+        }
+        let place = args.cpp_method.allocation_place.clone();
+        if let Some(index) = groups.iter().position(|&(ref key, ref group_place, _)| {
+          key == &cpp_method_key && group_place == &place
+        }) {
+          groups[index].2.push(args);
+        } else {
+          groups.push((cpp_method_key, place, vec![args]));
+        }
+      } else {
+        unreachable!()
+      }
+    }
+    let mut units = Vec::new();
+    for (_, place, mut variants) in groups {
+      variants.sort_by(|a, b| a.arguments.len().cmp(&b.arguments.len()));
+      let first_return_type = variants[0].return_type.rust_api_type.clone();
+      // variants can only be merged if any number of trailing arguments can be omitted
+      let required_count = variants[0].arguments.len();
+      if variants.iter().all(|x| x.return_type.rust_api_type == first_return_type) &&
+         variants.iter().enumerate().all(|(index, x)| x.arguments.len() == required_count + index) {
+        units.push((place, variants));
+      } else {
+        for variant in variants {
+          units.push((place.clone(), vec![variant]));
+        }
+      }
+    }
+    units.sort_by(|a, b| {
+      let a_text = a.1.last().map(|x| x.cpp_method.c_name.clone());
+      let b_text = b.1.last().map(|x| x.cpp_method.c_name.clone());
+      a_text.cmp(&b_text)
+    });
+
+    let mut results = Vec::new();
+    let mut group_names = HashSet::new();
+    for &(ref place, ref variants) in &units {
+      let longest = try!(variants.last().chain_err(|| "variants can't be empty"));
+      let mut name = base_name.clone();
+      if units.iter().filter(|x| &x.0 == place).count() > 1 {
+        let captions = try!(longest.arguments
+          .iter()
+          .filter(|x| x.name != "self")
+          .map_if_ok(|x| -> Result<_> {
+            try!(x.argument_type.rust_api_type.dealias_libc()).caption()
+          }));
+        name = if captions.is_empty() {
+          format!("{}_no_args", name)
+        } else {
+          format!("{}_{}", name, captions.join("_"))
+        };
+      }
+      if place == &ReturnValueAllocationPlace::Heap {
+        name = format!("{}_as_box", name);
+      }
+      let name = unique_method_name(name, &original_name, &group_names, used_names);
+      group_names.insert(name.clone());
+      used_names.insert(name.clone());
+
+      let doc_item = doc_formatter::DocItem {
+        cpp_fn: longest.cpp_method.cpp_method.short_text(),
+        rust_fns: Vec::new(),
+        doc: self.get_qt_doc_for_method(&longest.cpp_method.cpp_method),
+        inherited_from: longest.cpp_method.cpp_method.inherited_from.clone(),
+      };
+      let mut unsafe_reasons = Vec::new();
+      for variant in variants {
+        for reason in &variant.unsafe_reasons {
+          if !unsafe_reasons.contains(reason) {
+            unsafe_reasons.push(reason.clone());
+          }
+        }
+      }
+      let optional_arguments: Vec<_> = longest.arguments[variants[0].arguments.len()..]
+        .iter()
+        .map(|x| x.name.clone())
+        .collect();
+      let doc = doc_formatter::method_doc(vec![doc_item],
+                                          &longest.cpp_method.cpp_method.full_name()) +
+                &doc_formatter::default_arguments_doc(&optional_arguments) +
+                &doc_formatter::safety_doc(&unsafe_reasons);
+
+      let mut method_name = first_method.name.clone();
+      try!(method_name.parts.pop().chain_err(|| "name can't be empty"));
+      method_name.parts.push(name);
+      results.push(RustMethod {
+        name: method_name,
+        scope: first_method.scope.clone(),
+        arguments: if variants.len() == 1 {
+          RustMethodArguments::SingleVariant(longest.clone())
+        } else {
+          RustMethodArguments::DefaultArguments(variants.clone())
+        },
+        doc: doc,
+      });
+    }
+    Ok(results)
+  }
+
   fn get_qt_doc_for_method(&self, cpp_method: &CppMethod) -> Option<QtDocResultForMethod> {
     if let Some(ref qt_doc_data) = self.config.qt_doc_data {
       if let Some(ref inherited_from) = cpp_method.inherited_from {
@@ -1407,11 +2118,23 @@ impl RustGenerator {
   {
     // Step 1: convert all methods to SingleVariant Rust methods and
     // split them by last name.
-    let mut single_rust_methods: HashMap<String, Vec<RustMethod>> = HashMap::new();
+    let mut single_rust_methods: BTreeMap<String, Vec<RustMethod>> = BTreeMap::new();
     let mut result = ProcessFunctionsResult::default();
     for method in methods {
       if method.cpp_method.is_destructor() {
         match self.process_destructor(method, scope) {
+          // trait methods can't be unsafe, so the trait is not implemented
+          // if the destructor has reasons to be unsafe
+          Ok(ref r) if r.methods.iter().any(|x| x.is_unsafe()) => {
+            log::warning(format!("Destructor is unsafe, so Drop is not implemented: {}",
+                                 method.short_text()));
+            self.skipped_items
+              .lock()
+              .unwrap()
+              .push(SkippedItem::method(&method.cpp_method,
+                                        SkipStage::RustUnsafeTraitImpl,
+                                        "the destructor is unsafe"));
+          }
           Ok(r) => result.trait_impls.push(r),
           Err(msg) => {
             log::warning(format!("Failed to generate destructor: {}\n{:?}\n", msg, method))
@@ -1422,21 +2145,30 @@ impl RustGenerator {
       match self.generate_function(method, scope, false) {
         Ok(rust_method) => {
           let name = try!(rust_method.name.last_name()).clone();
-          add_to_multihash(&mut single_rust_methods, name, rust_method);
+          single_rust_methods.entry(name).or_insert_with(Vec::new).push(rust_method);
+        }
+        Err(err) => {
+          log::warning(err.to_string());
+          self.skipped_items
+            .lock()
+            .unwrap()
+            .push(SkippedItem::method(&method.cpp_method, SkipStage::RustTypes, err.to_string()));
         }
-        Err(err) => log::warning(err.to_string()),
       }
     }
+    // names generated with `OverloadStrategy::SuffixedNames` must not
+    // conflict with other methods
+    let mut used_names: HashSet<String> = single_rust_methods.keys().cloned().collect();
     for (_, current_methods) in single_rust_methods {
       assert!(!current_methods.is_empty());
       // Step 2: for each method name, split methods by type of
       // their self argument. Overloading can't be emulated if self types
       // differ.
-      let mut self_kind_to_methods: HashMap<_, Vec<_>> = HashMap::new();
+      let mut self_kind_to_methods: BTreeMap<_, Vec<_>> = BTreeMap::new();
       for method in current_methods {
-        add_to_multihash(&mut self_kind_to_methods,
-                         try!(method.self_arg_kind()),
-                         method);
+        self_kind_to_methods.entry(try!(method.self_arg_kind()))
+          .or_insert_with(Vec::new)
+          .push(method);
       }
       let all_self_args: Vec<_> = self_kind_to_methods.keys().cloned().collect();
       for (self_arg_kind, overloaded_methods) in self_kind_to_methods {
@@ -1475,6 +2207,13 @@ impl RustGenerator {
               log::warning(format!("Removing method because another method with the same \
                                     argument types exists:\n{:?}",
                                    args.cpp_method.short_text()));
+              self.skipped_items
+                .lock()
+                .unwrap()
+                .push(SkippedItem::method(&args.cpp_method.cpp_method,
+                                          SkipStage::RustDuplicate,
+                                          "another method with the same Rust argument types \
+                                           exists"));
               false
             } else {
               set.insert(real_args);
@@ -1489,14 +2228,24 @@ impl RustGenerator {
         }
         // Step 4: generate overloaded method if count of methods is still > 1,
         // or accept a single method without change.
-        let (method, type_declaration) =
-          try!(self.process_method(filtered_methods, scope, self_arg_kind_caption));
-        if method.doc.is_empty() {
-          return Err(unexpected(format!("doc is empty! {:?}", method)).into());
-        }
-        result.methods.push(method);
-        if let Some(r) = type_declaration {
-          result.overloading_types.push(r);
+        match self.config.overload_strategy {
+          OverloadStrategy::ParametersTrait => {
+            let (method, type_declaration) =
+              try!(self.process_method(filtered_methods, scope, self_arg_kind_caption));
+            if method.doc.is_empty() {
+              return Err(unexpected(format!("doc is empty! {:?}", method)).into());
+            }
+            result.methods.push(method);
+            if let Some(r) = type_declaration {
+              result.overloading_types.push(r);
+            }
+          }
+          OverloadStrategy::SuffixedNames => {
+            result.methods
+              .extend(try!(self.process_method_with_suffixes(filtered_methods,
+                                                             self_arg_kind_caption,
+                                                             &mut used_names)));
+          }
         }
       }
     }
@@ -1558,19 +2307,71 @@ fn calculate_rust_name_test_part(name: &'static str,
                                  include_file: &'static str,
                                  is_function: bool,
                                  expected: &[&'static str]) {
+  calculate_rust_name_test_part_with_layout(name,
+                                            include_file,
+                                            is_function,
+                                            ModuleLayout::IncludeFile,
+                                            &[],
+                                            expected);
+}
+
+#[cfg(test)]
+fn calculate_rust_name_test_part_with_layout(name: &'static str,
+                                             include_file: &'static str,
+                                             is_function: bool,
+                                             layout: ModuleLayout,
+                                             module_mapping: &[(&'static str, &'static str)],
+                                             expected: &[&'static str]) {
+  let mut config = test_config();
+  config.module_layout = layout;
+  config.module_mapping = module_mapping.iter()
+    .map(|&(file, module)| (file.to_string(), module.to_string()))
+    .collect();
   assert_eq!(calculate_rust_name(&name.to_string(),
                                  &include_file.to_string(),
                                  is_function,
                                  None,
-                                 &RustGeneratorConfig {
-                                   crate_name: "qt_core".to_string(),
-                                   remove_qt_prefix: true,
-                                   qt_doc_data: None,
-                                 })
+                                 &config)
                .unwrap(),
              RustName::new(expected.into_iter().map(|x| x.to_string()).collect()).unwrap());
 }
 
+#[cfg(test)]
+fn test_config() -> RustGeneratorConfig {
+  RustGeneratorConfig {
+    crate_name: "qt_core".to_string(),
+    remove_qt_prefix: true,
+    rename_rules: RenameRules::default(),
+    module_layout: ModuleLayout::IncludeFile,
+    overload_strategy: OverloadStrategy::ParametersTrait,
+    module_mapping: Vec::new(),
+    qt_doc_data: None,
+    default_pointer_policy: PointerPolicy::RawPointer,
+    pointer_policies: Vec::new(),
+    unsafe_methods: Vec::new(),
+    return_lifetimes: Vec::new(),
+    default_enum_representation: EnumRepresentation::RustEnum,
+    enum_representations: Vec::new(),
+    flag_enums: Vec::new(),
+    flags_containers: Vec::new(),
+    num_jobs: 1,
+  }
+}
+
+#[cfg(test)]
+fn test_generator(config: RustGeneratorConfig) -> RustGenerator {
+  RustGenerator {
+    input_data: CppAndFfiData {
+      cpp_data: ::cpp_data::CppData::default(),
+      cpp_ffi_headers: Vec::new(),
+    },
+    config: config,
+    processed_types: Vec::new(),
+    dependency_types: Vec::new(),
+    skipped_items: Mutex::new(Vec::new()),
+  }
+}
+
 #[test]
 fn calculate_rust_name_test() {
   calculate_rust_name_test_part("myFunc1",
@@ -1595,17 +2396,78 @@ fn calculate_rust_name_test() {
                                 &["qt_core", "rect", "ns", "func1"]);
 }
 
+#[test]
+fn calculate_rust_name_test_layout() {
+  let mapping = [("qregexp.h", "text")];
+  calculate_rust_name_test_part("QRegExp",
+                                "qregexp.h",
+                                false,
+                                &["qt_core", "qregexp", "RegExp"]);
+  calculate_rust_name_test_part_with_layout("QRegExp",
+                                            "qregexp.h",
+                                            false,
+                                            ModuleLayout::IncludeFile,
+                                            &mapping,
+                                            &["qt_core", "text", "RegExp"]);
+  calculate_rust_name_test_part_with_layout("ns::func1",
+                                            "QRect",
+                                            true,
+                                            ModuleLayout::Namespace,
+                                            &[],
+                                            &["qt_core", "ns", "func1"]);
+  calculate_rust_name_test_part_with_layout("Qt::AlignmentFlag",
+                                            "qnamespace.h",
+                                            false,
+                                            ModuleLayout::Namespace,
+                                            &[],
+                                            &["qt_core", "qt", "AlignmentFlag"]);
+  calculate_rust_name_test_part_with_layout("QPointF",
+                                            "QPointF",
+                                            false,
+                                            ModuleLayout::Namespace,
+                                            &[],
+                                            &["qt_core", "point_f", "PointF"]);
+  calculate_rust_name_test_part_with_layout("QRegExp",
+                                            "qregexp.h",
+                                            false,
+                                            ModuleLayout::Namespace,
+                                            &mapping,
+                                            &["qt_core", "text", "RegExp"]);
+}
+
+#[test]
+fn unique_method_name_test() {
+  let mut group_names = HashSet::new();
+  let mut used_names = HashSet::new();
+  used_names.insert("set".to_string());
+  used_names.insert("set_int".to_string());
+  assert_eq!(unique_method_name("set".to_string(), "set", &group_names, &used_names),
+             "set");
+  // suffixed name conflicts with another method
+  assert_eq!(unique_method_name("set_int".to_string(), "set", &group_names, &used_names),
+             "set_int2");
+  used_names.insert("set_int2".to_string());
+  assert_eq!(unique_method_name("set_int".to_string(), "set", &group_names, &used_names),
+             "set_int3");
+  group_names.insert("set".to_string());
+  assert_eq!(unique_method_name("set".to_string(), "set", &group_names, &used_names),
+             "set2");
+}
+
 #[test]
 fn prepare_enum_values_test_simple() {
   let r = prepare_enum_values(&[EnumValue {
                                   name: "var1".to_string(),
                                   value: 1,
+                                  platforms: Vec::new(),
                                 },
                                 EnumValue {
                                   name: "other_var2".to_string(),
                                   value: 2,
+                                  platforms: Vec::new(),
                                 }],
-                              "");
+                              "",
+                              &RenameRules::default());
   assert_eq!(r.len(), 2);
   assert_eq!(r[0].name, "Var1");
   assert_eq!(r[0].value, 1);
@@ -1618,16 +2480,20 @@ fn prepare_enum_values_test_duplicates() {
   let r = prepare_enum_values(&[EnumValue {
                                   name: "var1".to_string(),
                                   value: 1,
+                                  platforms: Vec::new(),
                                 },
                                 EnumValue {
                                   name: "other_var2".to_string(),
                                   value: 2,
+                                  platforms: Vec::new(),
                                 },
                                 EnumValue {
                                   name: "other_var_dup".to_string(),
                                   value: 2,
+                                  platforms: Vec::new(),
                                 }],
-                              "");
+                              "",
+                              &RenameRules::default());
   assert_eq!(r.len(), 2);
   assert_eq!(r[0].name, "Var1");
   assert_eq!(r[0].value, 1);
@@ -1640,16 +2506,20 @@ fn prepare_enum_values_test_prefix() {
   let r = prepare_enum_values(&[EnumValue {
                                   name: "OptionGood".to_string(),
                                   value: 1,
+                                  platforms: Vec::new(),
                                 },
                                 EnumValue {
                                   name: "OptionBad".to_string(),
                                   value: 2,
+                                  platforms: Vec::new(),
                                 },
                                 EnumValue {
                                   name: "OptionNecessaryEvil".to_string(),
                                   value: 3,
+                                  platforms: Vec::new(),
                                 }],
-                              "");
+                              "",
+                              &RenameRules::default());
   assert_eq!(r.len(), 3);
   assert_eq!(r[0].name, "Good");
   assert_eq!(r[1].name, "Bad");
@@ -1661,16 +2531,20 @@ fn prepare_enum_values_test_suffix() {
   let r = prepare_enum_values(&[EnumValue {
                                   name: "BestFriend".to_string(),
                                   value: 1,
+                                  platforms: Vec::new(),
                                 },
                                 EnumValue {
                                   name: "GoodFriend".to_string(),
                                   value: 2,
+                                  platforms: Vec::new(),
                                 },
                                 EnumValue {
                                   name: "NoFriend".to_string(),
                                   value: 3,
+                                  platforms: Vec::new(),
                                 }],
-                              "");
+                              "",
+                              &RenameRules::default());
   assert_eq!(r.len(), 3);
   assert_eq!(r[0].name, "Best");
   assert_eq!(r[1].name, "Good");
@@ -1682,12 +2556,15 @@ fn prepare_enum_values_test_prefix_digits() {
   let r = prepare_enum_values(&[EnumValue {
                                   name: "Base32".to_string(),
                                   value: 1,
+                                  platforms: Vec::new(),
                                 },
                                 EnumValue {
                                   name: "Base64".to_string(),
                                   value: 2,
+                                  platforms: Vec::new(),
                                 }],
-                              "");
+                              "",
+                              &RenameRules::default());
   assert_eq!(r.len(), 2);
   assert_eq!(r[0].name, "Base32");
   assert_eq!(r[1].name, "Base64");
@@ -1698,12 +2575,15 @@ fn prepare_enum_values_test_suffix_empty() {
   let r = prepare_enum_values(&[EnumValue {
                                   name: "NonRecursive".to_string(),
                                   value: 1,
+                                  platforms: Vec::new(),
                                 },
                                 EnumValue {
                                   name: "Recursive".to_string(),
                                   value: 2,
+                                  platforms: Vec::new(),
                                 }],
-                              "");
+                              "",
+                              &RenameRules::default());
   assert_eq!(r.len(), 2);
   assert_eq!(r[0].name, "NonRecursive");
   assert_eq!(r[1].name, "Recursive");
@@ -1714,13 +2594,483 @@ fn prepare_enum_values_test_suffix_partial() {
   let r = prepare_enum_values(&[EnumValue {
                                   name: "PreciseTimer".to_string(),
                                   value: 1,
+                                  platforms: Vec::new(),
                                 },
                                 EnumValue {
                                   name: "CoarseTimer".to_string(),
                                   value: 2,
+                                  platforms: Vec::new(),
                                 }],
-                              "");
+                              "",
+                              &RenameRules::default());
   assert_eq!(r.len(), 2);
   assert_eq!(r[0].name, "Precise");
   assert_eq!(r[1].name, "Coarse");
 }
+
+#[test]
+fn prepare_enum_constants_test() {
+  let r = prepare_enum_constants(&[EnumValue {
+                                     name: "Key_Escape".to_string(),
+                                     value: 2,
+                                     platforms: Vec::new(),
+                                   },
+                                   EnumValue {
+                                     name: "Key_Tab".to_string(),
+                                     value: 1,
+                                     platforms: Vec::new(),
+                                   },
+                                   EnumValue {
+                                     name: "Key_Backtab".to_string(),
+                                     value: 1,
+                                     platforms: Vec::new(),
+                                   }],
+                                 "Qt::Key",
+                                 &RenameRules::default());
+  assert_eq!(r.len(), 3);
+  assert_eq!(r[0].name, "TAB");
+  assert_eq!(r[0].value, 1);
+  assert_eq!(r[1].name, "BACKTAB");
+  assert_eq!(r[1].value, 1);
+  assert_eq!(r[2].name, "ESCAPE");
+  assert_eq!(r[2].value, 2);
+}
+
+#[test]
+fn prepare_enum_values_test_rename_rules() {
+  use rename_rules::RenameRule;
+  let rules = RenameRules::new(vec![RenameRule::RemovePrefix {
+                                      target: Some(RenameTarget::EnumVariant),
+                                      prefix: "GTK_".to_string(),
+                                    },
+                                    RenameRule::Explicit {
+                                      target: RenameTarget::EnumVariant,
+                                      cpp_name: "GtkAlign::GTK_ALIGN_START".to_string(),
+                                      rust_name: "Beginning".to_string(),
+                                    }])
+    .unwrap();
+  let r = prepare_enum_values(&[EnumValue {
+                                  name: "GTK_ALIGN_FILL".to_string(),
+                                  value: 0,
+                                  platforms: Vec::new(),
+                                },
+                                EnumValue {
+                                  name: "GTK_ALIGN_START".to_string(),
+                                  value: 1,
+                                  platforms: Vec::new(),
+                                },
+                                EnumValue {
+                                  name: "GTK_ALIGN_END".to_string(),
+                                  value: 2,
+                                  platforms: Vec::new(),
+                                }],
+                              "GtkAlign",
+                              &rules);
+  assert_eq!(r.len(), 3);
+  assert_eq!(r[0].name, "Fill");
+  assert_eq!(r[1].name, "Beginning");
+  assert_eq!(r[2].name, "End");
+}
+
+#[test]
+fn complete_type_enum() {
+  let enum_name = RustName::new(vec!["my_crate".to_string(), "Color".to_string()]).unwrap();
+  let processed_types = vec![RustProcessedTypeInfo {
+                               cpp_name: "Color".to_string(),
+                               cpp_template_arguments: None,
+                               kind: RustProcessedTypeKind::Enum {
+                                 values: Vec::new(),
+                                 integer_type: "u8".to_string(),
+                                 is_scoped: false,
+                               },
+                               rust_name: enum_name.clone(),
+                               platforms: Vec::new(),
+                             }];
+  let mut cpp_type = CppType {
+    base: CppTypeBase::Enum { name: "Color".to_string() },
+    indirection: CppTypeIndirection::None,
+    is_const: false,
+    is_const2: false,
+  };
+  let complete = |cpp_type: &CppType| {
+    complete_type(&processed_types,
+                  &[],
+                  &CppFfiType {
+                    original_type: cpp_type.clone(),
+                    ffi_type: cpp_type.clone(),
+                    conversion: IndirectionChange::NoChange,
+                  },
+                  &CppFfiArgumentMeaning::Argument(0),
+                  &ReturnValueAllocationPlace::NotApplicable,
+                  PointerPolicy::RawPointer)
+      .unwrap()
+  };
+  let rust_type = |base: RustName, indirection: RustTypeIndirection| {
+    RustType::Common {
+      base: base,
+      generic_arguments: None,
+      is_const: false,
+      is_const2: false,
+      indirection: indirection,
+    }
+  };
+
+  // values are passed as the underlying integer type
+  let value = complete(&cpp_type);
+  assert_eq!(value.rust_ffi_type,
+             rust_type(RustName::new(vec!["u8".to_string()]).unwrap(),
+                       RustTypeIndirection::None));
+  assert_eq!(value.rust_api_type,
+             rust_type(enum_name.clone(), RustTypeIndirection::None));
+  assert_eq!(value.rust_api_to_c_conversion, RustToCTypeConversion::EnumToInt);
+
+  cpp_type.indirection = CppTypeIndirection::Ptr;
+  let pointer = complete(&cpp_type);
+  assert_eq!(pointer.rust_ffi_type,
+             rust_type(enum_name, RustTypeIndirection::Ptr));
+  assert_eq!(pointer.rust_api_type, pointer.rust_ffi_type);
+  assert_eq!(pointer.rust_api_to_c_conversion, RustToCTypeConversion::None);
+}
+
+/// Creates a single variant Rust method for C++ function `set` with `arguments`
+/// of `int` or `bool` type. Only first `count` arguments are passed if `count` is
+/// less than the number of arguments, as if the rest of them were omitted.
+#[cfg(test)]
+fn suffixes_test_method(c_name: &str, arguments: &[(&str, &str)], count: usize) -> RustMethod {
+  use cpp_method::CppFunctionArgument;
+
+  let types: Vec<_> = arguments.iter()
+    .map(|&(name, type_name)| {
+      let cpp_type = CppType {
+        base: CppTypeBase::BuiltInNumeric(if type_name == "bool" {
+          CppBuiltInNumericType::Bool
+        } else {
+          CppBuiltInNumericType::Int
+        }),
+        indirection: CppTypeIndirection::None,
+        is_const: false,
+        is_const2: false,
+      };
+      let rust_type = RustType::Common {
+        base: RustName::new(vec![if type_name == "bool" { "bool" } else { "i32" }.to_string()])
+          .unwrap(),
+        generic_arguments: None,
+        is_const: false,
+        is_const2: false,
+        indirection: RustTypeIndirection::None,
+      };
+      (name, cpp_type, rust_type)
+    })
+    .collect();
+  let all_cpp_arguments: Vec<_> = types.iter()
+    .enumerate()
+    .map(|(index, &(name, ref cpp_type, _))| {
+      CppFunctionArgument {
+        name: name.to_string(),
+        argument_type: cpp_type.clone(),
+        has_default_value: index > 0,
+      }
+    })
+    .collect();
+  let mut cpp_method = CppMethod {
+    name: "set".to_string(),
+    class_membership: None,
+    return_type: CppType::void(),
+    arguments: all_cpp_arguments.clone(),
+    arguments_before_omitting: None,
+    inherited_from: None,
+    inheritance_chain: Vec::new(),
+    platforms: Vec::new(),
+    is_deprecated: false,
+    allows_variadic_arguments: false,
+    include_file: "set.h".to_string(),
+    origin_location: None,
+    template_arguments: None,
+    template_arguments_values: None,
+    operator: None,
+    declaration_code: None,
+  };
+  if count < arguments.len() {
+    cpp_method.arguments.truncate(count);
+    cpp_method.arguments_before_omitting = Some(all_cpp_arguments);
+  }
+  let complete = |cpp_type: &CppType, rust_type: &RustType| {
+    CompleteType {
+      cpp_type: cpp_type.clone(),
+      cpp_ffi_type: cpp_type.clone(),
+      cpp_to_ffi_conversion: IndirectionChange::NoChange,
+      rust_ffi_type: rust_type.clone(),
+      rust_api_type: rust_type.clone(),
+      rust_api_to_c_conversion: RustToCTypeConversion::None,
+    }
+  };
+  let variant = RustMethodArgumentsVariant {
+    arguments: types.iter()
+      .take(count)
+      .enumerate()
+      .map(|(index, &(name, ref cpp_type, ref rust_type))| {
+        RustMethodArgument {
+          argument_type: complete(cpp_type, rust_type),
+          name: name.to_string(),
+          ffi_index: Some(index as i32),
+        }
+      })
+      .collect(),
+    cpp_method: CppAndFfiMethod {
+      cpp_method: cpp_method,
+      allocation_place: ReturnValueAllocationPlace::NotApplicable,
+      c_signature: ::cpp_ffi_data::CppFfiFunctionSignature {
+        arguments: Vec::new(),
+        return_type: CppFfiType::void(),
+      },
+      c_name: c_name.to_string(),
+    },
+    return_type_ffi_index: None,
+    return_type: complete(&CppType::void(), &RustType::Void),
+    unsafe_reasons: Vec::new(),
+  };
+  RustMethod {
+    scope: RustMethodScope::Free,
+    name: RustName::new(vec!["qt_core".to_string(), "set".to_string()]).unwrap(),
+    arguments: RustMethodArguments::SingleVariant(variant),
+    doc: String::new(),
+  }
+}
+
+#[test]
+fn process_method_with_suffixes_test() {
+  let generator = test_generator(test_config());
+  let int_int = [("a", "int"), ("b", "int")];
+  let methods = vec![suffixes_test_method("qt_core_G_set1", &int_int, 2),
+                     suffixes_test_method("qt_core_G_set2", &[("c", "bool")], 1),
+                     suffixes_test_method("qt_core_G_set", &int_int, 1)];
+  let mut used_names = HashSet::new();
+  used_names.insert("set".to_string());
+  used_names.insert("set_bool".to_string());
+  let result = generator.process_method_with_suffixes(methods, None, &mut used_names).unwrap();
+  assert_eq!(result.len(), 2);
+  assert_eq!(result[0].name.last_name().unwrap(), "set_i32_i32");
+  if let RustMethodArguments::DefaultArguments(ref variants) = result[0].arguments {
+    assert_eq!(variants.len(), 2);
+    assert_eq!(variants[0].cpp_method.c_name, "qt_core_G_set");
+    assert_eq!(variants[1].cpp_method.c_name, "qt_core_G_set1");
+  } else {
+    panic!("DefaultArguments expected");
+  }
+  assert!(result[0].doc.contains("Argument `b` has a default value in C++"));
+  // the suffixed name conflicts with another method
+  assert_eq!(result[1].name.last_name().unwrap(), "set_bool2");
+  assert!(used_names.contains("set_i32_i32"));
+  assert!(used_names.contains("set_bool2"));
+
+  // variants can't be merged if the variant with one omitted argument is missing
+  let int_int_int = [("a", "int"), ("b", "int"), ("c", "int")];
+  let methods = vec![suffixes_test_method("qt_core_G_set", &int_int_int, 1),
+                     suffixes_test_method("qt_core_G_set1", &int_int_int, 3)];
+  let mut used_names = HashSet::new();
+  used_names.insert("set".to_string());
+  let result = generator.process_method_with_suffixes(methods, None, &mut used_names).unwrap();
+  assert_eq!(result.len(), 2);
+  assert_eq!(result[0].name.last_name().unwrap(), "set_i32");
+  assert_eq!(result[1].name.last_name().unwrap(), "set_i32_i32_i32");
+  for method in &result {
+    match method.arguments {
+      RustMethodArguments::SingleVariant(_) => {}
+      _ => panic!("SingleVariant expected"),
+    }
+  }
+}
+
+#[test]
+fn unsafe_reasons_test() {
+  use cpp_data::CppVisibility;
+  use cpp_method::{CppMethodClassMembership, CppMethodKind};
+
+  let mut config = test_config();
+  config.unsafe_methods = vec!["QObject".to_string(), "QTimer::start".to_string()];
+  let generator = test_generator(config);
+  let mut method = suffixes_test_method("qt_core_G_set", &[("a", "int")], 1);
+  let mut variant = match method.arguments {
+    RustMethodArguments::SingleVariant(ref variant) => variant.clone(),
+    _ => unreachable!(),
+  };
+  assert!(generator.unsafe_reasons(&variant.cpp_method.cpp_method,
+                                   &variant.arguments,
+                                   &variant.return_type)
+    .is_empty());
+
+  let pointer_type = RustType::Common {
+    base: RustName::new(vec!["qt_core".to_string(), "Object".to_string()]).unwrap(),
+    generic_arguments: None,
+    is_const: false,
+    is_const2: false,
+    indirection: RustTypeIndirection::Ptr,
+  };
+  variant.arguments[0].argument_type.rust_api_type = pointer_type.clone();
+  variant.return_type.rust_api_type = pointer_type;
+  assert_eq!(generator.unsafe_reasons(&variant.cpp_method.cpp_method,
+                                      &variant.arguments,
+                                      &variant.return_type),
+             vec!["takes raw pointer argument `a` that must be valid or null".to_string(),
+                  "returns a raw pointer that is not owned by the caller".to_string()]);
+
+  let mut cpp_method = variant.cpp_method.cpp_method.clone();
+  cpp_method.class_membership = Some(CppMethodClassMembership {
+    kind: CppMethodKind::Regular,
+    is_virtual: false,
+    is_pure_virtual: false,
+    is_const: false,
+    is_static: false,
+    visibility: CppVisibility::Public,
+    is_signal: false,
+    class_type: CppTypeClassBase {
+      name: "QObject".to_string(),
+      template_arguments: None,
+    },
+  });
+  let explicit_reason = "is explicitly marked as unsafe in the generator config".to_string();
+  // all methods of the listed class are unsafe
+  assert_eq!(generator.unsafe_reasons(&cpp_method, &[], &variant.return_type)[0],
+             explicit_reason);
+  cpp_method.class_membership.as_mut().unwrap().class_type.name = "QTimer".to_string();
+  assert!(generator.unsafe_reasons(&cpp_method, &[], &variant.return_type)[0] != explicit_reason);
+  cpp_method.name = "start".to_string();
+  assert_eq!(generator.unsafe_reasons(&cpp_method, &[], &variant.return_type)[0],
+             explicit_reason);
+  method.arguments = RustMethodArguments::SingleVariant(variant);
+  assert!(method.is_unsafe());
+}
+
+#[test]
+fn set_return_lifetime_test() {
+  use config::ReturnLifetime;
+
+  let reference = RustType::Common {
+    base: RustName::new(vec!["i32".to_string()]).unwrap(),
+    generic_arguments: None,
+    is_const: true,
+    is_const2: false,
+    indirection: RustTypeIndirection::Ref { lifetime: None },
+  };
+  let method = suffixes_test_method("qt_core_G_set",
+                                    &[("a", "int"), ("b", "int"), ("c", "int")],
+                                    3);
+  let mut original = match method.arguments {
+    RustMethodArguments::SingleVariant(variant) => variant,
+    _ => unreachable!(),
+  };
+  original.arguments[0].name = "self".to_string();
+  for index in &[0, 2] {
+    original.arguments[*index].argument_type.rust_api_type = reference.clone();
+  }
+  original.return_type.rust_api_type = reference.clone();
+  let lifetimes = |variant: &RustMethodArgumentsVariant| {
+    let mut result: Vec<Vec<String>> = variant.arguments
+      .iter()
+      .map(|arg| arg.argument_type.rust_api_type.lifetimes().into_iter().cloned().collect())
+      .collect();
+    result.push(variant.return_type.rust_api_type.lifetimes().into_iter().cloned().collect());
+    result
+  };
+  let run = |generator: &RustGenerator, variant: &RustMethodArgumentsVariant| {
+    let mut variant = variant.clone();
+    generator.set_return_lifetime(&variant.cpp_method,
+                                  &mut variant.arguments,
+                                  &mut variant.return_type)
+      .unwrap();
+    variant
+  };
+  let l = |names: &[&str]| -> Vec<String> { names.iter().map(|x| x.to_string()).collect() };
+
+  // tied to self, other reference arguments get separate lifetimes
+  let generator = test_generator(test_config());
+  assert_eq!(lifetimes(&run(&generator, &original)),
+             vec![l(&["l0"]), l(&[]), l(&["l1"]), l(&["l0"])]);
+
+  // tied to all reference arguments if there is no self
+  let mut no_self = original.clone();
+  no_self.arguments[0].name = "a".to_string();
+  assert_eq!(lifetimes(&run(&generator, &no_self)),
+             vec![l(&["l0"]), l(&[]), l(&["l0"]), l(&["l0"])]);
+
+  // lifetime set in the config
+  let mut config = test_config();
+  config.return_lifetimes = vec![("set".to_string(), ReturnLifetime::Static)];
+  let generator = test_generator(config);
+  assert_eq!(lifetimes(&run(&generator, &original)),
+             vec![l(&["l0"]), l(&[]), l(&["l1"]), l(&["static"])]);
+
+  // raw pointer is returned if there are no reference arguments
+  let mut no_refs = original.clone();
+  for arg in &mut no_refs.arguments {
+    arg.argument_type.rust_api_type = arg.argument_type.rust_ffi_type.clone();
+  }
+  let result = run(&test_generator(test_config()), &no_refs);
+  assert!(!result.return_type.rust_api_type.is_ref());
+}
+
+#[test]
+fn module_features_test() {
+  let object_type = |module: &str| {
+    RustType::Common {
+      base: RustName::new(vec!["qt_core".to_string(), module.to_string(), "Object".to_string()])
+        .unwrap(),
+      generic_arguments: None,
+      is_const: false,
+      is_const2: false,
+      indirection: RustTypeIndirection::Ptr,
+    }
+  };
+  let module = |name: &str, c_name: &str, argument_types: Vec<RustType>| {
+    let mut method = suffixes_test_method(c_name, &[("a", "int"), ("b", "int")], 2);
+    if let RustMethodArguments::SingleVariant(ref mut variant) = method.arguments {
+      for (arg, argument_type) in variant.arguments.iter_mut().zip(argument_types) {
+        arg.argument_type.rust_api_type = argument_type;
+      }
+    }
+    RustModule {
+      name: name.to_string(),
+      types: Vec::new(),
+      functions: vec![method],
+      submodules: Vec::new(),
+    }
+  };
+  let ffi_function = |name: &str| {
+    RustFFIFunction {
+      return_type: RustType::Void,
+      name: name.to_string(),
+      arguments: Vec::new(),
+      platforms: Vec::new(),
+    }
+  };
+  let modules = vec![// uses types of its own module and a module missing in the crate
+                     module("object", "qt_core_G_object", vec![object_type("object"),
+                                                               object_type("missing")]),
+                     module("timer", "qt_core_G_timer", vec![object_type("object")]),
+                     // uses both modules through a submodule
+                     RustModule {
+                       name: "application".to_string(),
+                       types: Vec::new(),
+                       functions: Vec::new(),
+                       submodules: vec![module("core_application",
+                                               "qt_core_G_application",
+                                               vec![object_type("timer"), object_type("object")])],
+                     }];
+  let ffi_functions = vec![("qobject.h".to_string(), vec![ffi_function("qt_core_G_object")]),
+                           ("qtimer.h".to_string(), vec![ffi_function("qt_core_G_timer")]),
+                           ("qcoreapplication.h".to_string(),
+                            vec![ffi_function("qt_core_G_unused"),
+                                 ffi_function("qt_core_G_application")])];
+  let features = module_features(&modules, &ffi_functions, "qt_core");
+  let feature = |name: &str, dependencies: &[&str], include_files: &[&str]| {
+    RustModuleFeature {
+      name: name.to_string(),
+      dependencies: dependencies.iter().map(|x| x.to_string()).collect(),
+      include_files: include_files.iter().map(|x| x.to_string()).collect(),
+    }
+  };
+  assert_eq!(features,
+             vec![feature("application", &["object", "timer"], &["qcoreapplication.h"]),
+                  feature("object", &[], &["qobject.h"]),
+                  feature("timer", &["object"], &["qtimer.h"])]);
+}
@@ -1,3 +1,4 @@
+use config::EnumRepresentation;
 use cpp_ffi_data::CppAndFfiMethod;
 use cpp_type::CppType;
 use errors::{Result, ChainErr, unexpected};
@@ -18,6 +19,9 @@ pub struct RustEnumValue {
   pub cpp_name: Option<String>,
   /// Documentation text
   pub doc: String,
+  /// Names of target platforms where the value is available.
+  /// Empty if the value is available on all platforms of the enum.
+  pub platforms: Vec<String>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -43,6 +47,9 @@ pub struct RustMethodArgumentsVariant {
   pub cpp_method: CppAndFfiMethod,
   pub return_type_ffi_index: Option<i32>,
   pub return_type: CompleteType,
+  /// Reasons for marking the function `unsafe`.
+  /// Empty if the function is safe.
+  pub unsafe_reasons: Vec<String>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -55,7 +62,15 @@ pub enum RustMethodArguments {
     params_trait_return_type: Option<RustType>,
     shared_arguments: Vec<RustMethodArgument>,
     variant_argument_name: String,
+    is_unsafe: bool,
+    /// Platforms where any of the variants is available
+    platforms: Vec<String>,
   },
+  /// Variants of the same C++ method that differ only by omitted
+  /// arguments with default values, sorted by number of arguments.
+  /// Arguments missing in any variant are exposed as nested `Option`s.
+  /// There must be a variant for each number of omitted arguments.
+  DefaultArguments(Vec<RustMethodArgumentsVariant>),
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -66,7 +81,7 @@ pub struct RustMethod {
   pub doc: String,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash, PartialOrd, Ord)]
 pub enum RustMethodSelfArgKind {
   Static,
   ConstRef,
@@ -74,11 +89,52 @@ pub enum RustMethodSelfArgKind {
   Value,
 }
 
+/// Returns names of platforms where any of `variants` is available.
+/// Empty list means all platforms.
+pub fn variants_platforms(variants: &[RustMethodArgumentsVariant]) -> Vec<String> {
+  let mut result = Vec::new();
+  for variant in variants {
+    if variant.cpp_method.cpp_method.platforms.is_empty() {
+      return Vec::new();
+    }
+    for platform in &variant.cpp_method.cpp_method.platforms {
+      if !result.contains(platform) {
+        result.push(platform.clone());
+      }
+    }
+  }
+  result
+}
+
 impl RustMethod {
+  /// Returns names of platforms where the method is available.
+  /// Empty list means all platforms.
+  pub fn platforms(&self) -> Vec<String> {
+    match self.arguments {
+      RustMethodArguments::SingleVariant(ref var) => var.cpp_method.cpp_method.platforms.clone(),
+      RustMethodArguments::MultipleVariants { ref platforms, .. } => platforms.clone(),
+      RustMethodArguments::DefaultArguments(ref variants) => variants_platforms(variants),
+    }
+  }
+
+  /// Returns true if the function should be declared as `unsafe fn`.
+  pub fn is_unsafe(&self) -> bool {
+    match self.arguments {
+      RustMethodArguments::SingleVariant(ref var) => !var.unsafe_reasons.is_empty(),
+      RustMethodArguments::MultipleVariants { ref is_unsafe, .. } => *is_unsafe,
+      RustMethodArguments::DefaultArguments(ref variants) => {
+        variants.iter().any(|var| !var.unsafe_reasons.is_empty())
+      }
+    }
+  }
+
   pub fn self_arg_kind(&self) -> Result<RustMethodSelfArgKind> {
     let args = match self.arguments {
       RustMethodArguments::SingleVariant(ref var) => &var.arguments,
       RustMethodArguments::MultipleVariants { ref shared_arguments, .. } => shared_arguments,
+      RustMethodArguments::DefaultArguments(ref variants) => {
+        &try!(variants.first().chain_err(|| "DefaultArguments can't be empty")).arguments
+      }
     };
     Ok(if let Some(arg) = args.get(0) {
       if arg.name == "self" {
@@ -173,8 +229,14 @@ pub enum RustTypeWrapperKind {
   Enum {
     values: Vec<RustEnumValue>,
     is_flaggable: bool,
+    representation: EnumRepresentation,
+    /// Integer type used as `repr` of the enum
+    integer_type: String,
+  },
+  Struct {
+    size: i32,
+    alignment: i32,
   },
-  Struct { size: i32 },
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -192,6 +254,7 @@ pub enum RustTypeDeclarationKind {
     shared_arguments: Vec<RustMethodArgument>,
     return_type: Option<RustType>,
     impls: Vec<RustMethodArgumentsVariant>,
+    is_unsafe: bool,
   },
 }
 
@@ -200,6 +263,9 @@ pub struct RustTypeDeclaration {
   pub name: String,
   pub kind: RustTypeDeclarationKind,
   pub doc: String,
+  /// Names of target platforms where the type is available.
+  /// Empty if the type is available on all platforms.
+  pub platforms: Vec<String>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -210,9 +276,22 @@ pub struct RustModule {
   pub submodules: Vec<RustModule>,
 }
 
+/// Cargo feature gating a top level module of the generated crate
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RustModuleFeature {
+  /// Name of the feature and the module
+  pub name: String,
+  /// Features of other modules whose types are used by this module
+  pub dependencies: Vec<String>,
+  /// C++ include files whose FFI functions are used by this module
+  pub include_files: Vec<String>,
+}
+
 
 use std::path::PathBuf;
 
+extern crate toml;
+
 pub struct InputCargoTomlData {
   /// Name of the crate
   pub name: String,
@@ -222,6 +301,8 @@ pub struct InputCargoTomlData {
   pub authors: Vec<String>,
   /// Name of the C++ library
   pub links: Option<String>,
+  /// Content of `[package.metadata.cpp_to_rust]` section
+  pub cpp_to_rust_metadata: Option<toml::Table>,
 }
 
 
@@ -255,6 +336,18 @@ impl InputCargoTomlData {
       } else {
         None
       },
+      cpp_to_rust_metadata: {
+        let metadata = package.get("metadata")
+          .and_then(|x| x.as_table())
+          .and_then(|x| x.get("cpp_to_rust"));
+        if let Some(metadata) = metadata {
+          Some(try!(metadata.as_table()
+              .chain_err(|| "'package.metadata.cpp_to_rust' must be a table"))
+            .clone())
+        } else {
+          None
+        }
+      },
     })
   }
 }
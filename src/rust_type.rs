@@ -132,6 +132,8 @@ pub enum RustType {
     return_type: Box<RustType>,
     arguments: Vec<RustType>,
   },
+  /// Tuple of two or more types
+  Tuple(Vec<RustType>),
 }
 
 impl RustType {
@@ -171,11 +173,71 @@ impl RustType {
         name
       }
       RustType::FunctionPointer { .. } => "fn".to_string(),
+      RustType::Tuple(ref items) => try!(items.iter().map_if_ok(|x| x.caption())).join("_"),
     })
   }
 
+  /// Returns names of all types referenced by this type,
+  /// including generic arguments and function pointer types.
+  pub fn referenced_names(&self) -> Vec<&RustName> {
+    match *self {
+      RustType::Void => Vec::new(),
+      RustType::Common { ref base, ref generic_arguments, .. } => {
+        let mut result = vec![base];
+        if let Some(ref args) = *generic_arguments {
+          for arg in args {
+            result.extend(arg.referenced_names());
+          }
+        }
+        result
+      }
+      RustType::FunctionPointer { ref return_type, ref arguments } => {
+        let mut result = return_type.referenced_names();
+        for arg in arguments {
+          result.extend(arg.referenced_names());
+        }
+        result
+      }
+      RustType::Tuple(ref items) => {
+        let mut result = Vec::new();
+        for item in items {
+          result.extend(item.referenced_names());
+        }
+        result
+      }
+    }
+  }
+
+  /// Returns true if this type is `Option<T>`.
+  pub fn is_option(&self) -> bool {
+    if let RustType::Common { ref base, ref generic_arguments, .. } = *self {
+      base.parts.len() == 1 && &base.parts[0] == "Option" &&
+      generic_arguments.as_ref().map_or(false, |args| args.len() == 1)
+    } else {
+      false
+    }
+  }
+
+  /// Returns true if this type is a raw pointer (including pointer to pointer).
+  pub fn is_pointer(&self) -> bool {
+    match *self {
+      RustType::Common { ref indirection, .. } => {
+        *indirection == RustTypeIndirection::Ptr || *indirection == RustTypeIndirection::PtrPtr
+      }
+      RustType::Void |
+      RustType::FunctionPointer { .. } |
+      RustType::Tuple(_) => false,
+    }
+  }
+
+  /// Returns true if this type is a reference or an optional reference.
   #[allow(dead_code)]
   pub fn is_ref(&self) -> bool {
+    if self.is_option() {
+      if let RustType::Common { ref generic_arguments, .. } = *self {
+        return generic_arguments.as_ref().unwrap()[0].is_ref();
+      }
+    }
     match *self {
       RustType::Common { ref indirection, .. } => {
         match *indirection {
@@ -185,13 +247,21 @@ impl RustType {
         }
       }
       RustType::Void |
-      RustType::FunctionPointer { .. } => false,
+      RustType::FunctionPointer { .. } |
+      RustType::Tuple(_) => false,
     }
   }
 
   pub fn with_lifetime(&self, new_lifetime: String) -> RustType {
     let mut r = self.clone();
-    if let RustType::Common { ref mut indirection, .. } = r {
+    let is_option = self.is_option();
+    if let RustType::Common { ref mut indirection, ref mut generic_arguments, .. } = r {
+      if is_option {
+        if let Some(ref mut args) = *generic_arguments {
+          args[0] = args[0].with_lifetime(new_lifetime);
+        }
+        return r;
+      }
       match *indirection {
         RustTypeIndirection::Ref { ref mut lifetime } |
         RustTypeIndirection::PtrRef { ref mut lifetime } => *lifetime = Some(new_lifetime),
@@ -201,16 +271,27 @@ impl RustType {
     r
   }
 
-  pub fn lifetime(&self) -> Option<&String> {
+  /// Returns all lifetimes used in this type, including lifetimes
+  /// of generic arguments and tuple items.
+  pub fn lifetimes(&self) -> Vec<&String> {
     match *self {
-      RustType::Common { ref indirection, .. } => {
+      RustType::Common { ref indirection, ref generic_arguments, .. } => {
+        let mut result = Vec::new();
         match *indirection {
-          RustTypeIndirection::Ref { ref lifetime } |
-          RustTypeIndirection::PtrRef { ref lifetime } => lifetime.as_ref(),
-          _ => None,
+          RustTypeIndirection::Ref { lifetime: Some(ref lifetime) } |
+          RustTypeIndirection::PtrRef { lifetime: Some(ref lifetime) } => result.push(lifetime),
+          _ => {}
+        }
+        if let Some(ref args) = *generic_arguments {
+          for arg in args {
+            result.extend(arg.lifetimes());
+          }
         }
+        result
       }
-      _ => None,
+      RustType::Tuple(ref items) => items.iter().flat_map(|x| x.lifetimes()).collect(),
+      RustType::Void |
+      RustType::FunctionPointer { .. } => Vec::new(),
     }
   }
 
@@ -258,6 +339,9 @@ impl RustType {
           arguments: try!(arguments.iter().map_if_ok(|arg| arg.dealias_libc())),
         }
       }
+      RustType::Tuple(ref items) => {
+        RustType::Tuple(try!(items.iter().map_if_ok(|x| x.dealias_libc())))
+      }
     })
   }
 }
@@ -270,6 +354,15 @@ pub enum RustToCTypeConversion {
   ValueToPtr,
   CppBoxToPtr,
   QFlagsToUInt,
+  /// `Option<&T>` in Rust API and `*const T` in FFI;
+  /// `None` corresponds to null pointer
+  OptionRefToPtr,
+  /// `&T` in Rust API and `*const T` in FFI;
+  /// returned null pointer causes a panic
+  NonNullRefToPtr,
+  /// Enum in Rust API and its underlying integer type in FFI;
+  /// returned value not declared in the enum causes a panic
+  EnumToInt,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -293,4 +386,7 @@ pub struct RustFFIFunction {
   pub return_type: RustType,
   pub name: String,
   pub arguments: Vec<RustFFIArgument>,
+  /// Names of target platforms where the function is available.
+  /// Empty if the function is available on all platforms.
+  pub platforms: Vec<String>,
 }
@@ -0,0 +1,48 @@
+use build_cache::{self, ContentHash};
+use file_utils::{PathBufWithAdded, create_dir, create_file, canonicalize};
+
+extern crate tempdir;
+
+#[test]
+fn content_hash() {
+  let mut hash1 = ContentHash::new();
+  hash1.add_str("ab");
+  hash1.add_str("c");
+  let mut hash2 = ContentHash::new();
+  hash2.add_str("a");
+  hash2.add_str("bc");
+  let mut hash3 = ContentHash::new();
+  hash3.add_str("ab");
+  hash3.add_str("c");
+  assert!(hash1.value() != hash2.value());
+  assert_eq!(hash1.value(), hash3.value());
+  assert_eq!(ContentHash::new().value(), "cbf29ce484222325");
+}
+
+#[test]
+fn dir_hash() {
+  let dir = tempdir::TempDir::new("test_build_cache").unwrap();
+  let dir_path = canonicalize(&dir.path().to_path_buf()).unwrap();
+  create_dir(dir_path.with_added("sub")).unwrap();
+  create_dir(dir_path.with_added("excluded")).unwrap();
+  create_file(dir_path.with_added("sub").with_added("a.h")).unwrap().write("int a;").unwrap();
+  let hash_dir = || {
+    let mut hash = ContentHash::new();
+    hash.add_path(&dir_path, &[dir_path.with_added("excluded")]).unwrap();
+    hash.value()
+  };
+  let value1 = hash_dir();
+  create_file(dir_path.with_added("excluded").with_added("b.h")).unwrap().write("int b;").unwrap();
+  assert_eq!(hash_dir(), value1);
+  create_file(dir_path.with_added("sub").with_added("a.h")).unwrap().write("int a2;").unwrap();
+  let value2 = hash_dir();
+  assert!(value2 != value1);
+
+  let key_path = dir_path.with_added("output.key");
+  assert!(!build_cache::is_up_to_date(&key_path, &value2));
+  build_cache::save_key(&key_path, &value2).unwrap();
+  assert!(build_cache::is_up_to_date(&key_path, &value2));
+  assert!(!build_cache::is_up_to_date(&key_path, &value1));
+  build_cache::remove_key(&key_path).unwrap();
+  assert!(!key_path.exists());
+}
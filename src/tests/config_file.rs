@@ -0,0 +1,112 @@
+use config::{Config, CppBuildBackend};
+use config_file;
+use file_utils::{PathBufWithAdded, create_file};
+
+use std::path::PathBuf;
+
+extern crate tempdir;
+extern crate toml;
+
+#[test]
+fn load() {
+  let dir = tempdir::TempDir::new("test_config_file").unwrap();
+  let path = dir.path().with_added("cpp_to_rust.toml");
+  create_file(&path)
+    .unwrap()
+    .write("linked_libs = [\"foo\"]\n\
+            include_paths = [\"include\", \"/usr/include/foo\"]\n\
+            include_directives = [\"foo.h\"]\n")
+    .unwrap();
+  let config = config_file::load(&path).unwrap();
+  assert_eq!(config.linked_libs(), &["foo".to_string()]);
+  assert_eq!(config.include_paths(),
+             &[dir.path().with_added("include"), PathBuf::from("/usr/include/foo")]);
+  assert_eq!(config.include_directives(), &[PathBuf::from("foo.h")]);
+  assert!(config.lib_paths().is_empty());
+}
+
+#[test]
+fn load_json() {
+  let dir = tempdir::TempDir::new("test_config_file").unwrap();
+  let path = dir.path().with_added("cpp_to_rust.json");
+  create_file(&path)
+    .unwrap()
+    .write(r#"{"cpp_parser_blocked_names": ["foo::detail"], "sys_crate": true,
+               "blocked_methods": ["^Foo::bar$"]}"#)
+    .unwrap();
+  let config = config_file::load(&path).unwrap();
+  assert_eq!(config.cpp_parser_blocked_names(), &["foo::detail".to_string()]);
+  assert!(config.sys_crate());
+  assert_eq!(config.cpp_ffi_generator_filters().len(), 1);
+}
+
+#[test]
+fn invalid_regex() {
+  let dir = tempdir::TempDir::new("test_config_file").unwrap();
+  let path = dir.path().with_added("cpp_to_rust.toml");
+  create_file(&path).unwrap().write("blocked_classes = [\"(\"]\n").unwrap();
+  assert!(config_file::load(&path).is_err());
+}
+
+#[test]
+fn build_settings() {
+  let dir = tempdir::TempDir::new("test_config_file").unwrap();
+  let path = dir.path().with_added("cpp_to_rust.toml");
+  create_file(&path)
+    .unwrap()
+    .write("cpp_build_backend = \"compiler\"\n\
+            cmake_generator = \"Ninja\"\n\
+            cpp_compiler_launcher = \"ccache\"\n\
+            [cmake_cache_vars]\n\
+            CMAKE_CXX_COMPILER = \"clang++\"\n\
+            FOO = \"1\"\n")
+    .unwrap();
+  let config = config_file::load(&path).unwrap();
+  assert_eq!(config.cpp_build_backend(), CppBuildBackend::Compiler);
+  assert_eq!(config.cmake_generator(), Some(&"Ninja".to_string()));
+  assert_eq!(config.cpp_compiler_launcher(), Some(&"ccache".to_string()));
+  assert_eq!(config.cmake_cache_vars(),
+             &[("CMAKE_CXX_COMPILER".to_string(), "clang++".to_string()),
+               ("FOO".to_string(), "1".to_string())]);
+}
+
+#[test]
+fn invalid_build_backend() {
+  let dir = tempdir::TempDir::new("test_config_file").unwrap();
+  let path = dir.path().with_added("cpp_to_rust.toml");
+  create_file(&path).unwrap().write("cpp_build_backend = \"make\"\n").unwrap();
+  assert!(config_file::load(&path).is_err());
+}
+
+#[test]
+fn unknown_key() {
+  let table = toml::Parser::new("linked_libs = [\"foo\"]\nlinked_lib = [\"bar\"]\n")
+    .parse()
+    .unwrap();
+  let mut config = Config::new();
+  let message = config_file::apply_table(&mut config, &table, &PathBuf::from("/tmp"))
+    .unwrap_err()
+    .to_string();
+  assert!(message.contains("'linked_lib'"));
+  assert!(message.contains("linked_libs, linked_frameworks"));
+
+  let dir = tempdir::TempDir::new("test_config_file").unwrap();
+  let path = dir.path().with_added("cpp_to_rust.toml");
+  create_file(&path)
+    .unwrap()
+    .write("[[filter_rules]]\naction = \"block\"\nnmae = \"Foo::*\"\n")
+    .unwrap();
+  assert!(config_file::load(&path).is_err());
+}
+
+#[test]
+fn json_integer_out_of_range() {
+  let dir = tempdir::TempDir::new("test_config_file").unwrap();
+  let path = dir.path().with_added("cpp_to_rust.json");
+  create_file(&path)
+    .unwrap()
+    .write(r#"{"cmake_cache_vars": {"FOO": 18446744073709551615}}"#)
+    .unwrap();
+  let err = config_file::load(&path).unwrap_err();
+  assert!(err.to_string().contains("integer is too large"));
+}
@@ -0,0 +1,38 @@
+use coverage_report::{self, SkippedItem, SkipStage};
+use tests::cpp_method::{empty_regular_method, empty_membership};
+
+#[test]
+fn generate() {
+  let mut method1 = empty_regular_method();
+  method1.name = "method1".to_string();
+  method1.class_membership = Some(empty_membership("Class1"));
+  let mut method2 = empty_regular_method();
+  method2.name = "method2".to_string();
+  method2.class_membership = Some(empty_membership("Class1"));
+  let mut method3 = empty_regular_method();
+  method3.name = "func1".to_string();
+
+  let methods = vec![("Class1".to_string(), method1.short_text()),
+                     ("Class1".to_string(), method2.short_text()),
+                     (String::new(), method3.short_text())];
+  let skipped_items = vec![SkippedItem::method(&method2, SkipStage::RustTypes, "bad type"),
+                           SkippedItem::method(&method1, SkipStage::FfiSignature, "bad\nsig")];
+  let report = coverage_report::generate(methods, skipped_items, &[]);
+  assert_eq!(report.classes.len(), 2);
+  assert_eq!(report.classes[0].class_name, "");
+  assert_eq!(report.classes[0].total_methods, 1);
+  assert_eq!(report.classes[0].wrapped_methods, 0);
+  assert_eq!(report.classes[1].class_name, "Class1");
+  assert_eq!(report.classes[1].total_methods, 2);
+  assert_eq!(report.classes[1].wrapped_methods, 0);
+  assert_eq!(report.skipped_items.len(), 2);
+  assert_eq!(report.skipped_items[0].name, method1.short_text());
+  assert_eq!(report.skipped_items[1].stage, SkipStage::RustTypes);
+
+  let markdown = report.to_markdown();
+  assert!(markdown.contains("0 of 3 methods are wrapped"));
+  assert!(markdown.contains("| Class1 | 0 | 2 | 0.0% |"));
+  assert!(markdown.contains("### Class1"));
+  assert!(markdown.contains(&format!("- `{}` (): FFI signature: bad sig",
+                                     method1.short_text())));
+}
@@ -0,0 +1,228 @@
+use config::TargetPlatform;
+use cpp_code_generator::CppCodeGenerator;
+use cpp_data::{CppTypeData, CppTypeKind, CppOriginLocation, EnumValue};
+use cpp_ffi_data::{CppAndFfiMethod, CppFfiFunctionSignature, CppFfiFunctionArgument, CppFfiType,
+                   CppFfiArgumentMeaning, IndirectionChange};
+use cpp_ffi_generator::CppFfiHeaderData;
+use cpp_method::ReturnValueAllocationPlace;
+use cpp_type::{CppType, CppTypeBase, CppTypeIndirection, CppTypeClassBase, CppBuiltInNumericType};
+use file_utils::{PathBufWithAdded, create_dir, file_to_string};
+use tests::cpp_method::empty_regular_method;
+
+extern crate tempdir;
+
+fn cpp_type(base: CppTypeBase, indirection: CppTypeIndirection) -> CppType {
+  CppType {
+    base: base,
+    indirection: indirection,
+    is_const: false,
+    is_const2: false,
+  }
+}
+
+fn class_base(name: &str) -> CppTypeBase {
+  CppTypeBase::Class(CppTypeClassBase {
+    name: name.to_string(),
+    template_arguments: None,
+  })
+}
+
+fn ffi_type(ffi_type: CppType) -> CppFfiType {
+  CppFfiType {
+    original_type: ffi_type.clone(),
+    ffi_type: ffi_type,
+    conversion: IndirectionChange::NoChange,
+  }
+}
+
+fn ffi_method(c_name: &str,
+              arguments: Vec<(&str, CppType)>,
+              return_type: CppType)
+              -> CppAndFfiMethod {
+  CppAndFfiMethod {
+    cpp_method: empty_regular_method(),
+    allocation_place: ReturnValueAllocationPlace::NotApplicable,
+    c_signature: CppFfiFunctionSignature {
+      arguments: arguments.into_iter()
+        .enumerate()
+        .map(|(index, (name, t))| {
+          CppFfiFunctionArgument {
+            name: name.to_string(),
+            argument_type: ffi_type(t),
+            meaning: CppFfiArgumentMeaning::Argument(index as i8),
+          }
+        })
+        .collect(),
+      return_type: ffi_type(return_type),
+    },
+    c_name: c_name.to_string(),
+  }
+}
+
+#[test]
+fn c_header() {
+  let dir = tempdir::TempDir::new("test_cpp_code_generator").unwrap();
+  let lib_path = dir.path().with_added("ctest_c");
+  create_dir(&lib_path).unwrap();
+  create_dir(lib_path.with_added("include")).unwrap();
+  let generator = CppCodeGenerator::new("ctest_c".to_string(),
+                                        lib_path.clone(),
+                                        false,
+                                        Vec::new(),
+                                        Vec::new());
+  let void = cpp_type(CppTypeBase::Void, CppTypeIndirection::None);
+  let int = cpp_type(CppTypeBase::BuiltInNumeric(CppBuiltInNumericType::Int),
+                     CppTypeIndirection::None);
+  let bool_type = cpp_type(CppTypeBase::BuiltInNumeric(CppBuiltInNumericType::Bool),
+                           CppTypeIndirection::None);
+
+  // const Foo* const* and ns::Bar**
+  let mut const_foo_ptr_ptr = cpp_type(class_base("Foo"), CppTypeIndirection::PtrPtr);
+  const_foo_ptr_ptr.is_const = true;
+  const_foo_ptr_ptr.is_const2 = true;
+  let bar_ptr_ptr = cpp_type(class_base("ns::Bar"), CppTypeIndirection::PtrPtr);
+  let ptrs_method = ffi_method("ctest_c_G_ptrs",
+                               vec![("items", const_foo_ptr_ptr), ("out", bar_ptr_ptr)],
+                               void.clone());
+
+  // int (*cb)(Foo*, bool) argument and void (*)(int) return type
+  let callback_arg = cpp_type(CppTypeBase::FunctionPointer {
+                                return_type: Box::new(int.clone()),
+                                arguments: vec![cpp_type(class_base("Foo"),
+                                                         CppTypeIndirection::Ptr),
+                                                bool_type],
+                                allows_variadic_arguments: false,
+                              },
+                              CppTypeIndirection::Ptr);
+  let callback_return = cpp_type(CppTypeBase::FunctionPointer {
+                                   return_type: Box::new(void.clone()),
+                                   arguments: vec![int.clone()],
+                                   allows_variadic_arguments: false,
+                                 },
+                                 CppTypeIndirection::Ptr);
+  let callback_method = ffi_method("ctest_c_G_callback",
+                                   vec![("cb", callback_arg)],
+                                   callback_return);
+
+  let enum_method = ffi_method("ctest_c_G_flags",
+                               vec![("value",
+                                     cpp_type(CppTypeBase::Enum { name: "ns::Flags".to_string() },
+                                              CppTypeIndirection::None))],
+                               void);
+  let enum_type = CppTypeData {
+    name: "ns::Flags".to_string(),
+    include_file: "flags.h".to_string(),
+    origin_location: CppOriginLocation {
+      include_file_path: "flags.h".to_string(),
+      line: 0,
+      column: 0,
+    },
+    kind: CppTypeKind::Enum {
+      values: vec![EnumValue {
+                     name: "A".to_string(),
+                     value: 1,
+                     platforms: Vec::new(),
+                   },
+                   EnumValue {
+                     name: "Big".to_string(),
+                     value: 1 << 40,
+                     platforms: Vec::new(),
+                   },
+                   EnumValue {
+                     name: "Negative".to_string(),
+                     value: -(1 << 33),
+                     platforms: Vec::new(),
+                   }],
+      underlying_type: cpp_type(CppTypeBase::BuiltInNumeric(CppBuiltInNumericType::LongLong),
+                                CppTypeIndirection::None),
+      is_scoped: false,
+    },
+    platforms: Vec::new(),
+  };
+
+  generator.generate_c_header(&[CppFfiHeaderData {
+                                  include_file: "foo.h".to_string(),
+                                  include_file_base_name: "foo".to_string(),
+                                  methods: vec![ptrs_method, callback_method, enum_method],
+                                }],
+                              &[&enum_type])
+    .unwrap();
+  let header = file_to_string(lib_path.with_added("include").with_added("ctest_c_c_api.h"))
+    .unwrap();
+  assert!(header.contains("typedef struct ctest_c_Foo ctest_c_Foo;\n\
+                           typedef struct ctest_c_ns_Bar ctest_c_ns_Bar;\n"));
+  assert!(header.contains("CTEST_C_EXPORT void ctest_c_G_ptrs(const ctest_c_Foo* const * items, \
+                           ctest_c_ns_Bar** out);\n"));
+  assert!(header.contains("CTEST_C_EXPORT void (*ctest_c_G_callback(int (*cb)(ctest_c_Foo*, \
+                           bool)))(int);\n"));
+  assert!(header.contains("CTEST_C_EXPORT void ctest_c_G_flags(ctest_c_ns_Flags value);\n"));
+  // values outside of the int range can't be enumerators in C
+  assert!(header.contains("typedef long long ctest_c_ns_Flags;\n\
+                           enum {\n  ctest_c_ns_Flags_A = 1,\n};\n\
+                           #define ctest_c_ns_Flags_Big ((ctest_c_ns_Flags) 1099511627776LL)\n\
+                           #define ctest_c_ns_Flags_Negative ((ctest_c_ns_Flags) \
+                           -8589934592LL)\n"));
+}
+
+#[test]
+fn platform_specific_headers() {
+  let dir = tempdir::TempDir::new("test_cpp_code_generator").unwrap();
+  let lib_path = dir.path().with_added("ctest_c");
+  create_dir(&lib_path).unwrap();
+  create_dir(lib_path.with_added("include")).unwrap();
+  create_dir(lib_path.with_added("src")).unwrap();
+  let platforms = vec![TargetPlatform {
+                         name: "windows".to_string(),
+                         target_triple: "x86_64-pc-windows-msvc".to_string(),
+                         defines: vec!["_WIN32".to_string()],
+                         rust_cfg: "target_os = \"windows\"".to_string(),
+                         cpp_condition: "defined(_WIN32)".to_string(),
+                       },
+                       TargetPlatform {
+                         name: "linux".to_string(),
+                         target_triple: "x86_64-unknown-linux-gnu".to_string(),
+                         defines: Vec::new(),
+                         rust_cfg: "target_os = \"linux\"".to_string(),
+                         cpp_condition: "defined(__linux__)".to_string(),
+                       }];
+  let generator = CppCodeGenerator::new("ctest_c".to_string(),
+                                        lib_path.clone(),
+                                        false,
+                                        Vec::new(),
+                                        platforms);
+  let void = cpp_type(CppTypeBase::Void, CppTypeIndirection::None);
+  let mut common_method = ffi_method("ctest_c_G_common", Vec::new(), void.clone());
+  common_method.cpp_method.name = "common".to_string();
+  let mut windows_method = ffi_method("ctest_c_G_windows_only", Vec::new(), void);
+  windows_method.cpp_method.name = "windows_only".to_string();
+  windows_method.cpp_method.platforms = vec!["windows".to_string()];
+  let data = vec![CppFfiHeaderData {
+                    include_file: "common".to_string(),
+                    include_file_base_name: "common".to_string(),
+                    methods: vec![common_method, windows_method.clone()],
+                  },
+                  CppFfiHeaderData {
+                    include_file: "windows".to_string(),
+                    include_file_base_name: "windows".to_string(),
+                    methods: vec![windows_method],
+                  }];
+  assert_eq!(data[0].platforms(), Vec::<String>::new());
+  assert_eq!(data[1].platforms(), vec!["windows".to_string()]);
+  generator.generate_files(&data).unwrap();
+
+  let all_headers = file_to_string(lib_path.with_added("include").with_added("ctest_c.h"))
+    .unwrap();
+  assert!(all_headers.contains("#include \"ctest_c_common.h\"\n\
+                                #if (defined(_WIN32))\n\
+                                #include \"ctest_c_windows.h\"\n\
+                                #endif\n"));
+  let common_cpp = file_to_string(lib_path.with_added("src").with_added("ctest_c_common.cpp"))
+    .unwrap();
+  assert!(common_cpp.starts_with("#include \"ctest_c_common.h\"\n"));
+  assert!(common_cpp.contains("#if (defined(_WIN32))\nvoid ctest_c_G_windows_only()"));
+  let windows_cpp = file_to_string(lib_path.with_added("src").with_added("ctest_c_windows.cpp"))
+    .unwrap();
+  assert!(windows_cpp.starts_with("#if (defined(_WIN32))\n\
+                                   #include \"ctest_c_windows.h\"\n\
+                                   #endif\n"));
+}
@@ -0,0 +1,87 @@
+use cpp_compiler_builder::CppCompilerBuilder;
+use file_utils::{PathBufWithAdded, create_dir, create_file};
+use launcher::BuildProfile;
+use utils::is_msvc;
+
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+extern crate tempdir;
+
+fn build(source_dir: &PathBuf,
+         build_dir: &PathBuf,
+         install_dir: &PathBuf,
+         dependency_key: &str) {
+  CppCompilerBuilder {
+      source_dir: source_dir,
+      build_dir: build_dir,
+      install_dir: install_dir,
+      lib_name: "ctest_c",
+      sources: None,
+      include_dirs: &[],
+      framework_dirs: &[],
+      cpp_compiler_flags: &[],
+      build_profile: BuildProfile::Debug,
+      num_jobs: 2,
+      compiler_launcher: None,
+      dependency_key: dependency_key,
+      pipe_output: true,
+    }
+    .run()
+    .unwrap_or_else(|e| {
+      e.display_report();
+      panic!("{}", e);
+    });
+}
+
+#[test]
+fn build_static_lib() {
+  let dir = tempdir::TempDir::new("test_cpp_compiler_builder").unwrap();
+  let source_dir = dir.path().with_added("source");
+  let build_dir = dir.path().with_added("build");
+  let install_dir = dir.path().with_added("install");
+  create_dir(&source_dir).unwrap();
+  create_dir(&build_dir).unwrap();
+  create_dir(source_dir.with_added("src")).unwrap();
+  create_dir(source_dir.with_added("include")).unwrap();
+  create_file(source_dir.with_added("include").with_added("ctest_c_global.h"))
+    .unwrap()
+    .write("int ctest_c_answer();\nint ctest_c_twice(int x);\n")
+    .unwrap();
+  create_file(source_dir.with_added("src").with_added("ctest_c_a.cpp"))
+    .unwrap()
+    .write("#include \"ctest_c_global.h\"\nint ctest_c_answer() { return 42; }\n")
+    .unwrap();
+  create_file(source_dir.with_added("src").with_added("ctest_c_b.cpp"))
+    .unwrap()
+    .write("#include \"ctest_c_global.h\"\nint ctest_c_twice(int x) { return 2 * x; }\n")
+    .unwrap();
+  build(&source_dir, &build_dir, &install_dir, "key1");
+
+  let lib_name = if is_msvc() {
+    "ctest_c.lib"
+  } else {
+    "libctest_c.a"
+  };
+  assert!(install_dir.with_added("lib").with_added(lib_name).is_file());
+  assert!(install_dir.with_added("include").with_added("ctest_c_global.h").is_file());
+
+  // unchanged sources are not recompiled
+  let object_name = if is_msvc() {
+    "ctest_c_a.obj"
+  } else {
+    "ctest_c_a.o"
+  };
+  let object_path = build_dir.with_added(object_name);
+  let object_modified = fs::metadata(&object_path).unwrap().modified().unwrap();
+  build(&source_dir, &build_dir, &install_dir, "key1");
+  assert_eq!(fs::metadata(&object_path).unwrap().modified().unwrap(),
+             object_modified);
+
+  // all objects are rebuilt if headers of dependencies change
+  thread::sleep(Duration::from_secs(1));
+  build(&source_dir, &build_dir, &install_dir, "key2");
+  assert!(fs::metadata(&object_path).unwrap().modified().unwrap() > object_modified);
+}
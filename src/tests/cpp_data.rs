@@ -0,0 +1,371 @@
+use coverage_report::{SkippedItem, SkipStage};
+use cpp_data::{CppData, CppTypeData, CppTypeKind, CppOriginLocation, CppBaseSpecifier,
+               CppVisibility, EnumValue, CppTemplateInstantiation, CppTemplateInstantiations};
+use cpp_type::{CppType, CppTypeBase, CppTypeClassBase, CppTypeIndirection, CppBuiltInNumericType};
+use file_utils::{PathBufWithAdded, file_to_string, load_json, save_json};
+use tests::cpp_method::{empty_regular_method, empty_membership};
+
+use std::time::Instant;
+
+extern crate tempdir;
+
+#[test]
+fn merge_platforms() {
+  let mut method1 = empty_regular_method();
+  method1.name = "common".to_string();
+  let mut method2 = empty_regular_method();
+  method2.name = "windows_only".to_string();
+  let mut method3 = empty_regular_method();
+  method3.name = "linux_only".to_string();
+  let windows_data = CppData {
+    methods: vec![method1.clone(), method2.clone()],
+    ..CppData::default()
+  };
+  let linux_data = CppData {
+    methods: vec![method3.clone(), method1.clone()],
+    ..CppData::default()
+  };
+  let result = CppData::merge_platforms(vec![("windows".to_string(), windows_data),
+                                             ("linux".to_string(), linux_data)])
+    .unwrap();
+  assert_eq!(result.methods.len(), 3);
+  assert_eq!(result.methods[0], method1);
+  assert!(result.methods[0].platforms.is_empty());
+  assert_eq!(result.methods[1].name, "windows_only");
+  assert_eq!(result.methods[1].platforms, vec!["windows".to_string()]);
+  assert_eq!(result.methods[2].name, "linux_only");
+  assert_eq!(result.methods[2].platforms, vec!["linux".to_string()]);
+}
+
+#[test]
+fn merge_platforms_different_locations() {
+  let mut method1 = empty_regular_method();
+  method1.name = "common".to_string();
+  let mut method2 = method1.clone();
+  method1.origin_location = Some(CppOriginLocation {
+    include_file_path: "/windows/include/common.h".to_string(),
+    line: 1,
+    column: 1,
+  });
+  method1.declaration_code = Some("void common();".to_string());
+  method2.origin_location = Some(CppOriginLocation {
+    include_file_path: "/linux/include/common.h".to_string(),
+    line: 2,
+    column: 1,
+  });
+  method2.declaration_code = Some("void common ();".to_string());
+  let windows_data = CppData { methods: vec![method1.clone()], ..CppData::default() };
+  let linux_data = CppData { methods: vec![method2], ..CppData::default() };
+  let result = CppData::merge_platforms(vec![("windows".to_string(), windows_data),
+                                             ("linux".to_string(), linux_data)])
+    .unwrap();
+  assert_eq!(result.methods.len(), 1);
+  assert_eq!(result.methods[0], method1);
+  assert!(result.methods[0].platforms.is_empty());
+}
+
+fn type_data(name: &str, kind: CppTypeKind) -> CppTypeData {
+  CppTypeData {
+    name: name.to_string(),
+    include_file: "types.h".to_string(),
+    origin_location: CppOriginLocation {
+      include_file_path: "types.h".to_string(),
+      line: 0,
+      column: 0,
+    },
+    kind: kind,
+    platforms: Vec::new(),
+  }
+}
+
+fn class_kind(size: i32) -> CppTypeKind {
+  CppTypeKind::Class {
+    size: Some(size),
+    alignment: Some(8),
+    bases: Vec::new(),
+    fields: Vec::new(),
+    template_arguments: None,
+    using_directives: Vec::new(),
+  }
+}
+
+fn enum_kind(values: &[(&str, i64)], underlying_type: CppBuiltInNumericType) -> CppTypeKind {
+  CppTypeKind::Enum {
+    values: values.iter()
+      .map(|&(name, value)| {
+        EnumValue {
+          name: name.to_string(),
+          value: value,
+          platforms: Vec::new(),
+        }
+      })
+      .collect(),
+    underlying_type: CppType {
+      base: CppTypeBase::BuiltInNumeric(underlying_type),
+      indirection: CppTypeIndirection::None,
+      is_const: false,
+      is_const2: false,
+    },
+    is_scoped: false,
+  }
+}
+
+#[test]
+fn merge_platforms_different_layouts() {
+  let windows_data = CppData { types: vec![type_data("A", class_kind(8))], ..CppData::default() };
+  let linux_data = CppData { types: vec![type_data("A", class_kind(16))], ..CppData::default() };
+  assert!(CppData::merge_platforms(vec![("windows".to_string(), windows_data.clone()),
+                                        ("linux".to_string(), windows_data.clone())])
+    .is_ok());
+  assert!(CppData::merge_platforms(vec![("windows".to_string(), windows_data),
+                                        ("linux".to_string(), linux_data)])
+    .is_err());
+}
+
+#[test]
+fn merge_platforms_enum_values() {
+  let windows_data = CppData {
+    types: vec![type_data("E", enum_kind(&[("A", 1), ("B", 2)], CppBuiltInNumericType::Int))],
+    ..CppData::default()
+  };
+  let linux_data = CppData {
+    types: vec![type_data("E", enum_kind(&[("A", 1), ("C", 3)], CppBuiltInNumericType::Int))],
+    ..CppData::default()
+  };
+  let result = CppData::merge_platforms(vec![("windows".to_string(), windows_data.clone()),
+                                             ("linux".to_string(), linux_data)])
+    .unwrap();
+  assert_eq!(result.types.len(), 1);
+  assert!(result.types[0].platforms.is_empty());
+  if let CppTypeKind::Enum { ref values, .. } = result.types[0].kind {
+    assert_eq!(values.len(), 3);
+    assert_eq!(values[0].name, "A");
+    assert!(values[0].platforms.is_empty());
+    assert_eq!(values[1].name, "B");
+    assert_eq!(values[1].platforms, vec!["windows".to_string()]);
+    assert_eq!(values[2].name, "C");
+    assert_eq!(values[2].platforms, vec!["linux".to_string()]);
+  } else {
+    panic!("enum expected");
+  }
+
+  // different underlying types can't be merged
+  let linux_data = CppData {
+    types: vec![type_data("E", enum_kind(&[("A", 1)], CppBuiltInNumericType::UInt))],
+    ..CppData::default()
+  };
+  assert!(CppData::merge_platforms(vec![("windows".to_string(), windows_data),
+                                        ("linux".to_string(), linux_data)])
+    .is_err());
+}
+
+fn instantiations(class_name: &str, arguments: &[CppBuiltInNumericType], size: i32)
+                  -> CppTemplateInstantiations {
+  CppTemplateInstantiations {
+    class_name: class_name.to_string(),
+    include_file: "types.h".to_string(),
+    instantiations: arguments.iter()
+      .map(|argument| {
+        CppTemplateInstantiation {
+          template_arguments: vec![CppType {
+                                     base: CppTypeBase::BuiltInNumeric(argument.clone()),
+                                     indirection: CppTypeIndirection::None,
+                                     is_const: false,
+                                     is_const2: false,
+                                   }],
+          size: size,
+          alignment: 8,
+          platforms: Vec::new(),
+        }
+      })
+      .collect(),
+  }
+}
+
+#[test]
+fn merge_platforms_template_instantiations() {
+  let windows_data = CppData {
+    template_instantiations: vec![instantiations("V",
+                                                 &[CppBuiltInNumericType::Int,
+                                                   CppBuiltInNumericType::Long],
+                                                 8)],
+    ..CppData::default()
+  };
+  let linux_data = CppData {
+    template_instantiations: vec![instantiations("V", &[CppBuiltInNumericType::Int], 8)],
+    ..CppData::default()
+  };
+  let result = CppData::merge_platforms(vec![("windows".to_string(), windows_data.clone()),
+                                             ("linux".to_string(), linux_data)])
+    .unwrap();
+  assert_eq!(result.template_instantiations.len(), 1);
+  let result_instantiations = &result.template_instantiations[0].instantiations;
+  assert_eq!(result_instantiations.len(), 2);
+  assert!(result_instantiations[0].platforms.is_empty());
+  assert_eq!(result_instantiations[1].platforms, vec!["windows".to_string()]);
+
+  let linux_data = CppData {
+    template_instantiations: vec![instantiations("V", &[CppBuiltInNumericType::Int], 16)],
+    ..CppData::default()
+  };
+  assert!(CppData::merge_platforms(vec![("windows".to_string(), windows_data),
+                                        ("linux".to_string(), linux_data)])
+    .is_err());
+}
+
+#[test]
+fn propagate_platforms() {
+  let mut windows_type = type_data("WinClass", class_kind(8));
+  windows_type.platforms = vec!["windows".to_string()];
+  let mut windows_instantiations = instantiations("V", &[CppBuiltInNumericType::Long], 8);
+  windows_instantiations.instantiations[0].platforms = vec!["windows".to_string()];
+
+  let mut method1 = empty_regular_method();
+  method1.name = "common".to_string();
+  method1.class_membership = Some(empty_membership("WinClass"));
+  let mut method2 = empty_regular_method();
+  method2.name = "linux_only".to_string();
+  method2.class_membership = Some(empty_membership("WinClass"));
+  method2.platforms = vec!["linux".to_string()];
+  let mut method3 = empty_regular_method();
+  method3.name = "instantiation_method".to_string();
+  let mut membership = empty_membership("V");
+  membership.class_type.template_arguments =
+    Some(windows_instantiations.instantiations[0].template_arguments.clone());
+  method3.class_membership = Some(membership);
+  method3.platforms = vec!["windows".to_string(), "linux".to_string()];
+  let mut method4 = empty_regular_method();
+  method4.name = "free_function".to_string();
+
+  let mut data = CppData {
+    types: vec![windows_type],
+    methods: vec![method1, method2, method3, method4],
+    template_instantiations: vec![windows_instantiations],
+    ..CppData::default()
+  };
+  data.propagate_platforms();
+  assert_eq!(data.methods.len(), 3);
+  assert_eq!(data.methods[0].name, "common");
+  assert_eq!(data.methods[0].platforms, vec!["windows".to_string()]);
+  assert_eq!(data.methods[1].name, "instantiation_method");
+  assert_eq!(data.methods[1].platforms, vec!["windows".to_string()]);
+  assert_eq!(data.methods[2].name, "free_function");
+  assert!(data.methods[2].platforms.is_empty());
+}
+
+/// Creates a chain of `depth` classes, each derived from the previous one,
+/// with `methods_count` methods declared in each class. Each class also
+/// redeclares `method0` of its base.
+fn deep_hierarchy(depth: usize, methods_count: usize) -> CppData {
+  let mut data = CppData::default();
+  for i in 0..depth {
+    let name = format!("Class{}", i);
+    let bases = if i == 0 {
+      Vec::new()
+    } else {
+      vec![CppBaseSpecifier {
+             base_type: CppType {
+               base: CppTypeBase::Class(CppTypeClassBase {
+                 name: format!("Class{}", i - 1),
+                 template_arguments: None,
+               }),
+               indirection: CppTypeIndirection::None,
+               is_const: false,
+               is_const2: false,
+             },
+             is_virtual: false,
+             visibility: CppVisibility::Public,
+           }]
+    };
+    data.types.push(CppTypeData {
+      name: name.clone(),
+      include_file: "classes.h".to_string(),
+      origin_location: CppOriginLocation {
+        include_file_path: "classes.h".to_string(),
+        line: 0,
+        column: 0,
+      },
+      kind: CppTypeKind::Class {
+        size: Some(8),
+        alignment: Some(8),
+        bases: bases,
+        fields: Vec::new(),
+        template_arguments: None,
+        using_directives: Vec::new(),
+      },
+      platforms: Vec::new(),
+    });
+    for j in 0..methods_count {
+      let mut method = empty_regular_method();
+      method.name = if j == 0 {
+        "method0".to_string()
+      } else {
+        format!("class{}_method{}", i, j)
+      };
+      let mut membership = empty_membership("");
+      membership.class_type.name = name.clone();
+      method.class_membership = Some(membership);
+      data.methods.push(method);
+    }
+  }
+  data
+}
+
+#[test]
+fn add_inherited_methods() {
+  let mut data = deep_hierarchy(4, 3);
+  data.add_inherited_methods(&[]).unwrap();
+  for i in 0..4 {
+    let class_name = format!("Class{}", i);
+    let methods: Vec<_> = data.methods
+      .iter()
+      .filter(|x| x.class_name() == Some(&class_name))
+      .collect();
+    // own methods and 2 methods of each base; method0 is overridden
+    assert_eq!(methods.len(), 3 + 2 * i);
+    assert_eq!(methods.iter().filter(|x| x.name == "method0").count(), 1);
+    for method in methods {
+      if method.inherited_from.is_some() {
+        assert!(!method.inheritance_chain.is_empty());
+      }
+    }
+  }
+  let method = data.methods
+    .iter()
+    .find(|x| x.class_name() == Some(&"Class3".to_string()) && x.name == "class0_method1")
+    .unwrap();
+  assert_eq!(method.inheritance_chain.len(), 3);
+  assert_eq!(method.inherited_from.as_ref().unwrap().class_type.name, "Class0");
+}
+
+/// Benchmark of `add_inherited_methods` on a deep hierarchy.
+/// Run with `cargo test --release -- --ignored add_inherited_methods_benchmark --nocapture`.
+#[test]
+#[ignore]
+fn add_inherited_methods_benchmark() {
+  let mut data = deep_hierarchy(300, 20);
+  let start = Instant::now();
+  data.add_inherited_methods(&[]).unwrap();
+  let elapsed = start.elapsed();
+  println!("add_inherited_methods: {} methods, {}.{:03} s",
+           data.methods.len(),
+           elapsed.as_secs(),
+           elapsed.subsec_nanos() / 1_000_000);
+  assert_eq!(data.methods.len(), 300 * 20 + 19 * 300 * 299 / 2);
+}
+
+#[test]
+fn skipped_items_not_serialized() {
+  let dir = tempdir::TempDir::new("test_cpp_data").unwrap();
+  let path = dir.path().with_added("cpp_data.json");
+  let data = CppData {
+    skipped_items: vec![SkippedItem::method(&empty_regular_method(),
+                                            SkipStage::FilterRule,
+                                            "blocked")],
+    ..CppData::default()
+  };
+  save_json(&path, &data).unwrap();
+  assert!(!file_to_string(&path).unwrap().contains("skipped_items"));
+  let loaded: CppData = load_json(&path).unwrap();
+  assert!(loaded.skipped_items.is_empty());
+}
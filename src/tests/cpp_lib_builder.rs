@@ -0,0 +1,76 @@
+use cpp_lib_builder::{CppLibBuilder, generator_supports_jobs, user_build_type};
+use launcher::BuildProfile;
+use utils::is_msvc;
+
+use std::path::PathBuf;
+
+#[test]
+fn supports_jobs() {
+  assert!(generator_supports_jobs("Ninja"));
+  assert!(generator_supports_jobs("Unix Makefiles"));
+  assert!(generator_supports_jobs("MinGW Makefiles"));
+  assert!(generator_supports_jobs("NMake Makefiles JOM"));
+  assert!(!generator_supports_jobs("NMake Makefiles"));
+  assert!(!generator_supports_jobs("Visual Studio 14 2015 Win64"));
+  assert!(!generator_supports_jobs("Xcode"));
+}
+
+#[test]
+fn build_type_from_cmake_vars() {
+  assert_eq!(user_build_type(&[("FOO".to_string(), "1".to_string())]), None);
+  assert_eq!(user_build_type(&[("CMAKE_BUILD_TYPE".to_string(), "Debug".to_string()),
+                               ("FOO".to_string(), "1".to_string()),
+                               ("CMAKE_BUILD_TYPE".to_string(), "MinSizeRel".to_string())]),
+             Some(&"MinSizeRel".to_string()));
+}
+
+#[test]
+fn command_args() {
+  let source_dir = PathBuf::from("/tmp/source");
+  let build_dir = PathBuf::from("/tmp/build");
+  let install_dir = PathBuf::from("/tmp/install");
+  let generator = "Ninja".to_string();
+  let nmake_generator = "NMake Makefiles".to_string();
+  let launcher = "ccache".to_string();
+  let mut builder = CppLibBuilder {
+    cmake_source_dir: &source_dir,
+    build_dir: &build_dir,
+    install_dir: &install_dir,
+    num_jobs: 4,
+    linker_env_library_dirs: None,
+    pipe_output: true,
+    cmake_vars: vec![("FOO".to_string(), "bar".to_string())],
+    build_profile: BuildProfile::Debug,
+    generator: Some(&generator),
+    compiler_launcher: Some(&launcher),
+  };
+  let default_build_type = if is_msvc() { "Release" } else { "Debug" };
+  assert_eq!(builder.cmake_args().unwrap(),
+             vec!["/tmp/source".to_string(),
+                  "-DCMAKE_INSTALL_PREFIX=/tmp/install".to_string(),
+                  format!("-DCMAKE_BUILD_TYPE={}", default_build_type),
+                  "-G".to_string(),
+                  "Ninja".to_string(),
+                  "-DCMAKE_CXX_COMPILER_LAUNCHER=ccache".to_string(),
+                  "-DFOO=bar".to_string()]);
+  assert_eq!(builder.build_args(),
+             vec!["--build".to_string(),
+                  ".".to_string(),
+                  "--target".to_string(),
+                  "install".to_string(),
+                  "--config".to_string(),
+                  default_build_type.to_string(),
+                  "--".to_string(),
+                  "-j4".to_string()]);
+
+  // build type set by the user is used for both commands
+  builder.cmake_vars.push(("CMAKE_BUILD_TYPE".to_string(), "RelWithDebInfo".to_string()));
+  let cmake_args = builder.cmake_args().unwrap();
+  assert_eq!(cmake_args[2], "-DCMAKE_BUILD_TYPE=RelWithDebInfo");
+  assert_eq!(cmake_args.last().unwrap(), "-DCMAKE_BUILD_TYPE=RelWithDebInfo");
+  assert_eq!(builder.build_args()[5], "RelWithDebInfo");
+
+  // build tool of the generator doesn't support multiple jobs
+  builder.generator = Some(&nmake_generator);
+  assert!(!builder.build_args().contains(&"--".to_string()));
+}
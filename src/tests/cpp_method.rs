@@ -46,6 +46,8 @@ pub fn empty_regular_method() -> CppMethod {
     arguments_before_omitting: None,
     inherited_from: None,
     inheritance_chain: Vec::new(),
+    platforms: Vec::new(),
+    is_deprecated: false,
     allows_variadic_arguments: false,
     include_file: String::new(),
     origin_location: None,
@@ -232,28 +234,28 @@ fn argument_types_equal8() {
 #[test]
 fn needs_allocation_place_variants() {
   let mut method1 = empty_regular_method();
-  assert!(!method1.needs_allocation_place_variants());
+  assert!(!method1.needs_allocation_place_variants(&[]));
   method1.class_membership = Some(empty_membership("Class1"));
   if let Some(ref mut info) = method1.class_membership {
     info.kind = CppMethodKind::Constructor;
   }
-  assert!(method1.needs_allocation_place_variants());
+  assert!(method1.needs_allocation_place_variants(&[]));
   if let Some(ref mut info) = method1.class_membership {
     info.kind = CppMethodKind::Destructor;
   }
-  assert!(method1.needs_allocation_place_variants());
+  assert!(method1.needs_allocation_place_variants(&[]));
   if let Some(ref mut info) = method1.class_membership {
     info.kind = CppMethodKind::Regular;
   }
   method1.operator = Some(CppOperator::Assignment);
-  assert!(!method1.needs_allocation_place_variants());
+  assert!(!method1.needs_allocation_place_variants(&[]));
   method1.return_type = CppType {
     indirection: CppTypeIndirection::None,
     is_const: false,
     is_const2: false,
     base: CppTypeBase::BuiltInNumeric(CppBuiltInNumericType::Int),
   };
-  assert!(!method1.needs_allocation_place_variants());
+  assert!(!method1.needs_allocation_place_variants(&[]));
   method1.return_type = CppType {
     indirection: CppTypeIndirection::None,
     is_const: false,
@@ -263,7 +265,7 @@ fn needs_allocation_place_variants() {
       template_arguments: None,
     }),
   };
-  assert!(method1.needs_allocation_place_variants());
+  assert!(method1.needs_allocation_place_variants(&[]));
   method1.return_type = CppType {
     indirection: CppTypeIndirection::Ptr,
     is_const: false,
@@ -273,13 +275,13 @@ fn needs_allocation_place_variants() {
       template_arguments: None,
     }),
   };
-  assert!(!method1.needs_allocation_place_variants());
+  assert!(!method1.needs_allocation_place_variants(&[]));
   if let Some(ref mut info) = method1.class_membership {
     info.kind = CppMethodKind::Regular;
   }
   method1.operator = None;
   method1.return_type = CppType::void();
-  assert!(!method1.needs_allocation_place_variants());
+  assert!(!method1.needs_allocation_place_variants(&[]));
 }
 
 #[test]
@@ -292,7 +294,7 @@ fn c_signature_empty() {
   assert!(!method1.is_operator());
   assert_eq!(method1.class_name(), None);
 
-  let r = method1.c_signature(ReturnValueAllocationPlace::NotApplicable).unwrap();
+  let r = method1.c_signature(ReturnValueAllocationPlace::NotApplicable, &[]).unwrap();
   assert!(r.arguments.is_empty());
   assert!(r.return_type.ffi_type.is_void());
 }
@@ -316,7 +318,7 @@ fn c_signature_simple_func() {
     name: "arg1".to_string(),
     has_default_value: false,
   });
-  let r = method1.c_signature(ReturnValueAllocationPlace::NotApplicable).unwrap();
+  let r = method1.c_signature(ReturnValueAllocationPlace::NotApplicable, &[]).unwrap();
   assert!(r.arguments.len() == 1);
   assert_eq!(r.arguments[0].name, "arg1");
   assert_eq!(r.arguments[0].argument_type.ffi_type,
@@ -357,7 +359,7 @@ fn c_signature_method_with_this() {
   assert!(!method1.is_operator());
   assert_eq!(method1.class_name(), Some(&"MyClass".to_string()));
 
-  let r = method1.c_signature(ReturnValueAllocationPlace::NotApplicable).unwrap();
+  let r = method1.c_signature(ReturnValueAllocationPlace::NotApplicable, &[]).unwrap();
   assert!(r.arguments.len() == 2);
   assert_eq!(r.arguments[0].name, "this_ptr");
   assert_eq!(r.arguments[0].argument_type.ffi_type.base,
@@ -403,7 +405,7 @@ fn c_signature_static_method() {
     name: "arg1".to_string(),
     has_default_value: false,
   });
-  let r = method1.c_signature(ReturnValueAllocationPlace::NotApplicable).unwrap();
+  let r = method1.c_signature(ReturnValueAllocationPlace::NotApplicable, &[]).unwrap();
   assert!(r.arguments.len() == 1);
   assert_eq!(r.arguments[0].name, "arg1");
   assert_eq!(r.arguments[0].argument_type.ffi_type,
@@ -439,7 +441,7 @@ fn c_signature_constructor() {
   assert!(!method1.is_operator());
   assert_eq!(method1.class_name(), Some(&"MyClass".to_string()));
 
-  let r_stack = method1.c_signature(ReturnValueAllocationPlace::Stack).unwrap();
+  let r_stack = method1.c_signature(ReturnValueAllocationPlace::Stack, &[]).unwrap();
   assert!(r_stack.arguments.len() == 2);
   assert_eq!(r_stack.arguments[0].name, "arg1");
   assert_eq!(r_stack.arguments[0].argument_type.ffi_type,
@@ -472,7 +474,7 @@ fn c_signature_constructor() {
 
   assert!(r_stack.return_type.ffi_type.is_void());
 
-  let r_heap = method1.c_signature(ReturnValueAllocationPlace::Heap).unwrap();
+  let r_heap = method1.c_signature(ReturnValueAllocationPlace::Heap, &[]).unwrap();
   assert!(r_heap.arguments.len() == 1);
   assert_eq!(r_heap.arguments[0].name, "arg1");
   assert_eq!(r_heap.arguments[0].argument_type.ffi_type,
@@ -514,7 +516,7 @@ fn c_signature_destructor() {
   assert!(!method1.is_operator());
   assert_eq!(method1.class_name(), Some(&"MyClass".to_string()));
 
-  let r_stack = method1.c_signature(ReturnValueAllocationPlace::Stack).unwrap();
+  let r_stack = method1.c_signature(ReturnValueAllocationPlace::Stack, &[]).unwrap();
   assert!(r_stack.arguments.len() == 1);
   assert_eq!(r_stack.arguments[0].name, "this_ptr");
   assert_eq!(&r_stack.arguments[0].argument_type.ffi_type.base,
@@ -527,7 +529,7 @@ fn c_signature_destructor() {
 
   assert!(r_stack.return_type.ffi_type.is_void());
 
-  let r_heap = method1.c_signature(ReturnValueAllocationPlace::Heap).unwrap();
+  let r_heap = method1.c_signature(ReturnValueAllocationPlace::Heap, &[]).unwrap();
   assert!(r_heap.arguments.len() == 1);
   assert_eq!(r_heap.arguments[0].name, "this_ptr");
   assert_eq!(r_heap.arguments[0].argument_type.ffi_type.base,
@@ -567,7 +569,7 @@ fn c_signature_method_returning_class() {
     name: "my_arg".to_string(),
     has_default_value: false,
   });
-  let r_stack = method1.c_signature(ReturnValueAllocationPlace::Stack).unwrap();
+  let r_stack = method1.c_signature(ReturnValueAllocationPlace::Stack, &[]).unwrap();
   assert!(r_stack.arguments.len() == 3);
   assert_eq!(r_stack.arguments[0].name, "this_ptr");
   assert_eq!(&r_stack.arguments[0].argument_type.ffi_type.base,
@@ -606,7 +608,7 @@ fn c_signature_method_returning_class() {
 
   assert!(r_stack.return_type.ffi_type.is_void());
 
-  let r_heap = method1.c_signature(ReturnValueAllocationPlace::Heap).unwrap();
+  let r_heap = method1.c_signature(ReturnValueAllocationPlace::Heap, &[]).unwrap();
   assert!(r_heap.arguments.len() == 2);
   assert_eq!(r_heap.arguments[0].name, "this_ptr");
   assert_eq!(r_heap.arguments[0].argument_type.ffi_type.base,
@@ -649,14 +651,14 @@ fn to_ffi_signatures_destructor() {
     info.kind = CppMethodKind::Destructor;
     info
   });
-  let result = method1.to_ffi_signatures().unwrap();
+  let result = method1.to_ffi_signatures(&[]).unwrap();
   assert_eq!(result.len(), 2);
   assert_eq!(result[0].c_signature,
-             method1.c_signature(ReturnValueAllocationPlace::Heap).unwrap());
+             method1.c_signature(ReturnValueAllocationPlace::Heap, &[]).unwrap());
   assert_eq!(result[0].cpp_method, method1);
   assert_eq!(result[0].allocation_place, ReturnValueAllocationPlace::Heap);
   assert_eq!(result[1].c_signature,
-             method1.c_signature(ReturnValueAllocationPlace::Stack).unwrap());
+             method1.c_signature(ReturnValueAllocationPlace::Stack, &[]).unwrap());
   assert_eq!(result[1].cpp_method, method1);
   assert_eq!(result[1].allocation_place,
              ReturnValueAllocationPlace::Stack);
@@ -681,10 +683,10 @@ fn to_ffi_signatures_simple_func() {
     name: "arg1".to_string(),
     has_default_value: false,
   });
-  let result = method1.to_ffi_signatures().unwrap();
+  let result = method1.to_ffi_signatures(&[]).unwrap();
   assert_eq!(result.len(), 1);
   assert_eq!(result[0].c_signature,
-             method1.c_signature(ReturnValueAllocationPlace::NotApplicable).unwrap());
+             method1.c_signature(ReturnValueAllocationPlace::NotApplicable, &[]).unwrap());
   assert_eq!(result[0].cpp_method, method1);
   assert_eq!(result[0].allocation_place,
              ReturnValueAllocationPlace::NotApplicable);
@@ -772,6 +774,8 @@ fn short_text1() {
     arguments_before_omitting: None,
     inherited_from: None,
     inheritance_chain: Vec::new(),
+    platforms: Vec::new(),
+    is_deprecated: false,
     allows_variadic_arguments: false,
     include_file: String::new(),
     origin_location: None,
@@ -9,11 +9,16 @@ use cpp_data::*;
 use cpp_method::*;
 use cpp_type::*;
 use cpp_operator::CppOperator;
+use coverage_report::SkipStage;
 use file_utils::{create_dir, create_file, PathBufWithAdded};
 
 use std::path::PathBuf;
 
 fn run_parser(code: &'static str) -> CppData {
+  run_parser_with_blacklist(code, Vec::new())
+}
+
+fn run_parser_with_blacklist(code: &'static str, name_blacklist: Vec<String>) -> CppData {
   let dir = tempdir::TempDir::new("test_cpp_parser_run").unwrap();
   let include_dir = dir.path().with_added("include");
   create_dir(&include_dir).unwrap();
@@ -29,7 +34,7 @@ fn run_parser(code: &'static str) -> CppData {
                                      include_directives: vec![PathBuf::from(include_name)],
                                      target_include_paths: Vec::new(),
                                      tmp_cpp_path: dir.path().with_added("1.cpp"),
-                                     name_blacklist: Vec::new(),
+                                     name_blacklist: name_blacklist,
                                      framework_paths: Vec::new(),
                                      flags: Vec::new(),
                                    },
@@ -78,6 +83,8 @@ fn simple_func() {
                arguments_before_omitting: None,
                inherited_from: None,
                inheritance_chain: Vec::new(),
+               platforms: Vec::new(),
+               is_deprecated: false,
                allows_variadic_arguments: false,
                include_file: "myfakelib.h".to_string(),
                origin_location: None,
@@ -117,6 +124,8 @@ fn simple_func_with_default_value() {
                arguments_before_omitting: None,
                inherited_from: None,
                inheritance_chain: Vec::new(),
+               platforms: Vec::new(),
+               is_deprecated: false,
                allows_variadic_arguments: false,
                include_file: "myfakelib.h".to_string(),
                origin_location: None,
@@ -136,11 +145,16 @@ fn functions_with_class_arg() {
   assert_eq!(data.types.len(), 1);
   assert_eq!(data.types[0].name, "Magic");
   if let CppTypeKind::Class { ref size,
+                              ref alignment,
                               ref bases,
                               ref fields,
                               ref template_arguments,
                               ref using_directives } = data.types[0].kind {
-    assert!(size.is_some());
+    // sizes of C++ types are the same as in Rust on the host platform
+    let int_size = ::std::mem::size_of::<::std::os::raw::c_int>() as i32;
+    let int_alignment = ::std::mem::align_of::<::std::os::raw::c_int>() as i32;
+    assert_eq!(size, &Some(2 * int_size));
+    assert_eq!(alignment, &Some(int_alignment));
     assert!(template_arguments.is_none());
     assert!(using_directives.is_empty());
     assert!(bases.is_empty());
@@ -195,6 +209,8 @@ fn functions_with_class_arg() {
                arguments_before_omitting: None,
                inherited_from: None,
                inheritance_chain: Vec::new(),
+               platforms: Vec::new(),
+               is_deprecated: false,
                allows_variadic_arguments: false,
                include_file: "myfakelib.h".to_string(),
                origin_location: None,
@@ -229,6 +245,8 @@ fn functions_with_class_arg() {
                arguments_before_omitting: None,
                inherited_from: None,
                inheritance_chain: Vec::new(),
+               platforms: Vec::new(),
+               is_deprecated: false,
                allows_variadic_arguments: false,
                include_file: "myfakelib.h".to_string(),
                origin_location: None,
@@ -263,6 +281,8 @@ fn functions_with_class_arg() {
                arguments_before_omitting: None,
                inherited_from: None,
                inheritance_chain: Vec::new(),
+               platforms: Vec::new(),
+               is_deprecated: false,
                allows_variadic_arguments: false,
                include_file: "myfakelib.h".to_string(),
                origin_location: None,
@@ -278,6 +298,10 @@ fn func_with_unknown_type() {
   assert!(data.template_instantiations.is_empty());
   assert!(data.types.is_empty());
   assert!(data.methods.is_empty());
+  assert_eq!(data.skipped_items.len(), 1);
+  assert!(!data.skipped_items[0].is_type);
+  assert_eq!(data.skipped_items[0].stage, SkipStage::ParserIntegrityCheck);
+  assert_eq!(data.skipped_items[0].reason, "unknown type: SomeClass");
 }
 
 #[test]
@@ -310,6 +334,8 @@ fn variadic_func() {
                arguments_before_omitting: None,
                inherited_from: None,
                inheritance_chain: Vec::new(),
+               platforms: Vec::new(),
+               is_deprecated: false,
                allows_variadic_arguments: true,
                include_file: "myfakelib.h".to_string(),
                origin_location: None,
@@ -355,6 +381,8 @@ fn free_template_func() {
                arguments_before_omitting: None,
                inherited_from: None,
                inheritance_chain: Vec::new(),
+               platforms: Vec::new(),
+               is_deprecated: false,
                allows_variadic_arguments: false,
                include_file: "myfakelib.h".to_string(),
                origin_location: None,
@@ -418,6 +446,8 @@ fn free_func_operator_sub() {
                  arguments_before_omitting: None,
                  inherited_from: None,
                  inheritance_chain: Vec::new(),
+                 platforms: Vec::new(),
+                 is_deprecated: false,
                  allows_variadic_arguments: false,
                  include_file: "myfakelib.h".to_string(),
                  origin_location: None,
@@ -485,6 +515,8 @@ fn simple_class_method() {
                arguments_before_omitting: None,
                inherited_from: None,
                inheritance_chain: Vec::new(),
+               platforms: Vec::new(),
+               is_deprecated: false,
                allows_variadic_arguments: false,
                include_file: "myfakelib.h".to_string(),
                origin_location: None,
@@ -650,6 +682,8 @@ fn template_class_method() {
                arguments_before_omitting: None,
                inherited_from: None,
                inheritance_chain: Vec::new(),
+               platforms: Vec::new(),
+               is_deprecated: false,
                allows_variadic_arguments: false,
                include_file: "myfakelib.h".to_string(),
                origin_location: None,
@@ -711,17 +745,22 @@ fn simple_enum() {
   };");
   assert_eq!(data.types.len(), 1);
   assert_eq!(data.types[0].name, "Enum1");
-  assert_eq!(data.types[0].kind,
-             CppTypeKind::Enum {
-               values: vec![EnumValue {
-                              name: "Good".to_string(),
-                              value: 0,
-                            },
-                            EnumValue {
-                              name: "Bad".to_string(),
-                              value: 1,
-                            }],
-             });
+  if let CppTypeKind::Enum { ref values, ref is_scoped, .. } = data.types[0].kind {
+    assert_eq!(values,
+               &vec![EnumValue {
+                       name: "Good".to_string(),
+                       value: 0,
+                       platforms: Vec::new(),
+                     },
+                     EnumValue {
+                       name: "Bad".to_string(),
+                       value: 1,
+                       platforms: Vec::new(),
+                     }]);
+    assert!(!is_scoped);
+  } else {
+    panic!("invalid type kind");
+  }
 }
 
 #[test]
@@ -736,20 +775,57 @@ fn simple_enum2() {
   }");
   assert_eq!(data.types.len(), 1);
   assert_eq!(data.types[0].name, "ns1::Enum1");
+  if let CppTypeKind::Enum { ref values, ref is_scoped, .. } = data.types[0].kind {
+    assert_eq!(values,
+               &vec![EnumValue {
+                       name: "Good".to_string(),
+                       value: 1,
+                       platforms: Vec::new(),
+                     },
+                     EnumValue {
+                       name: "Bad".to_string(),
+                       value: 2,
+                       platforms: Vec::new(),
+                     },
+                     EnumValue {
+                       name: "Questionable".to_string(),
+                       value: 3,
+                       platforms: Vec::new(),
+                     }]);
+    assert!(!is_scoped);
+  } else {
+    panic!("invalid type kind");
+  }
+}
+
+#[test]
+fn scoped_enum_with_underlying_type() {
+  let data = run_parser("
+  enum class Enum1 : unsigned char {
+    Good,
+    Bad
+  };");
+  assert_eq!(data.types.len(), 1);
+  assert_eq!(data.types[0].name, "Enum1");
   assert_eq!(data.types[0].kind,
              CppTypeKind::Enum {
                values: vec![EnumValue {
                               name: "Good".to_string(),
-                              value: 1,
+                              value: 0,
+                              platforms: Vec::new(),
                             },
                             EnumValue {
                               name: "Bad".to_string(),
-                              value: 2,
-                            },
-                            EnumValue {
-                              name: "Questionable".to_string(),
-                              value: 3,
+                              value: 1,
+                              platforms: Vec::new(),
                             }],
+               underlying_type: CppType {
+                 indirection: CppTypeIndirection::None,
+                 is_const: false,
+                 is_const2: false,
+                 base: CppTypeBase::BuiltInNumeric(CppBuiltInNumericType::UChar),
+               },
+               is_scoped: true,
              });
 }
 
@@ -1060,3 +1136,21 @@ fn non_type_template_parameter() {
   template<> struct QAtomicOpsSupport<4> { enum { IsSupported = 1 }; };");
   assert!(data.types.is_empty());
 }
+
+#[test]
+fn base_class_not_available() {
+  let data = run_parser_with_blacklist("class Base {};\nclass Derived : public Base {};",
+                                       vec!["Base".to_string()]);
+  assert_eq!(data.types.len(), 1);
+  assert_eq!(data.types[0].name, "Derived");
+  if let CppTypeKind::Class { ref bases, .. } = data.types[0].kind {
+    assert!(bases.is_empty());
+  } else {
+    panic!("invalid type kind");
+  }
+  assert_eq!(data.skipped_items.len(), 1);
+  assert!(data.skipped_items[0].is_type);
+  assert_eq!(data.skipped_items[0].name, "Base");
+  assert_eq!(data.skipped_items[0].class_name, Some("Derived".to_string()));
+  assert_eq!(data.skipped_items[0].stage, SkipStage::ParserIntegrityCheck);
+}
@@ -5,7 +5,7 @@ use cpp_ffi_data::IndirectionChange;
 
 fn assert_type_to_ffi_unchanged(t: &CppType) {
   for role in &[CppTypeRole::NotReturnType, CppTypeRole::ReturnType] {
-    let ffi1 = t.to_cpp_ffi_type(role.clone()).unwrap();
+    let ffi1 = t.to_cpp_ffi_type(role.clone(), &[]).unwrap();
     assert_eq!(&ffi1.original_type, t);
     assert_eq!(&ffi1.ffi_type, t);
     assert_eq!(ffi1.conversion, IndirectionChange::NoChange);
@@ -28,7 +28,7 @@ fn void() {
   assert_eq!(type1.caption(TypeCaptionStrategy::Short).unwrap(), "void");
   assert_eq!(type1.caption(TypeCaptionStrategy::Full).unwrap(), "void");
   assert_type_to_ffi_unchanged(&type1);
-  assert!(!type1.needs_allocation_place_variants());
+  assert!(!type1.needs_allocation_place_variants(&[]));
   assert_eq!(type1.base.maybe_name(), None);
 }
 
@@ -54,7 +54,7 @@ fn void_ptr() {
   assert_eq!(type1.caption(TypeCaptionStrategy::Full).unwrap(),
              "void_ptr");
   assert_type_to_ffi_unchanged(&type1);
-  assert!(!type1.needs_allocation_place_variants());
+  assert!(!type1.needs_allocation_place_variants(&[]));
   assert_eq!(type1.base.maybe_name(), None);
 }
 
@@ -79,7 +79,7 @@ fn int() {
   assert_eq!(type1.caption(TypeCaptionStrategy::Short).unwrap(), "int");
   assert_eq!(type1.caption(TypeCaptionStrategy::Full).unwrap(), "int");
   assert_type_to_ffi_unchanged(&type1);
-  assert!(!type1.needs_allocation_place_variants());
+  assert!(!type1.needs_allocation_place_variants(&[]));
   assert_eq!(type1.base.maybe_name(), None);
 }
 
@@ -105,7 +105,7 @@ fn bool_ptr() {
   assert_eq!(type1.caption(TypeCaptionStrategy::Full).unwrap(),
              "bool_ptr");
   assert_type_to_ffi_unchanged(&type1);
-  assert!(!type1.needs_allocation_place_variants());
+  assert!(!type1.needs_allocation_place_variants(&[]));
   assert_eq!(type1.base.maybe_name(), None);
 }
 
@@ -131,7 +131,7 @@ fn char_ptr_ptr() {
   assert_eq!(type1.caption(TypeCaptionStrategy::Full).unwrap(),
              "char_ptr_ptr");
   assert_type_to_ffi_unchanged(&type1);
-  assert!(!type1.needs_allocation_place_variants());
+  assert!(!type1.needs_allocation_place_variants(&[]));
   assert_eq!(type1.base.maybe_name(), None);
 }
 
@@ -160,7 +160,7 @@ fn qint64() {
   assert_eq!(type1.caption(TypeCaptionStrategy::Short).unwrap(), "qint64");
   assert_eq!(type1.caption(TypeCaptionStrategy::Full).unwrap(), "qint64");
   assert_type_to_ffi_unchanged(&type1);
-  assert!(!type1.needs_allocation_place_variants());
+  assert!(!type1.needs_allocation_place_variants(&[]));
   assert_eq!(type1.base.maybe_name(), Some(&"qint64".to_string()));
 }
 
@@ -190,7 +190,7 @@ fn quintptr() {
   assert_eq!(type1.caption(TypeCaptionStrategy::Full).unwrap(),
              "quintptr");
   assert_type_to_ffi_unchanged(&type1);
-  assert!(!type1.needs_allocation_place_variants());
+  assert!(!type1.needs_allocation_place_variants(&[]));
   assert_eq!(type1.base.maybe_name(), Some(&"quintptr".to_string()));
 }
 
@@ -217,7 +217,7 @@ fn enum1() {
   assert_eq!(type1.caption(TypeCaptionStrategy::Full).unwrap(),
              "Qt_CaseSensitivity");
   assert_type_to_ffi_unchanged(&type1);
-  assert!(!type1.needs_allocation_place_variants());
+  assert!(!type1.needs_allocation_place_variants(&[]));
   assert_eq!(type1.base.maybe_name(),
              Some(&"Qt::CaseSensitivity".to_string()));
 }
@@ -247,7 +247,7 @@ fn class_value() {
   assert_eq!(type1.caption(TypeCaptionStrategy::Short).unwrap(), "QPoint");
   assert_eq!(type1.caption(TypeCaptionStrategy::Full).unwrap(), "QPoint");
 
-  let ffi_return_type = type1.to_cpp_ffi_type(CppTypeRole::ReturnType).unwrap();
+  let ffi_return_type = type1.to_cpp_ffi_type(CppTypeRole::ReturnType, &[]).unwrap();
   assert_eq!(&ffi_return_type.original_type, &type1);
   assert_eq!(&ffi_return_type.ffi_type,
              &CppType {
@@ -264,7 +264,7 @@ fn class_value() {
   assert_eq!(ffi_return_type.conversion,
              IndirectionChange::ValueToPointer);
 
-  let ffi_arg = type1.to_cpp_ffi_type(CppTypeRole::NotReturnType).unwrap();
+  let ffi_arg = type1.to_cpp_ffi_type(CppTypeRole::NotReturnType, &[]).unwrap();
   assert_eq!(&ffi_arg.original_type, &type1);
   assert_eq!(&ffi_arg.ffi_type,
              &CppType {
@@ -279,7 +279,7 @@ fn class_value() {
   assert_eq!(&ffi_arg.ffi_type.to_cpp_code(None).unwrap(),
              "const QPoint*");
   assert_eq!(ffi_arg.conversion, IndirectionChange::ValueToPointer);
-  assert!(type1.needs_allocation_place_variants());
+  assert!(type1.needs_allocation_place_variants(&[]));
   assert_eq!(type1.base.maybe_name(), Some(&"QPoint".to_string()));
 }
 
@@ -309,7 +309,7 @@ fn class_const_ref() {
              "const_QRectF_ref");
 
   for role in &[CppTypeRole::NotReturnType, CppTypeRole::ReturnType] {
-    let ffi1 = type1.to_cpp_ffi_type(role.clone()).unwrap();
+    let ffi1 = type1.to_cpp_ffi_type(role.clone(), &[]).unwrap();
     assert_eq!(&ffi1.original_type, &type1);
     assert_eq!(&ffi1.ffi_type,
                &CppType {
@@ -324,7 +324,7 @@ fn class_const_ref() {
     assert_eq!(&ffi1.ffi_type.to_cpp_code(None).unwrap(), "const QRectF*");
     assert_eq!(ffi1.conversion, IndirectionChange::ReferenceToPointer);
   }
-  assert!(!type1.needs_allocation_place_variants());
+  assert!(!type1.needs_allocation_place_variants(&[]));
   assert_eq!(type1.base.maybe_name(), Some(&"QRectF".to_string()));
 }
 
@@ -354,7 +354,7 @@ fn class_mut_ref() {
              "QRectF_ref");
 
   for role in &[CppTypeRole::NotReturnType, CppTypeRole::ReturnType] {
-    let ffi1 = type1.to_cpp_ffi_type(role.clone()).unwrap();
+    let ffi1 = type1.to_cpp_ffi_type(role.clone(), &[]).unwrap();
     assert_eq!(&ffi1.original_type, &type1);
     assert_eq!(&ffi1.ffi_type,
                &CppType {
@@ -369,7 +369,7 @@ fn class_mut_ref() {
     assert_eq!(&ffi1.ffi_type.to_cpp_code(None).unwrap(), "QRectF*");
     assert_eq!(ffi1.conversion, IndirectionChange::ReferenceToPointer);
   }
-  assert!(!type1.needs_allocation_place_variants());
+  assert!(!type1.needs_allocation_place_variants(&[]));
   assert_eq!(type1.base.maybe_name(), Some(&"QRectF".to_string()));
 }
 
@@ -399,7 +399,7 @@ fn class_mut_ptr() {
   assert_eq!(type1.caption(TypeCaptionStrategy::Full).unwrap(),
              "QObject_ptr");
   assert_type_to_ffi_unchanged(&type1);
-  assert!(!type1.needs_allocation_place_variants());
+  assert!(!type1.needs_allocation_place_variants(&[]));
   assert_eq!(type1.base.maybe_name(), Some(&"QObject".to_string()));
 }
 
@@ -438,7 +438,7 @@ fn class_with_template_args() {
   assert_eq!(type1.caption(TypeCaptionStrategy::Full).unwrap(),
              "QVector_QString");
 
-  let ffi_return_type = type1.to_cpp_ffi_type(CppTypeRole::ReturnType).unwrap();
+  let ffi_return_type = type1.to_cpp_ffi_type(CppTypeRole::ReturnType, &[]).unwrap();
   assert_eq!(&ffi_return_type.original_type, &type1);
   assert_eq!(&ffi_return_type.ffi_type,
              &CppType {
@@ -455,7 +455,7 @@ fn class_with_template_args() {
   assert_eq!(ffi_return_type.conversion,
              IndirectionChange::ValueToPointer);
 
-  let ffi_arg = type1.to_cpp_ffi_type(CppTypeRole::NotReturnType).unwrap();
+  let ffi_arg = type1.to_cpp_ffi_type(CppTypeRole::NotReturnType, &[]).unwrap();
   assert_eq!(&ffi_arg.original_type, &type1);
   assert_eq!(&ffi_arg.ffi_type,
              &CppType {
@@ -470,7 +470,7 @@ fn class_with_template_args() {
   assert_eq!(&ffi_arg.ffi_type.to_cpp_code(None).unwrap(),
              "const QVector< QString >*");
   assert_eq!(ffi_arg.conversion, IndirectionChange::ValueToPointer);
-  assert!(type1.needs_allocation_place_variants());
+  assert!(type1.needs_allocation_place_variants(&[]));
   assert_eq!(type1.base.maybe_name(), Some(&"QVector".to_string()));
 }
 
@@ -553,8 +553,9 @@ fn qflags() {
   assert_eq!(type1.caption(TypeCaptionStrategy::Full).unwrap(),
              "QFlags_Qt_AlignmentFlag");
 
+  let flags_containers = vec!["QFlags".to_string()];
   for role in &[CppTypeRole::NotReturnType, CppTypeRole::ReturnType] {
-    let ffi_type = type1.to_cpp_ffi_type(role.clone()).unwrap();
+    let ffi_type = type1.to_cpp_ffi_type(role.clone(), &flags_containers).unwrap();
     assert_eq!(&ffi_type.original_type, &type1);
     assert_eq!(&ffi_type.ffi_type,
                &CppType {
@@ -566,8 +567,12 @@ fn qflags() {
     assert_eq!(&ffi_type.ffi_type.to_cpp_code(None).unwrap(),
                "unsigned int");
     assert_eq!(ffi_type.conversion, IndirectionChange::QFlagsToUInt);
+
+    let ffi_type2 = type1.to_cpp_ffi_type(role.clone(), &[]).unwrap();
+    assert_eq!(ffi_type2.conversion, IndirectionChange::ValueToPointer);
   }
-  assert!(!type1.needs_allocation_place_variants());
+  assert!(!type1.needs_allocation_place_variants(&flags_containers));
+  assert!(type1.needs_allocation_place_variants(&[]));
   assert_eq!(type1.base.maybe_name(), Some(&"QFlags".to_string()));
 }
 
@@ -594,9 +599,9 @@ fn template_parameter() {
   assert!(type1.base.to_cpp_code(None).is_err());
   assert!(type1.to_cpp_code(Some(&String::new())).is_err());
   assert!(type1.base.to_cpp_code(Some(&String::new())).is_err());
-  assert!(type1.to_cpp_ffi_type(CppTypeRole::NotReturnType).is_err());
-  assert!(type1.to_cpp_ffi_type(CppTypeRole::ReturnType).is_err());
-  assert!(!type1.needs_allocation_place_variants());
+  assert!(type1.to_cpp_ffi_type(CppTypeRole::NotReturnType, &[]).is_err());
+  assert!(type1.to_cpp_ffi_type(CppTypeRole::ReturnType, &[]).is_err());
+  assert!(!type1.needs_allocation_place_variants(&[]));
   assert_eq!(type1.base.maybe_name(), None);
 
   assert!(type1.base.caption(TypeCaptionStrategy::Short).is_err());
@@ -651,7 +656,7 @@ fn function1() {
   assert_eq!(type1.caption(TypeCaptionStrategy::Full).unwrap(),
              "int_func_int_bool_ptr");
   assert_type_to_ffi_unchanged(&type1);
-  assert!(!type1.needs_allocation_place_variants());
+  assert!(!type1.needs_allocation_place_variants(&[]));
   assert_eq!(type1.base.maybe_name(), None);
 }
 
@@ -0,0 +1,23 @@
+use doc_formatter::{safety_doc, default_arguments_doc};
+
+#[test]
+fn safety_doc_test() {
+  assert_eq!(safety_doc(&[]), "");
+  assert_eq!(safety_doc(&["takes raw pointer argument `a` that must be valid or null"
+                            .to_string(),
+                          "returns a raw pointer that is not owned by the caller".to_string()]),
+             "\n\n# Safety\n\nThis function is unsafe because it:\n\n\
+              - takes raw pointer argument `a` that must be valid or null\n\
+              - returns a raw pointer that is not owned by the caller\n");
+}
+
+#[test]
+fn default_arguments_doc_test() {
+  assert_eq!(default_arguments_doc(&[]), "");
+  assert_eq!(default_arguments_doc(&["b".to_string()]),
+             "\n\nArgument `b` has a default value in C++ and can be omitted by passing \
+              `None`.\n");
+  assert!(default_arguments_doc(&["b".to_string(), "c".to_string()])
+    .contains("`b`, `c` have default values in C++. They are passed in `optional_args` as \
+               `Some((b, Some(c)))`."));
+}
@@ -0,0 +1,30 @@
+use file_utils::{PathBufWithAdded, create_dir, create_file, file_to_string, move_files,
+                 files_equal};
+
+use std::fs;
+
+extern crate tempdir;
+
+#[test]
+fn move_files_incremental() {
+  let dir = tempdir::TempDir::new("test_file_utils").unwrap();
+  let src = dir.path().with_added("src");
+  let dst = dir.path().with_added("dst");
+  create_dir(&src).unwrap();
+  create_dir(&dst).unwrap();
+  create_file(src.with_added("same.rs")).unwrap().write("fn same() {}").unwrap();
+  create_file(src.with_added("changed.rs")).unwrap().write("fn new() {}").unwrap();
+  create_file(dst.with_added("same.rs")).unwrap().write("fn same() {}").unwrap();
+  create_file(dst.with_added("changed.rs")).unwrap().write("fn old() {}").unwrap();
+  create_file(dst.with_added("stale.rs")).unwrap().write("fn stale() {}").unwrap();
+  assert!(!files_equal(src.with_added("changed.rs"), dst.with_added("changed.rs")).unwrap());
+  assert!(files_equal(src.with_added("same.rs"), dst.with_added("same.rs")).unwrap());
+
+  let same_modified = fs::metadata(dst.with_added("same.rs")).unwrap().modified().unwrap();
+  move_files(&src, &dst).unwrap();
+  assert!(!src.exists());
+  assert_eq!(fs::metadata(dst.with_added("same.rs")).unwrap().modified().unwrap(),
+             same_modified);
+  assert_eq!(file_to_string(dst.with_added("changed.rs")).unwrap(), "fn new() {}");
+  assert!(!dst.with_added("stale.rs").exists());
+}
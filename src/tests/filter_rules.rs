@@ -0,0 +1,101 @@
+use cpp_data::{CppData, CppVisibility};
+use cpp_type::{CppType, CppTypeBase, CppBuiltInNumericType, CppTypeIndirection};
+use cpp_method::CppFunctionArgument;
+use filter_rules::{FilterRule, FilterRules, FilterAction, method_signature};
+use tests::cpp_method::{empty_regular_method, empty_membership};
+
+#[test]
+fn name_patterns() {
+  let mut method1 = empty_regular_method();
+  method1.name = "tr".to_string();
+  method1.class_membership = Some(empty_membership("QObject"));
+  let mut method2 = empty_regular_method();
+  method2.name = "qt_free".to_string();
+
+  let mut glob_rule = FilterRule::new(FilterAction::Block);
+  glob_rule.name = Some("QObject::*".to_string());
+  let rules = FilterRules::new(vec![glob_rule.clone()]).unwrap();
+  assert_eq!(rules.blocking_method_rule(&method1), Some(&glob_rule));
+  assert_eq!(rules.blocking_method_rule(&method2), None);
+
+  let mut regex_rule = FilterRule::new(FilterAction::Block);
+  regex_rule.name = Some("regex:qt_.+".to_string());
+  let rules = FilterRules::new(vec![regex_rule.clone()]).unwrap();
+  assert_eq!(rules.blocking_method_rule(&method1), None);
+  assert_eq!(rules.blocking_method_rule(&method2), Some(&regex_rule));
+
+  let mut invalid_rule = FilterRule::new(FilterAction::Block);
+  invalid_rule.name = Some("regex:(".to_string());
+  assert!(FilterRules::new(vec![invalid_rule]).is_err());
+}
+
+#[test]
+fn first_matching_rule_wins() {
+  let mut method1 = empty_regular_method();
+  method1.name = "f".to_string();
+  method1.class_membership = Some(empty_membership("A"));
+  method1.is_deprecated = true;
+  let mut method2 = method1.clone();
+  method2.class_membership.as_mut().unwrap().visibility = CppVisibility::Protected;
+
+  let mut allow_rule = FilterRule::new(FilterAction::Allow);
+  allow_rule.access = Some(CppVisibility::Protected);
+  let mut block_rule = FilterRule::new(FilterAction::Block);
+  block_rule.deprecated = Some(true);
+  let rules = FilterRules::new(vec![allow_rule, block_rule.clone()]).unwrap();
+  assert_eq!(rules.blocking_method_rule(&method1), Some(&block_rule));
+  assert_eq!(rules.blocking_method_rule(&method2), None);
+}
+
+#[test]
+fn signature_and_arguments() {
+  let mut method = empty_regular_method();
+  method.name = "f".to_string();
+  method.arguments.push(CppFunctionArgument {
+    name: "x".to_string(),
+    argument_type: CppType {
+      indirection: CppTypeIndirection::Ptr,
+      is_const: false,
+      is_const2: false,
+      base: CppTypeBase::BuiltInNumeric(CppBuiltInNumericType::Int),
+    },
+    has_default_value: false,
+  });
+  assert_eq!(method_signature(&method), "void f(int*)");
+
+  let mut rule = FilterRule::new(FilterAction::Block);
+  rule.argument_type = Some("int*".to_string());
+  let rules = FilterRules::new(vec![rule.clone()]).unwrap();
+  assert!(rules.blocking_method_rule(&method).is_some());
+
+  rule.argument_type = Some("regex:int\\*".to_string());
+  rule.signature = Some("void *(*)".to_string());
+  let rules = FilterRules::new(vec![rule]).unwrap();
+  assert!(rules.blocking_method_rule(&method).is_some());
+}
+
+#[test]
+fn apply_to_methods() {
+  let mut method1 = empty_regular_method();
+  method1.name = "f".to_string();
+  method1.class_membership = Some(empty_membership("Base"));
+  let mut method2 = method1.clone();
+  method2.class_membership = Some(empty_membership("Derived"));
+  let mut data = CppData::default();
+  data.methods.push(method1);
+
+  let mut rule = FilterRule::new(FilterAction::Block);
+  rule.name = Some("Derived::*".to_string());
+  let rules = FilterRules::new(vec![rule]).unwrap();
+  rules.apply(&mut data);
+  assert_eq!(data.methods.len(), 1);
+  assert!(data.skipped_items.is_empty());
+
+  // e.g. a method inherited during post-processing
+  data.methods.push(method2);
+  rules.apply_to_methods(&mut data);
+  assert_eq!(data.methods.len(), 1);
+  assert_eq!(data.methods[0].class_name(), Some(&"Base".to_string()));
+  assert_eq!(data.skipped_items.len(), 1);
+  assert_eq!(data.skipped_items[0].class_name, Some("Derived".to_string()));
+}
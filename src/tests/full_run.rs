@@ -1,6 +1,7 @@
 use file_utils::{PathBufWithAdded, create_dir, create_file};
 use utils::{manifest_dir, run_command, add_env_path_item};
 use cpp_lib_builder::CppLibBuilder;
+use launcher::BuildProfile;
 
 use std::process::Command;
 
@@ -27,6 +28,10 @@ fn build_cpp_lib() -> tempdir::TempDir {
       num_jobs: 1,
       linker_env_library_dirs: None,
       pipe_output: true,
+      cmake_vars: Vec::new(),
+      build_profile: BuildProfile::Debug,
+      generator: None,
+      compiler_launcher: None,
     }
     .run()
     .unwrap_or_else(|e| {
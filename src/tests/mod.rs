@@ -1,9 +1,22 @@
 mod utils;
 mod cpp_type;
 mod cpp_method;
+mod cpp_data;
 mod cpp_ffi_data;
 mod cpp_operator;
 mod cpp_parser;
+mod rust_type;
+mod doc_formatter;
+mod rename_rules;
+mod config_file;
+mod filter_rules;
+mod coverage_report;
+mod build_cache;
+mod file_utils;
+mod cpp_compiler_builder;
+mod cpp_lib_builder;
+mod cpp_code_generator;
+mod rust_code_generator;
 mod full_run;
 
 // Testing plan:
@@ -0,0 +1,64 @@
+use rename_rules::{RenameRules, RenameRule, RenameTarget};
+
+#[test]
+fn prefix_and_suffix() {
+  let rules = RenameRules::new(vec![RenameRule::RemovePrefix {
+                                      target: None,
+                                      prefix: "gtk_".to_string(),
+                                    },
+                                    RenameRule::RemovePrefix {
+                                      target: Some(RenameTarget::Type),
+                                      prefix: "Gst".to_string(),
+                                    },
+                                    RenameRule::RemoveSuffix {
+                                      target: Some(RenameTarget::Method),
+                                      suffix: "KHR".to_string(),
+                                    }])
+    .unwrap();
+  assert_eq!(rules.apply(RenameTarget::Method, "gtk_widget_show"),
+             "widget_show");
+  assert_eq!(rules.apply(RenameTarget::Module, "gtk_widget"), "widget");
+  assert_eq!(rules.apply(RenameTarget::Type, "GstElement"), "Element");
+  assert_eq!(rules.apply(RenameTarget::Method, "GstElement"), "GstElement");
+  assert_eq!(rules.apply(RenameTarget::Method, "vkCreateSwapchainKHR"),
+             "vkCreateSwapchain");
+  assert_eq!(rules.apply(RenameTarget::Type, "Gst"), "Gst");
+  assert_eq!(rules.apply(RenameTarget::Method, "KHR"), "KHR");
+}
+
+#[test]
+fn regex() {
+  let rules = RenameRules::new(vec![RenameRule::Regex {
+                                      target: Some(RenameTarget::Method),
+                                      pattern: "^vk([A-Z])".to_string(),
+                                      replacement: "$1".to_string(),
+                                    }])
+    .unwrap();
+  assert_eq!(rules.apply(RenameTarget::Method, "vkCreateInstance"),
+             "CreateInstance");
+  assert_eq!(rules.apply(RenameTarget::Method, "vkinit"), "vkinit");
+  assert_eq!(rules.apply(RenameTarget::Type, "vkCreateInstance"),
+             "vkCreateInstance");
+
+  assert!(RenameRules::new(vec![RenameRule::Regex {
+                                  target: None,
+                                  pattern: "(".to_string(),
+                                  replacement: String::new(),
+                                }])
+    .is_err());
+}
+
+#[test]
+fn explicit() {
+  let rules = RenameRules::new(vec![RenameRule::Explicit {
+                                      target: RenameTarget::Method,
+                                      cpp_name: "QString::arg".to_string(),
+                                      rust_name: "format_arg".to_string(),
+                                    }])
+    .unwrap();
+  assert_eq!(rules.explicit_name(RenameTarget::Method, "QString::arg"),
+             Some(&"format_arg".to_string()));
+  assert_eq!(rules.explicit_name(RenameTarget::Type, "QString::arg"), None);
+  assert_eq!(rules.explicit_name(RenameTarget::Method, "QString::args"), None);
+  assert_eq!(rules.apply(RenameTarget::Method, "arg"), "arg");
+}
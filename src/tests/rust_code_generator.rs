@@ -0,0 +1,420 @@
+use cpp_ffi_data::{CppAndFfiMethod, CppFfiFunctionSignature, CppFfiFunctionArgument, CppFfiType,
+                   CppFfiArgumentMeaning, IndirectionChange};
+use cpp_method::ReturnValueAllocationPlace;
+use cpp_type::{CppType, CppTypeBase, CppTypeIndirection};
+use file_utils::{PathBufWithAdded, file_to_string, load_toml};
+use rust_code_generator::{self, RustCodeGeneratorConfig, RustCodeGeneratorDependency,
+                          CLibBuildConfig};
+use rust_generator::RustGeneratorOutput;
+use rust_info::{RustModule, RustMethod, RustMethodScope, RustMethodArguments,
+                RustMethodArgumentsVariant, RustMethodArgument, RustTypeDeclaration,
+                RustTypeDeclarationKind, RustTypeWrapperKind};
+use rust_type::{RustName, RustType, RustTypeIndirection, RustFFIFunction, RustFFIArgument,
+                CompleteType, RustToCTypeConversion};
+use tests::cpp_method::empty_regular_method;
+
+extern crate tempdir;
+
+fn void_cpp_type() -> CppType {
+  CppType {
+    base: CppTypeBase::Void,
+    indirection: CppTypeIndirection::None,
+    is_const: false,
+    is_const2: false,
+  }
+}
+
+fn rust_type(parts: &[&str], indirection: RustTypeIndirection, is_const: bool) -> RustType {
+  RustType::Common {
+    base: RustName::new(parts.iter().map(|x| x.to_string()).collect()).unwrap(),
+    generic_arguments: None,
+    is_const: is_const,
+    is_const2: false,
+    indirection: indirection,
+  }
+}
+
+fn complete_type(rust_ffi_type: RustType,
+                 rust_api_type: RustType,
+                 conversion: RustToCTypeConversion)
+                 -> CompleteType {
+  CompleteType {
+    cpp_type: void_cpp_type(),
+    cpp_ffi_type: void_cpp_type(),
+    cpp_to_ffi_conversion: IndirectionChange::NoChange,
+    rust_ffi_type: rust_ffi_type,
+    rust_api_type: rust_api_type,
+    rust_api_to_c_conversion: conversion,
+  }
+}
+
+/// Removes all whitespace, so that the result doesn't depend on rustfmt.
+fn strip_whitespace(text: &str) -> String {
+  text.chars().filter(|x| !x.is_whitespace()).collect()
+}
+
+/// Generates a crate with `-sys` crate containing struct `foo::Foo`
+/// and function `foo::take_foo(&mut Foo) -> *const dep_crate::Bar`.
+#[test]
+fn sys_crate() {
+  let dir = tempdir::TempDir::new("test_rust_code_generator").unwrap();
+  let output_path = dir.path().with_added("my_crate");
+  let dep_path = dir.path().with_added("dep_crate");
+
+  let foo_ptr = rust_type(&["my_crate", "foo", "Foo"], RustTypeIndirection::Ptr, false);
+  let foo_ref = rust_type(&["my_crate", "foo", "Foo"],
+                          RustTypeIndirection::Ref { lifetime: None },
+                          false);
+  let bar_ptr = rust_type(&["dep_crate", "Bar"], RustTypeIndirection::Ptr, true);
+  let mut cpp_method = empty_regular_method();
+  cpp_method.name = "take_foo".to_string();
+  let variant = RustMethodArgumentsVariant {
+    arguments: vec![RustMethodArgument {
+                      argument_type: complete_type(foo_ptr.clone(),
+                                                   foo_ref,
+                                                   RustToCTypeConversion::RefToPtr),
+                      name: "value".to_string(),
+                      ffi_index: Some(0),
+                    }],
+    cpp_method: CppAndFfiMethod {
+      cpp_method: cpp_method,
+      allocation_place: ReturnValueAllocationPlace::NotApplicable,
+      c_signature: CppFfiFunctionSignature {
+        arguments: vec![CppFfiFunctionArgument {
+                          name: "value".to_string(),
+                          argument_type: CppFfiType::void(),
+                          meaning: CppFfiArgumentMeaning::Argument(0),
+                        }],
+        return_type: CppFfiType::void(),
+      },
+      c_name: "my_crate_c_G_take_foo".to_string(),
+    },
+    return_type_ffi_index: None,
+    return_type: complete_type(bar_ptr.clone(), bar_ptr.clone(), RustToCTypeConversion::None),
+    unsafe_reasons: Vec::new(),
+  };
+  let data = RustGeneratorOutput {
+    modules: vec![RustModule {
+                    name: "foo".to_string(),
+                    types: vec![RustTypeDeclaration {
+                                  name: "Foo".to_string(),
+                                  kind: RustTypeDeclarationKind::CppTypeWrapper {
+                                    kind: RustTypeWrapperKind::Struct {
+                                      size: 8,
+                                      alignment: 8,
+                                    },
+                                    cpp_type_name: "Foo".to_string(),
+                                    cpp_template_arguments: None,
+                                    methods: Vec::new(),
+                                    traits: Vec::new(),
+                                  },
+                                  doc: String::new(),
+                                  platforms: Vec::new(),
+                                }],
+                    functions: vec![RustMethod {
+                                      scope: RustMethodScope::Free,
+                                      name: RustName::new(vec!["my_crate".to_string(),
+                                                               "foo".to_string(),
+                                                               "take_foo".to_string()])
+                                        .unwrap(),
+                                      arguments: RustMethodArguments::SingleVariant(variant),
+                                      doc: String::new(),
+                                    }],
+                    submodules: Vec::new(),
+                  }],
+    ffi_functions: vec![("foo.h".to_string(),
+                         vec![RustFFIFunction {
+                                return_type: bar_ptr,
+                                name: "my_crate_c_G_take_foo".to_string(),
+                                arguments: vec![RustFFIArgument {
+                                                  name: "value".to_string(),
+                                                  argument_type: foo_ptr,
+                                                }],
+                                platforms: Vec::new(),
+                              }])],
+    processed_types: Vec::new(),
+    module_features: Vec::new(),
+    skipped_items: Vec::new(),
+  };
+  rust_code_generator::run(RustCodeGeneratorConfig {
+                             crate_name: "my_crate".to_string(),
+                             crate_version: "0.1.0".to_string(),
+                             crate_authors: Vec::new(),
+                             output_path: output_path.clone(),
+                             template_path: dir.path().with_added("template"),
+                             c_lib_name: "my_crate_c".to_string(),
+                             c_lib_is_shared: false,
+                             link_items: Vec::new(),
+                             framework_dirs: Vec::new(),
+                             rustfmt_config_path: None,
+                             dependencies: vec![RustCodeGeneratorDependency {
+                                                  crate_name: "dep_crate".to_string(),
+                                                  crate_path: dep_path.clone(),
+                                                  sys_crate_name: Some("dep_crate_sys"
+                                                    .to_string()),
+                                                }],
+                             flat_module: None,
+                             module_features: false,
+                             platforms: Vec::new(),
+                             sys_crate: true,
+                             c_lib_build: CLibBuildConfig {
+                               generator: Some("Ninja".to_string()),
+                               compiler_launcher: None,
+                               cmake_vars: vec![("FOO".to_string(), "bar".to_string()),
+                                                ("CMAKE_BUILD_TYPE".to_string(),
+                                                 "RelWithDebInfo".to_string())],
+                               linker_env_library_dirs: Vec::new(),
+                             },
+                             num_jobs: 1,
+                           },
+                           &data)
+    .unwrap();
+
+  let sys_path = output_path.with_added("sys");
+  let sys_cargo_toml = load_toml(sys_path.with_added("Cargo.toml")).unwrap();
+  let package = sys_cargo_toml.get("package").unwrap();
+  assert_eq!(package.lookup("name").unwrap().as_str(), Some("my_crate_sys"));
+  assert_eq!(package.lookup("links").unwrap().as_str(), Some("my_crate_c"));
+  let dependencies = sys_cargo_toml.get("dependencies").unwrap();
+  assert_eq!(dependencies.lookup("dep_crate_sys.path").unwrap().as_str(),
+             dep_path.with_added("sys").to_str());
+  assert!(dependencies.lookup("dep_crate").is_none());
+  // the C wrapper library is built by the -sys crate
+  let sys_build_rs = file_to_string(sys_path.with_added("build.rs")).unwrap();
+  assert!(sys_build_rs.contains("  cmake_command.arg(\"-G\").arg(\"Ninja\");\n"));
+  assert!(sys_build_rs.contains("  cmake_command.arg(\"-DFOO=bar\");\n"));
+  assert!(sys_build_rs.contains("make_command.arg(\"--\").arg(format!(\"-j{}\""));
+  // build type set by the user is used instead of the profile's
+  assert!(sys_build_rs.contains("  let build_type = \"RelWithDebInfo\";\n"));
+  assert!(!output_path.with_added("build.rs").exists());
+
+  let sys_lib = file_to_string(sys_path.with_added("src").with_added("lib.rs")).unwrap();
+  assert!(sys_lib.starts_with("pub extern crate libc;\npub extern crate dep_crate_sys;\n"));
+  assert!(sys_lib.contains("pub mod foo {\n"));
+  assert!(sys_lib.contains("pub struct Foo"));
+  // types of dependencies are replaced with types of their -sys crates
+  assert!(sys_lib.contains("  pub fn my_crate_c_G_take_foo(value: *mut ::foo::Foo) -> *const \
+                            dep_crate_sys::Bar;\n"));
+
+  let cargo_toml = load_toml(output_path.with_added("Cargo.toml")).unwrap();
+  assert!(cargo_toml.get("package").unwrap().lookup("build").is_none());
+  assert_eq!(cargo_toml.get("dependencies").unwrap().lookup("my_crate_sys.path").unwrap().as_str(),
+             Some("sys"));
+  let lib = file_to_string(output_path.with_added("src").with_added("lib.rs")).unwrap();
+  assert!(lib.contains("extern crate my_crate_sys as ffi;"));
+  assert!(!output_path.with_added("src").with_added("ffi.rs").exists());
+
+  // pointers to the crate's types are cast to the -sys crate's types and back
+  let foo_module = file_to_string(output_path.with_added("src").with_added("foo.rs")).unwrap();
+  assert!(strip_whitespace(&foo_module)
+    .contains("::ffi::my_crate_c_G_take_foo((valueas*mut::foo::Foo)as_)as*constdep_crate::Bar"));
+}
+
+/// Creates a variant of free function `c_name` taking `arguments` by value.
+fn free_function_variant(c_name: &str,
+                         arguments: &[(&str, RustType)])
+                         -> RustMethodArgumentsVariant {
+  let mut cpp_method = empty_regular_method();
+  cpp_method.name = "set".to_string();
+  RustMethodArgumentsVariant {
+    arguments: arguments.iter()
+      .enumerate()
+      .map(|(index, &(name, ref argument_type))| {
+        RustMethodArgument {
+          argument_type: complete_type(argument_type.clone(),
+                                       argument_type.clone(),
+                                       RustToCTypeConversion::None),
+          name: name.to_string(),
+          ffi_index: Some(index as i32),
+        }
+      })
+      .collect(),
+    cpp_method: CppAndFfiMethod {
+      cpp_method: cpp_method,
+      allocation_place: ReturnValueAllocationPlace::NotApplicable,
+      c_signature: CppFfiFunctionSignature {
+        arguments: arguments.iter()
+          .enumerate()
+          .map(|(index, &(name, _))| {
+            CppFfiFunctionArgument {
+              name: name.to_string(),
+              argument_type: CppFfiType::void(),
+              meaning: CppFfiArgumentMeaning::Argument(index as i8),
+            }
+          })
+          .collect(),
+        return_type: CppFfiType::void(),
+      },
+      c_name: c_name.to_string(),
+    },
+    return_type_ffi_index: None,
+    return_type: complete_type(RustType::Void, RustType::Void, RustToCTypeConversion::None),
+    unsafe_reasons: Vec::new(),
+  }
+}
+
+/// Generates function `foo::set(a: i32, b: i32 = 0, c: bool = false)`
+/// that exposes arguments with default values as nested `Option`s.
+#[test]
+fn default_arguments() {
+  let dir = tempdir::TempDir::new("test_rust_code_generator").unwrap();
+  let output_path = dir.path().with_added("my_crate");
+  let int = rust_type(&["i32"], RustTypeIndirection::None, false);
+  let bool_type = rust_type(&["bool"], RustTypeIndirection::None, false);
+  let variants = vec![free_function_variant("my_crate_c_G_set", &[("a", int.clone())]),
+                      free_function_variant("my_crate_c_G_set1",
+                                            &[("a", int.clone()), ("b", int.clone())]),
+                      free_function_variant("my_crate_c_G_set2",
+                                            &[("a", int.clone()),
+                                              ("b", int.clone()),
+                                              ("c", bool_type)])];
+  let data = RustGeneratorOutput {
+    modules: vec![RustModule {
+                    name: "foo".to_string(),
+                    types: Vec::new(),
+                    functions: vec![RustMethod {
+                                      scope: RustMethodScope::Free,
+                                      name: RustName::new(vec!["my_crate".to_string(),
+                                                               "foo".to_string(),
+                                                               "set".to_string()])
+                                        .unwrap(),
+                                      arguments:
+                                        RustMethodArguments::DefaultArguments(variants),
+                                      doc: String::new(),
+                                    }],
+                    submodules: Vec::new(),
+                  }],
+    ffi_functions: Vec::new(),
+    processed_types: Vec::new(),
+    module_features: Vec::new(),
+    skipped_items: Vec::new(),
+  };
+  rust_code_generator::run(RustCodeGeneratorConfig {
+                             crate_name: "my_crate".to_string(),
+                             crate_version: "0.1.0".to_string(),
+                             crate_authors: Vec::new(),
+                             output_path: output_path.clone(),
+                             template_path: dir.path().with_added("template"),
+                             c_lib_name: "my_crate_c".to_string(),
+                             c_lib_is_shared: false,
+                             link_items: Vec::new(),
+                             framework_dirs: Vec::new(),
+                             rustfmt_config_path: None,
+                             dependencies: Vec::new(),
+                             flat_module: None,
+                             module_features: false,
+                             platforms: Vec::new(),
+                             sys_crate: false,
+                             c_lib_build: CLibBuildConfig::default(),
+                             num_jobs: 1,
+                           },
+                           &data)
+    .unwrap();
+  let foo_module = strip_whitespace(&file_to_string(output_path.with_added("src")
+      .with_added("foo.rs"))
+    .unwrap());
+  assert!(foo_module.contains("pubfnset(a:i32,optional_args:Option<(i32,Option<bool>)>){"));
+  assert!(foo_module.contains("matchoptional_args{\
+                               None=>{unsafe{::ffi::my_crate_c_G_set(a)}}\
+                               Some((b,None))=>{unsafe{::ffi::my_crate_c_G_set1(a,b)}}\
+                               Some((b,Some(c)))=>{unsafe{::ffi::my_crate_c_G_set2(a,b,c)}}\
+                               }"));
+  assert!(!foo_module.contains("panic!"));
+}
+
+/// Generates struct `foo::Foo` of size 16 and alignment 8 and
+/// function `foo::new_foo() -> Foo` that returns it by value.
+#[test]
+fn value_type() {
+  let dir = tempdir::TempDir::new("test_rust_code_generator").unwrap();
+  let output_path = dir.path().with_added("my_crate");
+  let foo = rust_type(&["my_crate", "foo", "Foo"], RustTypeIndirection::None, false);
+  let foo_ptr = rust_type(&["my_crate", "foo", "Foo"], RustTypeIndirection::Ptr, false);
+  let mut cpp_method = empty_regular_method();
+  cpp_method.name = "new_foo".to_string();
+  let variant = RustMethodArgumentsVariant {
+    arguments: Vec::new(),
+    cpp_method: CppAndFfiMethod {
+      cpp_method: cpp_method,
+      allocation_place: ReturnValueAllocationPlace::Stack,
+      c_signature: CppFfiFunctionSignature {
+        arguments: vec![CppFfiFunctionArgument {
+                          name: "output".to_string(),
+                          argument_type: CppFfiType::void(),
+                          meaning: CppFfiArgumentMeaning::ReturnValue,
+                        }],
+        return_type: CppFfiType::void(),
+      },
+      c_name: "my_crate_c_G_new_foo_to_output".to_string(),
+    },
+    return_type_ffi_index: Some(0),
+    return_type: complete_type(foo_ptr, foo, RustToCTypeConversion::ValueToPtr),
+    unsafe_reasons: Vec::new(),
+  };
+  let data = RustGeneratorOutput {
+    modules: vec![RustModule {
+                    name: "foo".to_string(),
+                    types: vec![RustTypeDeclaration {
+                                  name: "Foo".to_string(),
+                                  kind: RustTypeDeclarationKind::CppTypeWrapper {
+                                    kind: RustTypeWrapperKind::Struct {
+                                      size: 16,
+                                      alignment: 8,
+                                    },
+                                    cpp_type_name: "Foo".to_string(),
+                                    cpp_template_arguments: None,
+                                    methods: Vec::new(),
+                                    traits: Vec::new(),
+                                  },
+                                  doc: String::new(),
+                                  platforms: Vec::new(),
+                                }],
+                    functions: vec![RustMethod {
+                                      scope: RustMethodScope::Free,
+                                      name: RustName::new(vec!["my_crate".to_string(),
+                                                               "foo".to_string(),
+                                                               "new_foo".to_string()])
+                                        .unwrap(),
+                                      arguments: RustMethodArguments::SingleVariant(variant),
+                                      doc: String::new(),
+                                    }],
+                    submodules: Vec::new(),
+                  }],
+    ffi_functions: Vec::new(),
+    processed_types: Vec::new(),
+    module_features: Vec::new(),
+    skipped_items: Vec::new(),
+  };
+  rust_code_generator::run(RustCodeGeneratorConfig {
+                             crate_name: "my_crate".to_string(),
+                             crate_version: "0.1.0".to_string(),
+                             crate_authors: Vec::new(),
+                             output_path: output_path.clone(),
+                             template_path: dir.path().with_added("template"),
+                             c_lib_name: "my_crate_c".to_string(),
+                             c_lib_is_shared: false,
+                             link_items: Vec::new(),
+                             framework_dirs: Vec::new(),
+                             rustfmt_config_path: None,
+                             dependencies: Vec::new(),
+                             flat_module: None,
+                             module_features: false,
+                             platforms: Vec::new(),
+                             sys_crate: false,
+                             c_lib_build: CLibBuildConfig::default(),
+                             num_jobs: 1,
+                           },
+                           &data)
+    .unwrap();
+  let foo_module = strip_whitespace(&file_to_string(output_path.with_added("src")
+      .with_added("foo.rs"))
+    .unwrap());
+  // the buffer has the size and alignment of the C++ class
+  assert!(foo_module.contains("#[repr(C,align(8))]pubstructFoo{_buffer:[u8;16],}"));
+  // the C++ wrapper constructs the object in uninitialized memory
+  assert!(foo_module.contains("pubfnnew_foo()->::foo::Foo{\
+                               {letmutobject=::std::mem::MaybeUninit::<::foo::Foo>::uninit();\
+                               unsafe{::ffi::my_crate_c_G_new_foo_to_output(object.as_mut_ptr())}\
+                               unsafe{object.assume_init()}}}"));
+  assert!(!foo_module.contains("uninitialized()"));
+}
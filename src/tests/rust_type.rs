@@ -0,0 +1,73 @@
+use rust_type::{RustName, RustType, RustTypeIndirection};
+
+fn class_ref(lifetime: Option<String>) -> RustType {
+  RustType::Common {
+    base: RustName::new(vec!["qt_core".to_string(), "object".to_string(), "Object".to_string()])
+      .unwrap(),
+    generic_arguments: None,
+    is_const: true,
+    is_const2: false,
+    indirection: RustTypeIndirection::Ref { lifetime: lifetime },
+  }
+}
+
+fn option_of(t: RustType) -> RustType {
+  RustType::Common {
+    base: RustName::new(vec!["Option".to_string()]).unwrap(),
+    generic_arguments: Some(vec![t]),
+    is_const: false,
+    is_const2: false,
+    indirection: RustTypeIndirection::None,
+  }
+}
+
+#[test]
+fn option_ref() {
+  let type1 = option_of(class_ref(None));
+  assert!(type1.is_option());
+  assert!(type1.is_ref());
+  assert!(type1.lifetimes().is_empty());
+  let type2 = type1.with_lifetime("l0".to_string());
+  assert_eq!(type2, option_of(class_ref(Some("l0".to_string()))));
+  assert_eq!(type2.lifetimes(), vec![&"l0".to_string()]);
+  assert!(!class_ref(None).is_option());
+}
+
+#[test]
+fn tuple_lifetimes() {
+  let type1 = option_of(RustType::Tuple(vec![class_ref(Some("l0".to_string())),
+                                             option_of(class_ref(Some("l1".to_string())))]));
+  assert_eq!(type1.lifetimes(), vec![&"l0".to_string(), &"l1".to_string()]);
+  assert_eq!(type1.caption().unwrap(), "option_object_ref_option_object_ref");
+}
+
+#[test]
+fn referenced_names() {
+  let type1 = option_of(class_ref(None));
+  assert_eq!(type1.referenced_names(),
+             vec![&RustName::new(vec!["Option".to_string()]).unwrap(),
+                  &RustName::new(vec!["qt_core".to_string(),
+                                      "object".to_string(),
+                                      "Object".to_string()])
+                    .unwrap()]);
+  let type2 = RustType::FunctionPointer {
+    return_type: Box::new(RustType::Void),
+    arguments: vec![class_ref(None)],
+  };
+  assert_eq!(type2.referenced_names().len(), 1);
+  assert!(RustType::Void.referenced_names().is_empty());
+}
+
+#[test]
+fn is_pointer() {
+  assert!(!class_ref(None).is_pointer());
+  assert!(!RustType::Void.is_pointer());
+  let ptr = RustType::Common {
+    base: RustName::new(vec!["libc".to_string(), "c_void".to_string()]).unwrap(),
+    generic_arguments: None,
+    is_const: false,
+    is_const2: false,
+    indirection: RustTypeIndirection::Ptr,
+  };
+  assert!(ptr.is_pointer());
+}
@@ -100,3 +100,13 @@ fn case_operations() {
   assert_eq!(s8.to_snake_case(), "opengl_functions");
 
 }
+
+#[test]
+fn parallel_map_order() {
+  use utils::parallel_map;
+  let items: Vec<_> = (0..100).collect();
+  let expected: Vec<_> = items.iter().map(|x| x * 2).collect();
+  assert_eq!(parallel_map(&items, 1, |x| x * 2), expected);
+  assert_eq!(parallel_map(&items, 4, |x| x * 2), expected);
+  assert_eq!(parallel_map(&items[..0], 4, |x| x * 2), Vec::<i32>::new());
+}
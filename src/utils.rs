@@ -6,6 +6,10 @@ use std::path::PathBuf;
 use std::collections::HashMap;
 use std::hash::Hash;
 use std::process::Command;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+extern crate crossbeam;
 
 #[cfg(all(windows, target_env = "msvc"))]
 pub fn is_msvc() -> bool {
@@ -31,6 +35,41 @@ pub fn add_to_multihash<K: Eq + Hash + Clone, T, V: Default + Extend<T>>(hash: &
   }
 }
 
+/// Applies `f` to all `items` using up to `num_jobs` threads.
+/// Results are returned in the order of `items` regardless of
+/// the order in which they were computed.
+pub fn parallel_map<T, R, F>(items: &[T], num_jobs: usize, f: F) -> Vec<R>
+  where T: Sync,
+        R: Send,
+        F: Fn(&T) -> R + Sync
+{
+  if num_jobs <= 1 || items.len() <= 1 {
+    return items.iter().map(|x| f(x)).collect();
+  }
+  let next_index = AtomicUsize::new(0);
+  let results = Mutex::new(Vec::with_capacity(items.len()));
+  crossbeam::scope(|scope| {
+    for _ in 0..std::cmp::min(num_jobs, items.len()) {
+      scope.spawn(|| {
+        loop {
+          let index = next_index.fetch_add(1, Ordering::SeqCst);
+          if index >= items.len() {
+            break;
+          }
+          let result = f(&items[index]);
+          results.lock().unwrap().push((index, result));
+        }
+      });
+    }
+  });
+  let mut results = results.into_inner().unwrap();
+  if results.len() != items.len() {
+    panic!("parallel_map: worker thread panicked");
+  }
+  results.sort_by(|a, b| a.0.cmp(&b.0));
+  results.into_iter().map(|x| x.1).collect()
+}
+
 /// Runs a command, checks that it is successful, and
 /// returns its output if requested
 pub fn run_command(command: &mut Command, fetch_stdout: bool, pipe_output: bool) -> Result<String> {
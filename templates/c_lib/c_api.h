@@ -0,0 +1,24 @@
+#ifndef {lib_name_uppercase}_C_API_H
+#define {lib_name_uppercase}_C_API_H
+
+// Plain C interface of the {lib_name_lowercase} library.
+// This header should not be included together with other
+// headers of the library.
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#include "{lib_name_lowercase}_exports.h"
+
+#ifdef __cplusplus
+extern "C" {{
+#endif
+
+{types_code}
+{functions_code}
+#ifdef __cplusplus
+}} // extern "C"
+#endif
+
+#endif // {lib_name_uppercase}_C_API_H
@@ -0,0 +1,50 @@
+use std::env;
+use std::path::PathBuf;
+use std::process::Command;
+
+fn run(command: &mut Command) {{
+  let status = command.status()
+    .unwrap_or_else(|err| panic!("failed to run {{:?}}: {{}}", command, err));
+  if !status.success() {{
+    panic!("command failed: {{:?}}", command);
+  }}
+}}
+
+/// Builds the C wrapper library with CMake and links to it.
+fn main() {{
+  let manifest_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+  let source_dir = manifest_dir.join("..").join("c_lib").join("source");
+  let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+  let build_dir = out_dir.join("c_lib_build");
+  let install_dir = out_dir.join("c_lib_install");
+  std::fs::create_dir_all(&build_dir).unwrap();
+  // unused if both the generator and the build type are set in the config
+  #[allow(unused_variables)]
+  let is_msvc = env::var("TARGET").unwrap().contains("msvc");
+{build_type}
+  let mut cmake_command = Command::new("cmake");
+  cmake_command.arg(&source_dir)
+    .arg(format!("-DCMAKE_INSTALL_PREFIX={{}}", install_dir.display()))
+    .arg(format!("-DCMAKE_BUILD_TYPE={{}}", build_type))
+    .current_dir(&build_dir);
+{cmake_args}  run(&mut cmake_command);
+
+  let mut make_command = Command::new("cmake");
+  make_command.arg("--build")
+    .arg(".")
+    .arg("--target")
+    .arg("install")
+    .arg("--config")
+    .arg(build_type)
+    .current_dir(&build_dir);
+{make_args}  run(&mut make_command);
+
+  println!("cargo:rerun-if-changed={{}}", source_dir.join("CMakeLists.txt").display());
+  for dir in &["src", "include"] {{
+    for entry in std::fs::read_dir(source_dir.join(dir)).unwrap() {{
+      println!("cargo:rerun-if-changed={{}}", entry.unwrap().path().display());
+    }}
+  }}
+  println!("cargo:rustc-link-search={{}}", install_dir.join("lib").display());
+{extra}
+}}
@@ -0,0 +1,112 @@
+use libc;
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::{BitAnd, BitOr, BitXor, Not};
+
+/// An enum whose values can be combined into `Flags`.
+pub trait FlaggableEnum: Sized {
+  /// Returns the integer value of this enum variant.
+  fn to_flag_value(self) -> libc::c_int;
+  /// Returns the name of the enum type.
+  fn enum_name() -> &'static str;
+}
+
+/// A combination of values of a flaggable enum
+/// (C++ type: `QFlags<T>` or another flags container).
+pub struct Flags<T: FlaggableEnum> {
+  value: libc::c_int,
+  _phantom_data: PhantomData<T>,
+}
+
+impl<T: FlaggableEnum> Flags<T> {
+  /// Creates flags from a raw integer value.
+  pub fn from_int(value: libc::c_int) -> Flags<T> {
+    Flags {
+      value: value,
+      _phantom_data: PhantomData,
+    }
+  }
+
+  /// Creates flags containing a single enum value.
+  pub fn from_enum(value: T) -> Flags<T> {
+    Flags::from_int(value.to_flag_value())
+  }
+
+  /// Returns the raw integer value.
+  pub fn to_int(&self) -> libc::c_int {
+    self.value
+  }
+
+  /// Returns true if all bits of `flag` are set.
+  pub fn test_flag(&self, flag: T) -> bool {
+    let value = flag.to_flag_value();
+    self.value & value == value
+  }
+
+  /// Returns true if no flags are set.
+  pub fn is_empty(&self) -> bool {
+    self.value == 0
+  }
+}
+
+impl<T: FlaggableEnum> Clone for Flags<T> {
+  fn clone(&self) -> Flags<T> {
+    Flags::from_int(self.value)
+  }
+}
+
+impl<T: FlaggableEnum> Copy for Flags<T> {}
+
+impl<T: FlaggableEnum> PartialEq for Flags<T> {
+  fn eq(&self, other: &Flags<T>) -> bool {
+    self.value == other.value
+  }
+}
+
+impl<T: FlaggableEnum> Eq for Flags<T> {}
+
+impl<T: FlaggableEnum> Default for Flags<T> {
+  fn default() -> Flags<T> {
+    Flags::from_int(0)
+  }
+}
+
+impl<T: FlaggableEnum> From<T> for Flags<T> {
+  fn from(value: T) -> Flags<T> {
+    Flags::from_enum(value)
+  }
+}
+
+impl<T: FlaggableEnum, U: Into<Flags<T>>> BitOr<U> for Flags<T> {
+  type Output = Flags<T>;
+  fn bitor(self, rhs: U) -> Flags<T> {
+    Flags::from_int(self.value | rhs.into().value)
+  }
+}
+
+impl<T: FlaggableEnum, U: Into<Flags<T>>> BitAnd<U> for Flags<T> {
+  type Output = Flags<T>;
+  fn bitand(self, rhs: U) -> Flags<T> {
+    Flags::from_int(self.value & rhs.into().value)
+  }
+}
+
+impl<T: FlaggableEnum, U: Into<Flags<T>>> BitXor<U> for Flags<T> {
+  type Output = Flags<T>;
+  fn bitxor(self, rhs: U) -> Flags<T> {
+    Flags::from_int(self.value ^ rhs.into().value)
+  }
+}
+
+impl<T: FlaggableEnum> Not for Flags<T> {
+  type Output = Flags<T>;
+  fn not(self) -> Flags<T> {
+    Flags::from_int(!self.value)
+  }
+}
+
+impl<T: FlaggableEnum> fmt::Debug for Flags<T> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "Flags<{}>({:#x})", T::enum_name(), self.value)
+  }
+}